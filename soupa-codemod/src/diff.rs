@@ -0,0 +1,144 @@
+//! Minimal unified diffs, used to preview rewrites.
+
+use std::fmt::Write;
+
+/// Number of unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Produces a unified diff between two versions of the source at `path`, or an
+/// empty string if they are the same.
+pub fn unified(path: &str, old: &str, new: &str) -> String {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+
+    let lines = lines(&old, &new);
+
+    if lines.iter().all(|line| matches!(line, Line::Same(_))) {
+        return String::new();
+    }
+
+    let mut output = format!("--- a/{path}\n+++ b/{path}\n");
+
+    // Group changes which are close enough together to share context
+    let changed = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Same(_)))
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+
+    let mut hunks = Vec::<(usize, usize)>::new();
+    for index in changed {
+        let start = index.saturating_sub(CONTEXT);
+        let end = (index + CONTEXT + 1).min(lines.len());
+
+        match hunks.last_mut() {
+            Some(hunk) if start <= hunk.1 => hunk.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    for (start, end) in hunks {
+        let (mut old_start, mut new_start) = (1, 1);
+        for line in &lines[..start] {
+            match line {
+                Line::Same(_) => {
+                    old_start += 1;
+                    new_start += 1;
+                }
+                Line::Removed(_) => old_start += 1,
+                Line::Added(_) => new_start += 1,
+            }
+        }
+
+        let hunk = &lines[start..end];
+        let old_len = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Added(_)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Removed(_)))
+            .count();
+
+        let _ = writeln!(
+            output,
+            "@@ -{old_start},{old_len} +{new_start},{new_len} @@"
+        );
+
+        for line in hunk {
+            let _ = match line {
+                Line::Same(line) => writeln!(output, " {line}"),
+                Line::Removed(line) => writeln!(output, "-{line}"),
+                Line::Added(line) => writeln!(output, "+{line}"),
+            };
+        }
+    }
+
+    output
+}
+
+/// Diffs two sequences of lines using their longest common subsequence.
+fn lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    // Skip the common prefix and suffix, which is most of a source file
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut lengths = vec![vec![0usize; new_mid.len() + 1]; old_mid.len() + 1];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            lengths[i][j] = if old_mid[i] == new_mid[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut output = old[..prefix]
+        .iter()
+        .map(|line| Line::Same(line))
+        .collect::<Vec<_>>();
+
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+            output.push(Line::Same(old_mid[i]));
+            i += 1;
+            j += 1;
+        } else if i < old_mid.len()
+            && (j == new_mid.len() || lengths[i + 1][j] >= lengths[i][j + 1])
+        {
+            output.push(Line::Removed(old_mid[i]));
+            i += 1;
+        } else {
+            output.push(Line::Added(new_mid[j]));
+            j += 1;
+        }
+    }
+
+    output.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| Line::Same(line)),
+    );
+    output
+}
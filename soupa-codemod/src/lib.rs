@@ -0,0 +1,645 @@
+//! Rewrites hand-written pre-clone blocks into `soupa!` form.
+//!
+//! A block of the form `{ let x = <expr>; ...; move || ... }`, where each
+//! binding is only used within the closure, is rewritten into
+//! `soupa!(move || ... super { <expr> } ...)`.
+//! The same applies to `async move { ... }` blocks and `async move` closures.
+//!
+//! A binding used once is replaced by an anonymous super block.
+//! A binding used several times becomes a labelled super block at its first use,
+//! `super(x) { <expr> }`, with later uses referring to the label.
+//!
+//! Blocks are only rewritten when doing so can't change their meaning, so a
+//! block is skipped if, for example, a binding is shadowed within the closure, or
+//! lifting the bindings would change the order they are evaluated in.
+//! Only the block itself is edited, so comments within the closure are kept.
+//! `use soupa::soupa;` is added to each module with a rewritten block, including
+//! inline modules, unless the macro is already imported there.
+//!
+//! # Examples
+//!
+//! ```rust
+//! let rewritten = soupa_codemod::rewrite(
+//!     "\
+//! use soupa::soupa;
+//!
+//! fn main() {
+//!     let func = {
+//!         let foo = foo.clone();
+//!         move || {
+//!             work(foo)
+//!         }
+//!     };
+//! }
+//! ",
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(
+//!     rewritten.source,
+//!     "\
+//! use soupa::soupa;
+//!
+//! fn main() {
+//!     let func = soupa!(move || {
+//!         work(super { foo.clone() })
+//!     });
+//! }
+//! ",
+//! );
+//! ```
+
+use std::{error, fmt, ops::Range};
+
+pub mod diff;
+
+use proc_macro2::{TokenStream, TokenTree};
+use serde::Serialize;
+use syn::{spanned::Spanned, visit::Visit};
+
+/// Rewrites all pre-clone blocks within `source`.
+///
+/// # Errors
+///
+/// Fails if `source` could not be parsed.
+pub fn rewrite(source: &str) -> Result<Rewritten, Error> {
+    let file = syn::parse_file(source)?;
+
+    let mut finder = Finder {
+        source,
+        changes: Vec::new(),
+        edits: Vec::new(),
+        macro_use: macro_use(&file.items),
+        rewritten: vec![false],
+    };
+    finder.visit_file(&file);
+
+    if finder.rewritten.pop() == Some(true) {
+        finder.import(&file.items);
+    }
+
+    let Finder {
+        mut changes, edits, ..
+    } = finder;
+
+    changes.sort_by_key(|change| (change.line, change.column));
+
+    Ok(Rewritten {
+        source: apply(source, edits),
+        changes,
+    })
+}
+
+/// The result of rewriting a source file.
+#[derive(Clone, Debug)]
+pub struct Rewritten {
+    /// The rewritten source.
+    pub source: String,
+    /// Every pre-clone block which was found, whether it was rewritten or not.
+    pub changes: Vec<Change>,
+}
+
+/// A pre-clone block found within a source.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Change {
+    /// The line the block starts on, starting from 1.
+    pub line: usize,
+    /// The column the block starts on, starting from 1.
+    pub column: usize,
+    /// What was done with the block.
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+/// What was done with a pre-clone block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum Outcome {
+    /// The block was rewritten into a `soupa!` invocation.
+    Rewritten {
+        /// The bindings which were moved into super blocks.
+        bindings: Vec<String>,
+    },
+    /// The block was left as is.
+    Skipped {
+        /// Why the block couldn't be rewritten.
+        reason: String,
+    },
+}
+
+/// An error encountered while rewriting a source file.
+#[derive(Debug)]
+pub enum Error {
+    /// The source could not be parsed.
+    Parse(syn::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(error) => {
+                let start = error.span().start();
+                write!(
+                    f,
+                    "failed to parse source at {}:{}: {error}",
+                    start.line,
+                    start.column + 1
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<syn::Error> for Error {
+    fn from(error: syn::Error) -> Self {
+        Error::Parse(error)
+    }
+}
+
+struct Finder<'a> {
+    source: &'a str,
+    changes: Vec<Change>,
+    edits: Vec<(Range<usize>, String)>,
+    /// Whether `soupa!` is imported into every module by `#[macro_use]`.
+    macro_use: bool,
+    /// Whether a block was rewritten within each module being visited,
+    /// innermost last.
+    rewritten: Vec<bool>,
+}
+
+impl Finder<'_> {
+    /// Imports `soupa!` into the module containing `items` if it isn't already.
+    fn import(&mut self, items: &[syn::Item]) {
+        if self.macro_use || imports_soupa(items) {
+            return;
+        }
+
+        // Keep the import with any existing ones, or separate it from the first item
+        let Some(item) = items.first() else {
+            return;
+        };
+
+        let start = item.span().byte_range().start;
+        let indent = line_indent(self.source, start);
+        let separator = match item {
+            syn::Item::Use(_) => "\n",
+            _ => "\n\n",
+        };
+
+        // An item sharing a line with the start of its module is imported before inline
+        let (offset, import) = if self.source[line_start(self.source, start)..start]
+            .trim()
+            .is_empty()
+        {
+            let offset = line_start(self.source, start);
+            (offset, format!("{indent}use soupa::soupa;{separator}"))
+        } else {
+            (start, String::from("use soupa::soupa; "))
+        };
+
+        self.edits.push((offset..offset, import));
+    }
+}
+
+impl<'ast> Visit<'ast> for Finder<'_> {
+    fn visit_item_mod(&mut self, item: &'ast syn::ItemMod) {
+        // Imports within a file don't apply to the inline modules within it
+        let Some((_, items)) = &item.content else {
+            return;
+        };
+
+        self.rewritten.push(false);
+        syn::visit::visit_item_mod(self, item);
+
+        if self.rewritten.pop() == Some(true) {
+            self.import(items);
+        }
+    }
+
+    fn visit_expr_block(&mut self, block: &'ast syn::ExprBlock) {
+        let Some(candidate) = Candidate::new(block) else {
+            syn::visit::visit_expr_block(self, block);
+            return;
+        };
+
+        let start = block.span().start();
+        let outcome = match candidate.rewrite(self.source) {
+            Ok((range, replacement)) => {
+                self.edits.push((range, replacement));
+                if let Some(rewritten) = self.rewritten.last_mut() {
+                    *rewritten = true;
+                }
+                Outcome::Rewritten {
+                    bindings: candidate
+                        .bindings
+                        .iter()
+                        .map(|binding| binding.name.clone())
+                        .collect(),
+                }
+            }
+            Err(reason) => {
+                syn::visit::visit_expr_block(self, block);
+                Outcome::Skipped { reason }
+            }
+        };
+
+        self.changes.push(Change {
+            line: start.line,
+            column: start.column + 1,
+            outcome,
+        });
+    }
+}
+
+/// A block with the shape `{ let x = <expr>; ...; move || ... }`.
+struct Candidate<'a> {
+    block: &'a syn::ExprBlock,
+    bindings: Vec<Binding<'a>>,
+    tail: &'a syn::Expr,
+}
+
+struct Binding<'a> {
+    name: String,
+    local: &'a syn::Local,
+    init: &'a syn::Expr,
+}
+
+impl<'a> Candidate<'a> {
+    fn new(block: &'a syn::ExprBlock) -> Option<Self> {
+        let (tail, locals) = block.block.stmts.split_last()?;
+
+        let tail = match tail {
+            syn::Stmt::Expr(tail, None) => tail,
+            _ => return None,
+        };
+
+        let is_move = match tail {
+            syn::Expr::Closure(closure) => closure.capture.is_some(),
+            syn::Expr::Async(block) => block.capture.is_some(),
+            _ => false,
+        };
+
+        if !is_move || locals.is_empty() {
+            return None;
+        }
+
+        let bindings = locals
+            .iter()
+            .map(|stmt| match stmt {
+                syn::Stmt::Local(local) => {
+                    let init = local.init.as_ref()?;
+                    let name = match &local.pat {
+                        syn::Pat::Ident(pat) => pat.ident.to_string(),
+                        _ => String::new(),
+                    };
+                    Some(Binding {
+                        name,
+                        local,
+                        init: &init.expr,
+                    })
+                }
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Self {
+            block,
+            bindings,
+            tail,
+        })
+    }
+
+    /// Produces the edit which rewrites this block, or the reason it can't be.
+    fn rewrite(&self, source: &str) -> Result<(Range<usize>, String), String> {
+        if !self.block.attrs.is_empty() || self.block.label.is_some() {
+            return Err(String::from("the block has attributes or a label"));
+        }
+
+        for (index, binding) in self.bindings.iter().enumerate() {
+            let plain = matches!(
+                &binding.local.pat,
+                syn::Pat::Ident(pat) if pat.by_ref.is_none() && pat.mutability.is_none() && pat.subpat.is_none()
+            );
+
+            if !plain {
+                return Err(format!(
+                    "the binding `{}` is not a plain identifier",
+                    text(source, binding.local.pat.span().byte_range())
+                ));
+            }
+
+            if !binding.local.attrs.is_empty() {
+                return Err(format!("the binding `{}` has attributes", binding.name));
+            }
+
+            if let Some(init) = &binding.local.init {
+                if init.diverge.is_some() {
+                    return Err(format!(
+                        "the binding `{}` uses `let ... else`",
+                        binding.name
+                    ));
+                }
+            }
+
+            let mut uses = Uses::new(&self.bindings[..index]);
+            uses.visit_expr(binding.init);
+
+            if let Some(earlier) = uses.first_used() {
+                return Err(format!(
+                    "the binding `{}` depends on the earlier binding `{earlier}`",
+                    binding.name
+                ));
+            }
+        }
+
+        let mut uses = Uses::new(&self.bindings);
+        uses.visit_expr(self.tail);
+
+        if let Some(problem) = uses.problem {
+            return Err(problem);
+        }
+
+        // Lifted expressions are evaluated in the order of their super blocks
+        let mut order = (0..self.bindings.len()).collect::<Vec<_>>();
+        for (index, binding) in self.bindings.iter().enumerate() {
+            if uses.sites[index].is_empty() {
+                return Err(format!(
+                    "the binding `{}` is not used within the closure",
+                    binding.name
+                ));
+            }
+        }
+        order.sort_by_key(|&index| uses.sites[index][0].start);
+
+        if order
+            .iter()
+            .enumerate()
+            .any(|(position, &index)| position != index)
+        {
+            return Err(String::from(
+                "lifting the bindings would change the order they are evaluated in",
+            ));
+        }
+
+        let block_range = self.block.span().byte_range();
+        let tail_range = self.tail.span().byte_range();
+
+        // Everything outside of the closure is discarded, so only rewrite if no
+        // comments would be lost with it
+        let mut kept = self
+            .bindings
+            .iter()
+            .map(|binding| binding.init.span().byte_range())
+            .collect::<Vec<_>>();
+        kept.push(tail_range.clone());
+
+        if has_comment(source, block_range.clone(), &kept) {
+            return Err(String::from("comments within the block would be lost"));
+        }
+
+        // Replace each use within the closure
+        let mut replacements = Vec::new();
+        for (index, binding) in self.bindings.iter().enumerate() {
+            let sites = &uses.sites[index];
+            let expr = text(source, binding.init.span().byte_range());
+
+            let lifted = if sites.len() == 1 {
+                format!("super {{ {expr} }}")
+            } else {
+                format!("super({}) {{ {expr} }}", binding.name)
+            };
+
+            let first = sites[0].start - tail_range.start..sites[0].end - tail_range.start;
+            replacements.push((first, lifted));
+        }
+
+        let closure = apply(text(source, tail_range.clone()), replacements);
+
+        // The closure is no longer nested within the block, so remove the
+        // extra indentation from its lines
+        let block_indent = line_indent(source, block_range.start).len();
+        let closure_indent = line_indent(source, tail_range.start).len();
+        let closure = dedent(&closure, closure_indent.saturating_sub(block_indent));
+
+        Ok((block_range, format!("soupa!({closure})")))
+    }
+}
+
+/// Finds uses of bindings within an expression.
+struct Uses<'a, 'b> {
+    bindings: &'b [Binding<'a>],
+    sites: Vec<Vec<Range<usize>>>,
+    problem: Option<String>,
+}
+
+impl<'a, 'b> Uses<'a, 'b> {
+    fn new(bindings: &'b [Binding<'a>]) -> Self {
+        Self {
+            bindings,
+            sites: bindings.iter().map(|_| Vec::new()).collect(),
+            problem: None,
+        }
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.bindings
+            .iter()
+            .position(|binding| binding.name == name)
+    }
+
+    fn first_used(&self) -> Option<&str> {
+        self.sites
+            .iter()
+            .zip(self.bindings)
+            .find(|(sites, _)| !sites.is_empty())
+            .map(|(_, binding)| binding.name.as_str())
+    }
+
+    fn report(&mut self, problem: String) {
+        self.problem.get_or_insert(problem);
+    }
+
+    /// Checks the tokens of a macro invocation, which can't be rewritten.
+    fn check_tokens(&mut self, tokens: TokenStream) {
+        for token in tokens {
+            match token {
+                TokenTree::Ident(ident) => {
+                    if self.position(&ident.to_string()).is_some() {
+                        self.report(format!("the binding `{ident}` is used within a macro"));
+                    }
+                }
+                TokenTree::Literal(literal) => {
+                    // Format strings can capture bindings implicitly
+                    let literal = literal.to_string();
+                    for binding in self.bindings {
+                        if literal.contains(&format!("{{{}}}", binding.name))
+                            || literal.contains(&format!("{{{}:", binding.name))
+                        {
+                            self.report(format!(
+                                "the binding `{}` is used within a macro",
+                                binding.name
+                            ));
+                        }
+                    }
+                }
+                TokenTree::Group(group) => self.check_tokens(group.stream()),
+                TokenTree::Punct(_) => {}
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Uses<'_, '_> {
+    fn visit_expr_path(&mut self, path: &'ast syn::ExprPath) {
+        if path.qself.is_none() {
+            if let Some(ident) = path.path.get_ident() {
+                if let Some(index) = self.position(&ident.to_string()) {
+                    self.sites[index].push(ident.span().byte_range());
+                }
+            }
+        }
+
+        syn::visit::visit_expr_path(self, path);
+    }
+
+    fn visit_pat_ident(&mut self, pat: &'ast syn::PatIdent) {
+        if self.position(&pat.ident.to_string()).is_some() {
+            self.report(format!(
+                "the binding `{}` is shadowed within the closure",
+                pat.ident
+            ));
+        }
+
+        syn::visit::visit_pat_ident(self, pat);
+    }
+
+    fn visit_field_value(&mut self, field: &'ast syn::FieldValue) {
+        if field.colon_token.is_none() {
+            if let syn::Member::Named(ident) = &field.member {
+                if self.position(&ident.to_string()).is_some() {
+                    self.report(format!(
+                        "the binding `{ident}` is used in a struct field shorthand"
+                    ));
+                }
+            }
+        }
+
+        syn::visit::visit_field_value(self, field);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        self.check_tokens(mac.tokens.clone());
+    }
+}
+
+/// Whether `items` include `#[macro_use] extern crate soupa;`, which imports
+/// `soupa!` into every module.
+fn macro_use(items: &[syn::Item]) -> bool {
+    items.iter().any(|item| match item {
+        syn::Item::ExternCrate(item) => {
+            item.ident == "soupa"
+                && item
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path().is_ident("macro_use"))
+        }
+        _ => false,
+    })
+}
+
+/// Whether a `use` item within `items` imports the `soupa!` macro by that name.
+fn imports_soupa(items: &[syn::Item]) -> bool {
+    items.iter().any(|item| match item {
+        syn::Item::Use(item) => match &item.tree {
+            // `use soupa::*;` imports the macro along with the rest of the crate
+            syn::UseTree::Path(path) if path.ident == "soupa" => {
+                matches!(&*path.tree, syn::UseTree::Glob(_)) || names_soupa(&path.tree)
+            }
+            syn::UseTree::Path(path) => names_soupa(&path.tree),
+            syn::UseTree::Group(group) => group.items.iter().any(|tree| match tree {
+                syn::UseTree::Path(path) => names_soupa(&path.tree),
+                _ => false,
+            }),
+            // `use soupa;` only imports the crate
+            _ => false,
+        },
+        _ => false,
+    })
+}
+
+/// Whether `tree`, within the path of a `use` item, brings a `soupa` name into scope.
+fn names_soupa(tree: &syn::UseTree) -> bool {
+    match tree {
+        syn::UseTree::Path(path) => names_soupa(&path.tree),
+        syn::UseTree::Name(name) => name.ident == "soupa",
+        syn::UseTree::Rename(rename) => rename.rename == "soupa",
+        syn::UseTree::Group(group) => group.items.iter().any(names_soupa),
+        syn::UseTree::Glob(_) => false,
+    }
+}
+
+/// Whether `range` contains a comment outside of the `kept` ranges.
+fn has_comment(source: &str, range: Range<usize>, kept: &[Range<usize>]) -> bool {
+    let mut cursor = range.start;
+    let mut kept = kept.to_vec();
+    kept.sort_by_key(|range| range.start);
+
+    for kept in kept.iter().chain([&(range.end..range.end)]) {
+        let between = &source[cursor..kept.start.max(cursor)];
+        if between.contains("//") || between.contains("/*") {
+            return true;
+        }
+        cursor = kept.end;
+    }
+
+    false
+}
+
+fn text(source: &str, range: Range<usize>) -> &str {
+    &source[range]
+}
+
+fn line_start(source: &str, offset: usize) -> usize {
+    source[..offset].rfind('\n').map_or(0, |index| index + 1)
+}
+
+/// Returns the leading whitespace of the line containing `offset`.
+fn line_indent(source: &str, offset: usize) -> &str {
+    let line = &source[line_start(source, offset)..];
+    let end = line.len() - line.trim_start().len();
+
+    &line[..end]
+}
+
+/// Removes up to `amount` leading spaces from every line but the first.
+fn dedent(text: &str, amount: usize) -> String {
+    let mut lines = text.split('\n');
+    let mut output = String::from(lines.next().unwrap_or_default());
+
+    for line in lines {
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        output.push('\n');
+        output.push_str(&line[spaces.min(amount)..]);
+    }
+
+    output
+}
+
+/// Applies non-overlapping edits to `source`.
+fn apply(source: &str, mut edits: Vec<(Range<usize>, String)>) -> String {
+    edits.sort_by_key(|(range, _)| range.start);
+
+    let mut output = String::with_capacity(source.len());
+    let mut cursor = 0;
+
+    for (range, replacement) in edits {
+        output.push_str(&source[cursor..range.start]);
+        output.push_str(&replacement);
+        cursor = range.end;
+    }
+
+    output.push_str(&source[cursor..]);
+    output
+}
@@ -0,0 +1,190 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use serde::Serialize;
+use soupa_codemod::{diff, Change, Outcome};
+
+const USAGE: &str = "\
+Rewrites hand-written pre-clone blocks into soupa! form.
+
+Usage: soupa-codemod [OPTIONS] <PATH>...
+
+Each PATH may be a source file, or a directory which will be searched for
+source files. Sources are overwritten with their rewritten form.
+
+Options:
+  --dry-run        Print a diff of the rewrites instead of applying them
+  --report <FILE>  Write a JSON report of every block found to FILE, or to
+                   standard output if FILE is `-`
+  -h, --help       Print this message
+";
+
+/// The blocks found within a single source, as written to the JSON report.
+#[derive(Serialize)]
+struct FileReport<'a> {
+    path: String,
+    changes: &'a [Change],
+}
+
+fn main() -> ExitCode {
+    let mut dry_run = false;
+    let mut report = None;
+    let mut paths = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            "--report" => match args.next() {
+                Some(file) => report = Some(file),
+                None => {
+                    eprint!("error: `--report` requires a file\n\n{USAGE}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            flag if flag.starts_with('-') => {
+                eprint!("error: unknown option `{flag}`\n\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if paths.is_empty() {
+        eprint!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+
+    let mut files = Vec::new();
+    for path in &paths {
+        if let Err(error) = collect_sources(path, &mut files) {
+            eprintln!("error: failed to read `{}`: {error}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let mut status = ExitCode::SUCCESS;
+    let mut results = Vec::new();
+
+    for file in &files {
+        let result = fs::read_to_string(file)
+            .map_err(|error| error.to_string())
+            .and_then(|source| {
+                soupa_codemod::rewrite(&source)
+                    .map(|rewritten| (source, rewritten))
+                    .map_err(|error| error.to_string())
+            });
+
+        let (source, rewritten) = match result {
+            Ok(result) => result,
+            Err(error) => {
+                eprintln!("error: {}: {error}", file.display());
+                status = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        let path = file.display().to_string();
+
+        if dry_run {
+            print!("{}", diff::unified(&path, &source, &rewritten.source));
+        } else if rewritten.source != source {
+            if let Err(error) = fs::write(file, &rewritten.source) {
+                eprintln!("error: failed to write `{path}`: {error}");
+                status = ExitCode::FAILURE;
+            }
+        }
+
+        if report.is_none() {
+            for change in &rewritten.changes {
+                if let Outcome::Skipped { reason } = &change.outcome {
+                    eprintln!(
+                        "skipped: {path}:{}:{}: {reason}",
+                        change.line, change.column
+                    );
+                }
+            }
+        }
+
+        results.push((path, rewritten.changes));
+    }
+
+    let rewrites = results
+        .iter()
+        .flat_map(|(_, changes)| changes)
+        .filter(|change| matches!(change.outcome, Outcome::Rewritten { .. }))
+        .count();
+    let skips = results
+        .iter()
+        .map(|(_, changes)| changes.len())
+        .sum::<usize>()
+        - rewrites;
+
+    if let Some(report) = report {
+        let files = results
+            .iter()
+            .filter(|(_, changes)| !changes.is_empty())
+            .map(|(path, changes)| FileReport {
+                path: path.clone(),
+                changes,
+            })
+            .collect::<Vec<_>>();
+
+        let json = serde_json::to_string_pretty(&files).expect("report is serializable");
+
+        let written = if report == "-" {
+            println!("{json}");
+            Ok(())
+        } else {
+            fs::write(&report, format!("{json}\n"))
+        };
+
+        if let Err(error) = written {
+            eprintln!("error: failed to write report `{report}`: {error}");
+            status = ExitCode::FAILURE;
+        }
+    }
+
+    let verb = if dry_run { "would rewrite" } else { "rewrote" };
+    eprintln!("{verb} {rewrites} block(s), skipped {skips}");
+
+    status
+}
+
+/// Collects `path` if it is a file, or all Rust sources beneath it if it is a
+/// directory, skipping hidden and `target` directories.
+fn collect_sources(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_owned());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for entry in entries {
+        let name = entry
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+
+        if entry.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                collect_sources(&entry, files)?;
+            }
+        } else if name.ends_with(".rs") {
+            files.push(entry);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use soupa_codemod::{diff, rewrite, Outcome};
+
+/// Wraps `body` in a function within a source which already imports `soupa`.
+fn source(body: &str) -> String {
+    format!("use soupa::soupa;\n\nfn main() {{\n{body}}}\n")
+}
+
+fn skipped(body: &str) -> String {
+    let rewritten = rewrite(&source(body)).unwrap();
+    assert_eq!(rewritten.source, source(body));
+    assert_eq!(rewritten.changes.len(), 1);
+
+    match &rewritten.changes[0].outcome {
+        Outcome::Skipped { reason } => reason.clone(),
+        outcome => panic!("expected block to be skipped, but it was {outcome:?}"),
+    }
+}
+
+#[test]
+fn several_bindings() {
+    let rewritten = rewrite(&source(
+        r#"    let func = {
+        let foo = foo.clone();
+        let bar = Arc::clone(&bar);
+        move |x| {
+            // Comments within the closure are kept
+            work(foo, bar, x)
+        }
+    };
+"#,
+    ))
+    .unwrap();
+
+    assert_eq!(
+        rewritten.source,
+        source(
+            r#"    let func = soupa!(move |x| {
+        // Comments within the closure are kept
+        work(super { foo.clone() }, super { Arc::clone(&bar) }, x)
+    });
+"#
+        )
+    );
+
+    assert_eq!(
+        rewritten.changes[0].outcome,
+        Outcome::Rewritten {
+            bindings: vec![String::from("foo"), String::from("bar")]
+        }
+    );
+}
+
+#[test]
+fn several_uses() {
+    let rewritten = rewrite(&source(
+        r#"    let func = {
+        let foo = foo.clone();
+        move || foo.first() + foo.second()
+    };
+"#,
+    ))
+    .unwrap();
+
+    assert_eq!(
+        rewritten.source,
+        source(
+            "    let func = soupa!(move || super(foo) { foo.clone() }.first() + foo.second());\n"
+        )
+    );
+}
+
+#[test]
+fn async_block() {
+    let rewritten = rewrite(&source(
+        r#"    spawn({
+        let state = state.clone();
+        async move {
+            state.run().await
+        }
+    });
+"#,
+    ))
+    .unwrap();
+
+    assert_eq!(
+        rewritten.source,
+        source(
+            r#"    spawn(soupa!(async move {
+        super { state.clone() }.run().await
+    }));
+"#
+        )
+    );
+}
+
+#[test]
+fn adds_import() {
+    let rewritten = rewrite(
+        r#"//! Docs
+
+use std::sync::Arc;
+
+fn main() {
+    let func = { let foo = foo.clone(); move || foo };
+}
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        rewritten.source,
+        r#"//! Docs
+
+use soupa::soupa;
+use std::sync::Arc;
+
+fn main() {
+    let func = soupa!(move || super { foo.clone() });
+}
+"#
+    );
+}
+
+#[test]
+fn import_of_macro_name() {
+    // Other items from the crate don't import the macro
+    let rewritten = rewrite(
+        r#"use soupa::adapters::Cloned;
+
+fn main() {
+    let func = { let foo = foo.clone(); move || foo };
+}
+"#,
+    )
+    .unwrap();
+
+    assert!(rewritten
+        .source
+        .starts_with("use soupa::soupa;\nuse soupa::adapters::Cloned;\n"));
+
+    for import in [
+        "use soupa::{adapters::Cloned, soupa};",
+        "use soupa::*;",
+        "#[macro_use]\nextern crate soupa;",
+    ] {
+        let source = format!(
+            "{import}\n\nfn main() {{\n    let func = {{ let foo = foo.clone(); move || foo }};\n}}\n"
+        );
+        let rewritten = rewrite(&source).unwrap();
+
+        assert!(rewritten
+            .source
+            .starts_with(&format!("{import}\n\nfn main")));
+        assert!(!rewritten.source.contains("use soupa::soupa;"));
+    }
+}
+
+#[test]
+fn import_within_inline_module() {
+    let rewritten = rewrite(
+        r#"use soupa::soupa;
+
+mod inner {
+    use std::sync::Arc;
+
+    fn run() {
+        let func = { let foo = foo.clone(); move || foo };
+    }
+}
+
+mod other { fn run() { let func = { let foo = foo.clone(); move || foo }; } }
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        rewritten.source,
+        r#"use soupa::soupa;
+
+mod inner {
+    use soupa::soupa;
+    use std::sync::Arc;
+
+    fn run() {
+        let func = soupa!(move || super { foo.clone() });
+    }
+}
+
+mod other { use soupa::soupa; fn run() { let func = soupa!(move || super { foo.clone() }); } }
+"#
+    );
+}
+
+#[test]
+fn not_candidates() {
+    let body = r#"    let a = { let foo = foo.clone(); || foo };
+    let b = { let foo = foo.clone(); foo };
+    let c = { work(); move || foo };
+"#;
+
+    let rewritten = rewrite(&source(body)).unwrap();
+
+    assert_eq!(rewritten.source, source(body));
+    assert!(rewritten.changes.is_empty());
+}
+
+#[test]
+fn skip_reasons() {
+    assert_eq!(
+        skipped("    let f = { let mut foo = foo.clone(); move || foo.push(1) };\n"),
+        "the binding `mut foo` is not a plain identifier"
+    );
+    assert_eq!(
+        skipped("    let f = { let foo = foo.clone(); let bar = foo.bar(); move || bar };\n"),
+        "the binding `bar` depends on the earlier binding `foo`"
+    );
+    assert_eq!(
+        skipped("    let f = { let foo = foo.clone(); move |foo| foo };\n"),
+        "the binding `foo` is shadowed within the closure"
+    );
+    assert_eq!(
+        skipped("    let f = { let foo = foo.clone(); move || println!(\"{foo}\") };\n"),
+        "the binding `foo` is used within a macro"
+    );
+    assert_eq!(
+        skipped("    let f = { let foo = foo.clone(); move || Foo { foo } };\n"),
+        "the binding `foo` is used in a struct field shorthand"
+    );
+    assert_eq!(
+        skipped("    let f = { let foo = foo.clone(); let bar = 1; move || foo };\n"),
+        "the binding `bar` is not used within the closure"
+    );
+    assert_eq!(
+        skipped("    let f = { let foo = foo(); let bar = bar(); move || (bar, foo) };\n"),
+        "lifting the bindings would change the order they are evaluated in"
+    );
+    assert_eq!(
+        skipped("    let f = {\n        // Comment\n        let foo = foo.clone();\n        move || foo\n    };\n"),
+        "comments within the block would be lost"
+    );
+}
+
+#[test]
+fn report() {
+    let rewritten = rewrite(&source(
+        "    let f = { let foo = foo.clone(); move || foo };\n    let g = { let foo = foo.clone(); move |foo| foo };\n",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        serde_json::to_value(&rewritten.changes).unwrap(),
+        serde_json::json!([
+            { "line": 4, "column": 13, "outcome": "rewritten", "bindings": ["foo"] },
+            {
+                "line": 5,
+                "column": 13,
+                "outcome": "skipped",
+                "reason": "the binding `foo` is shadowed within the closure"
+            },
+        ])
+    );
+}
+
+#[test]
+fn unified_diff() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let new = "a\nb\nc\nd\nE\nf\ng\nh\ni\nj\nk\n";
+
+    assert_eq!(
+        diff::unified("src/lib.rs", old, new),
+        "\
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -2,9 +2,10 @@
+ b
+ c
+ d
+-e
++E
+ f
+ g
+ h
+ i
+ j
++k
+"
+    );
+
+    assert_eq!(diff::unified("src/lib.rs", old, old), "");
+}
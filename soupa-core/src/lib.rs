@@ -0,0 +1,1222 @@
+//! Provides the transformation behind [`soupa!`](https://docs.rs/soupa/latest/soupa/macro.soupa.html)
+//! as a library, allowing `super { ... }` blocks to be supported within the
+//! bodies of other procedural macros.
+//!
+//! The transformation follows the same rules as the `soupa!` macro:
+//!
+//! * `super { ... }` blocks at any depth are lifted, in the order they appear.
+//! * Any other use of `super`, such as in `super::path` or `pub(super)`, is passed through unchanged.
+//! * Outer attributes directly before a super block are copied onto its init statement, and only
+//!   `#[cfg]` attributes are kept where it was written, if it begins a statement.
+//! * A labelled super block, `super(label) { ... }`, uses its label as the name of its temporary.
+//! * An ordered super block, `super(label, after = other) { ... }`, is evaluated after the super
+//!   block labelled `other`.
+//! * `lift!(...)`, `soupa::lift!(...)` and `::soupa::lift!(...)` markers are treated as super blocks.
+//! * `super span` and `super span(...)` markers before an async block lift a span and
+//!   instrument the block with it, and before a closure lift a span and enter it around
+//!   the closure's body. The span is created with `::tracing` directly, rather than
+//!   through `soupa`.
+//! * A shared super block, `super share(name) { ... }`, is lifted with `name` as the name of its
+//!   temporary, and each use of it, including `super share(name)`, is given a clone of it,
+//!   except for the last, which takes it.
+//! * A super block with a capture adapter, `super with Adapter { ... }`, is lifted as a call
+//!   to the adapter's `capture`, and replaced by a call to its `restore`. Both go through
+//!   `::soupa`, which must be a dependency of the crate the output is compiled in.
+//! * The leading `@strict` option is accepted and ignored, as it only enables lints.
+//!   The `@send`, `@sync` and `@static` options are too, as they only add checks.
+//! * The leading `@boxed(...)` option boxes the output as a trait object, or pins and boxes it
+//!   when the trait is `Future`, through `::soupa`, which must be a dependency with the `alloc`
+//!   feature enabled.
+//! * With the leading `@dedup` option, a super block or lift marker without a label or
+//!   attributes which is identical to an earlier one is lifted as a clone of its value.
+//! * With the leading `@lazy` option, super blocks within the block of an `if`, `else` or match
+//!   arm which isn't within a closure are lifted to the start of that block instead.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use quote::quote;
+//! let (stmts, body) = soupa_core::lift(quote! {
+//!     move || super { foo.clone() }
+//! })
+//! .unwrap();
+//!
+//! assert_eq!(stmts.len(), 1);
+//! assert_eq!(body.to_string(), quote!(move || __soupa_temp_a).to_string());
+//! ```
+
+use std::collections::VecDeque;
+
+use proc_macro2::{Delimiter, Group, Ident, Punct, Spacing, Span, TokenStream, TokenTree};
+use quote::{quote, quote_spanned, ToTokens, TokenStreamExt};
+use syn::Stmt;
+
+/// Lifts all super blocks out of `input`, returning the init statements for the
+/// lifted expressions and the remaining body which refers to them.
+///
+/// This is the same transformation performed by `soupa!`, which would output
+/// the init statements followed by the body, all within a block.
+///
+/// # Errors
+///
+/// Fails if an init statement could not be parsed, such as when the attributes
+/// on a super block are malformed.
+pub fn lift(input: TokenStream) -> syn::Result<(Vec<Stmt>, TokenStream)> {
+    Lifter::new().lift(input).into_stmts()
+}
+
+/// Iterates over the expressions which would be lifted out of `input`, in the
+/// order they would be evaluated.
+pub fn lifts(input: TokenStream) -> std::vec::IntoIter<Lift> {
+    Lifter::new().lift(input).into_iter()
+}
+
+/// Performs the complete transformation of `soupa!`, producing a block containing
+/// the init statements followed by the body.
+pub fn expand(input: TokenStream) -> TokenStream {
+    Lifter::new().lift(input).into_token_stream()
+}
+
+/// Controls how the temporaries for unlabelled super blocks are named.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Naming {
+    /// Temporaries are named `__soupa_temp_a`, `__soupa_temp_b`, and so on,
+    /// exactly as `soupa!` would name them.
+    #[default]
+    Temps,
+    /// Temporaries are named after the identifiers in their expression, such as
+    /// `__soupa_foo_clone` for `super { foo.clone() }`, and spanned at the super
+    /// block they were lifted from.
+    ///
+    /// `soupa!` itself has no equivalent, as it can't build new identifiers.
+    Readable,
+}
+
+/// Configurable entry point for the lifting transformation.
+///
+/// The free functions [`lift`], [`lifts`] and [`expand`] use the default configuration.
+#[derive(Clone, Debug, Default)]
+pub struct Lifter {
+    naming: Naming,
+}
+
+impl Lifter {
+    /// Creates a [`Lifter`] with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how the temporaries for unlabelled super blocks are named.
+    pub fn naming(mut self, naming: Naming) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Lifts all super blocks out of `input`.
+    pub fn lift(&self, input: TokenStream) -> Lifted {
+        let (input, options) = strip_options(input);
+        self.lift_with(input, options)
+    }
+
+    fn lift_with(&self, input: TokenStream, options: Options) -> Lifted {
+        let mut state = State {
+            naming: self.naming,
+            dedup: options.dedup,
+            lazy: options.lazy,
+            seen: Vec::new(),
+            lifts: Vec::new(),
+            temps: 0,
+            names: Vec::new(),
+            shares: Vec::new(),
+            error: None,
+        };
+
+        let body = state.stream(input, false, true);
+        state.take_last_shares();
+
+        let (lifts, error) = match (state.error, order(state.lifts)) {
+            (Some(error), Ok(lifts) | Err((lifts, _))) => (lifts, Some(error)),
+            (None, Ok(lifts)) => (lifts, None),
+            (None, Err((lifts, error))) => (lifts, Some(error)),
+        };
+
+        Lifted {
+            lifts,
+            body,
+            boxed: options.boxed,
+            error,
+        }
+    }
+}
+
+/// The result of lifting all super blocks out of some tokens.
+#[derive(Clone, Debug)]
+pub struct Lifted {
+    lifts: Vec<Lift>,
+    body: TokenStream,
+    boxed: Option<TokenStream>,
+    error: Option<syn::Error>,
+}
+
+impl Lifted {
+    /// The lifted expressions, in the order they will be evaluated.
+    pub fn lifts(&self) -> &[Lift] {
+        &self.lifts
+    }
+
+    /// Iterates over the lifted expressions, in the order they will be evaluated.
+    pub fn iter(&self) -> std::slice::Iter<'_, Lift> {
+        self.lifts.iter()
+    }
+
+    /// The remaining body, with each super block replaced by its temporary.
+    ///
+    /// This is before it is boxed by the `@boxed(...)` option, if given.
+    pub fn body(&self) -> &TokenStream {
+        &self.body
+    }
+
+    /// The trait object given by the `@boxed(...)` option, if any.
+    pub fn boxed(&self) -> Option<&TokenStream> {
+        self.boxed.as_ref()
+    }
+
+    /// The error preventing the lifted expressions from being ordered, such as an
+    /// ordered super block following a label which doesn't exist, or a misplaced
+    /// span marker.
+    pub fn error(&self) -> Option<&syn::Error> {
+        self.error.as_ref()
+    }
+
+    /// Converts the lifted expressions into init statements, returned alongside
+    /// the remaining body.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the lifted expressions could not be ordered, or an init statement
+    /// could not be parsed.
+    pub fn into_stmts(self) -> syn::Result<(Vec<Stmt>, TokenStream)> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let stmts = self
+            .lifts
+            .iter()
+            .map(Lift::to_stmt)
+            .collect::<syn::Result<_>>()?;
+
+        let body = match &self.boxed {
+            Some(bound) => boxed(bound, self.body),
+            None => self.body,
+        };
+
+        Ok((stmts, body))
+    }
+}
+
+impl IntoIterator for Lifted {
+    type Item = Lift;
+    type IntoIter = std::vec::IntoIter<Lift>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.lifts.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Lifted {
+    type Item = &'a Lift;
+    type IntoIter = std::slice::Iter<'a, Lift>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.lifts.iter()
+    }
+}
+
+impl ToTokens for Lifted {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        if let Some(error) = &self.error {
+            tokens.extend(error.to_compile_error());
+            return;
+        }
+
+        let mut inner = TokenStream::new();
+        inner.append_all(&self.lifts);
+        inner.extend(self.body.clone());
+
+        let output = Group::new(Delimiter::Brace, inner).into_token_stream();
+
+        match &self.boxed {
+            Some(bound) => tokens.extend(boxed(bound, output)),
+            None => tokens.extend(output),
+        }
+    }
+}
+
+/// A single expression lifted out of a super block.
+#[derive(Clone, Debug)]
+pub struct Lift {
+    attrs: TokenStream,
+    ident: Ident,
+    labelled: bool,
+    after: Option<Ident>,
+    block: Group,
+    span: Span,
+}
+
+impl Lift {
+    /// The identifier the lifted value is assigned to.
+    pub fn ident(&self) -> &Ident {
+        &self.ident
+    }
+
+    /// Whether the identifier was provided by a label, `super(label) { ... }`.
+    pub fn is_labelled(&self) -> bool {
+        self.labelled
+    }
+
+    /// The label of the super block this must be evaluated after, provided by
+    /// `super(label, after = other) { ... }`.
+    pub fn after(&self) -> Option<&Ident> {
+        self.after.as_ref()
+    }
+
+    /// The outer attributes written before the super block.
+    pub fn attrs(&self) -> &TokenStream {
+        &self.attrs
+    }
+
+    /// The contents of the super block.
+    pub fn expr(&self) -> TokenStream {
+        self.block.stream()
+    }
+
+    /// The span of the `super` keyword, or `lift!` marker, which introduced this
+    /// lifted expression.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Converts this lifted expression into its init statement.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the init statement could not be parsed.
+    pub fn to_stmt(&self) -> syn::Result<Stmt> {
+        syn::parse2(self.to_token_stream())
+    }
+}
+
+impl ToTokens for Lift {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(self.attrs.clone());
+        tokens.append(Ident::new("let", self.span));
+        tokens.append(self.ident.clone());
+        tokens.append(Punct::new('=', Spacing::Alone));
+        tokens.append(self.block.clone());
+        tokens.append(Punct::new(';', Spacing::Alone));
+    }
+}
+
+struct State {
+    naming: Naming,
+    /// Whether identical super blocks are deduplicated.
+    dedup: bool,
+    /// The super blocks which may be deduplicated, along with their temporaries.
+    seen: Vec<(String, Ident)>,
+    /// Whether super blocks within branches are lifted into them.
+    lazy: bool,
+    lifts: Vec<Lift>,
+    temps: usize,
+    names: Vec<String>,
+    /// The indices of lifts which are uses of shared super blocks, along with
+    /// the names they share.
+    shares: Vec<(usize, Ident)>,
+    error: Option<syn::Error>,
+}
+
+/// A super block or lift marker found in a token stream.
+struct Marker {
+    /// Number of tokens the marker occupies, including attributes.
+    len: usize,
+    attrs: TokenStream,
+    /// Whether the marker begins a statement, so its cfg attributes can be kept in place.
+    statement: bool,
+    label: Option<Ident>,
+    after: Option<Ident>,
+    block: Group,
+    span: Span,
+}
+
+/// What a `{}` tree outside of a closure may be, as tracked for the `@lazy` option.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pending {
+    No,
+    /// Within the condition of an `if`, so a `{}` tree is its block.
+    Condition,
+    /// Within a pattern in a condition, so a `{}` tree is part of it.
+    Pattern,
+}
+
+impl State {
+    /// Transforms `input`, which is within a closure if `closure` is set, and
+    /// is the contents of a block, which may begin with a statement, if `block` is.
+    fn stream(&mut self, input: TokenStream, closure: bool, block: bool) -> TokenStream {
+        let tokens = input.into_iter().collect::<Vec<_>>();
+        let mut output = TokenStream::new();
+        let mut index = 0;
+        let outer = closure;
+        let mut closure = closure;
+        let mut pending = Pending::No;
+        // Angle brackets within a closure, up to four, so a `,` within them doesn't end it
+        let mut angles = 0usize;
+
+        while index < tokens.len() {
+            if is_ident(tokens.get(index), "super") && is_ident(tokens.get(index + 1), "span") {
+                index = self.span_marker(&tokens, index, &mut output);
+                continue;
+            }
+
+            if let Some(marker) = marker(&tokens[..index], &tokens[index..], block) {
+                index += marker.len;
+
+                if self.dedup && marker.attrs.is_empty() && marker.label.is_none() {
+                    self.dedup_marker(marker, &mut output);
+                } else {
+                    let naming = marker.block.clone();
+                    self.lift_marker(marker, &naming, &mut output);
+                }
+
+                continue;
+            }
+
+            if is_ident(tokens.get(index), "super") {
+                if let Some(next) = self.adapter_marker(&tokens, index, &mut output) {
+                    index = next;
+                    continue;
+                }
+
+                if let Some(next) = self.share_marker(&tokens, index, &mut output) {
+                    index = next;
+                    continue;
+                }
+            }
+
+            if self.lazy && !closure && pending == Pending::No {
+                // The block of an `else` or a match arm
+                let len = if is_ident(tokens.get(index), "else") {
+                    1
+                } else if is_fat_arrow(&tokens[index..]) {
+                    2
+                } else {
+                    0
+                };
+
+                if let Some(block) =
+                    group(tokens.get(index + len), Delimiter::Brace).filter(|_| len > 0)
+                {
+                    output.extend(tokens[index..index + len].iter().cloned());
+                    output.append(self.branch(block));
+                    index += len + 1;
+                    continue;
+                }
+            }
+
+            match &tokens[index] {
+                // The block of an `if`
+                TokenTree::Group(group)
+                    if group.delimiter() == Delimiter::Brace && pending == Pending::Condition =>
+                {
+                    pending = Pending::No;
+                    if self.lazy && !closure {
+                        output.append(self.branch(group));
+                    } else {
+                        let mut inner = Group::new(
+                            group.delimiter(),
+                            self.stream(group.stream(), closure, true),
+                        );
+                        inner.set_span(group.span());
+                        output.append(inner);
+                    }
+                }
+                // Invisible groups are opaque to the soupa! muncher
+                TokenTree::Group(group) if group.delimiter() != Delimiter::None => {
+                    let block = group.delimiter() == Delimiter::Brace;
+                    let mut inner = Group::new(
+                        group.delimiter(),
+                        self.stream(group.stream(), closure, block),
+                    );
+                    inner.set_span(group.span());
+                    output.append(inner);
+                }
+                token => {
+                    output.append(token.clone());
+
+                    match token {
+                        TokenTree::Punct(punct) => match punct.as_char() {
+                            '|' => closure = true,
+                            ';' if !outer => {
+                                closure = false;
+                                angles = 0;
+                            }
+                            ',' if !outer && angles == 0 => closure = false,
+                            '<' if closure && !outer && is_angle(&tokens, index) => {
+                                angles = (angles + 1).min(4);
+                            }
+                            '>' if closure && !outer && is_angle(&tokens, index) => {
+                                angles = angles.saturating_sub(1);
+                            }
+                            '=' if pending == Pending::Pattern && is_lone_eq(&tokens, index) => {
+                                pending = Pending::Condition;
+                            }
+                            _ => {}
+                        },
+                        TokenTree::Ident(ident) if ident == "async" => closure = true,
+                        TokenTree::Ident(ident)
+                            if ident == "if" && !closure && pending == Pending::No =>
+                        {
+                            pending = Pending::Condition;
+                        }
+                        TokenTree::Ident(ident)
+                            if ident == "let" && pending == Pending::Condition =>
+                        {
+                            pending = Pending::Pattern;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            index += 1;
+        }
+
+        output
+    }
+
+    /// Lifts the expression of a super block or lift marker, outputting what
+    /// replaces it and returning the identifier it is assigned to. Unlabelled
+    /// temporaries are named after `naming`.
+    fn lift_marker(&mut self, marker: Marker, naming: &Group, output: &mut TokenStream) -> Ident {
+        let labelled = marker.label.is_some();
+        let ident = match marker.label {
+            Some(label) => label,
+            None => self.temp(naming, marker.span),
+        };
+
+        if marker.statement {
+            output.extend(cfg_attrs(&marker.attrs));
+        }
+        output.append(ident.clone());
+
+        self.lifts.push(Lift {
+            attrs: marker.attrs,
+            ident: ident.clone(),
+            labelled,
+            after: marker.after,
+            block: marker.block,
+            span: marker.span,
+        });
+
+        ident
+    }
+
+    /// Lifts the expression of a super block or lift marker, unless an identical
+    /// one has already been lifted, in which case it is cloned instead.
+    fn dedup_marker(&mut self, marker: Marker, output: &mut TokenStream) {
+        let tokens = marker.block.stream().to_string();
+
+        match self.seen.iter().find(|(seen, _)| *seen == tokens) {
+            Some((_, first)) => {
+                let first = first.clone();
+                let span = marker.span;
+                let naming = marker.block.clone();
+
+                self.lift_marker(
+                    Marker {
+                        block: Group::new(Delimiter::Brace, quote_spanned!(span=> #first.clone())),
+                        ..marker
+                    },
+                    &naming,
+                    output,
+                );
+            }
+            None => {
+                let naming = marker.block.clone();
+                let ident = self.lift_marker(marker, &naming, output);
+                self.seen.push((tokens, ident));
+            }
+        }
+    }
+
+    /// Processes a super block with a capture adapter at `tokens[index]`, as
+    /// `super with Adapter { ... }` or `super(...) with Adapter { ... }`, returning
+    /// the index after its block. Returns `None` if there isn't one.
+    fn adapter_marker(
+        &mut self,
+        tokens: &[TokenTree],
+        index: usize,
+        output: &mut TokenStream,
+    ) -> Option<usize> {
+        let span = tokens[index].span();
+        let args = group(tokens.get(index + 1), Delimiter::Parenthesis);
+        let mut next = index + 1 + usize::from(args.is_some());
+
+        if !is_ident(tokens.get(next), "with") {
+            return None;
+        }
+
+        next += 1;
+        let start = next;
+
+        while next < tokens.len()
+            && group(tokens.get(next), Delimiter::Brace).is_none()
+            && !is_punct(tokens.get(next), ',')
+            && !is_punct(tokens.get(next), ';')
+        {
+            next += 1;
+        }
+
+        let block = group(tokens.get(next), Delimiter::Brace)?.stream();
+        let adapter = tokens[start..next].iter().cloned().collect::<TokenStream>();
+
+        if adapter.is_empty() {
+            return None;
+        }
+
+        // super(...) { ::soupa::__private::capture(&Adapter, { ... }) }
+        let mut lifted = vec![tokens[index].clone()];
+        lifted.extend(args.cloned().map(TokenTree::Group));
+        lifted.push(TokenTree::Group(Group::new(
+            Delimiter::Brace,
+            quote_spanned!(span=> ::soupa::__private::capture(&#adapter, { #block })),
+        )));
+
+        let marker = marker(&[], &lifted, false)?;
+        let naming = Group::new(Delimiter::Brace, quote!(#adapter #block));
+
+        let mut restored = TokenStream::new();
+        self.lift_marker(marker, &naming, &mut restored);
+        output.extend(quote_spanned!(span=> ::soupa::__private::restore(&#restored)));
+
+        Some(next + 1)
+    }
+
+    /// Processes a shared super block at `tokens[index]`, as `super share(name) { ... }`
+    /// or `super share(name)`, returning the index after it. Returns `None` if
+    /// there isn't one.
+    fn share_marker(
+        &mut self,
+        tokens: &[TokenTree],
+        index: usize,
+        output: &mut TokenStream,
+    ) -> Option<usize> {
+        if !is_ident(tokens.get(index + 1), "share") {
+            return None;
+        }
+
+        let span = tokens[index].span();
+        let args = group(tokens.get(index + 2), Delimiter::Parenthesis)?
+            .stream()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let name = match args.as_slice() {
+            [TokenTree::Ident(name)] => name.clone(),
+            _ => return None,
+        };
+
+        let mut next = index + 3;
+
+        if let Some(block) = group(tokens.get(next), Delimiter::Brace) {
+            next += 1;
+
+            // An earlier use would have cloned a variable from the surrounding scope
+            if self.shares.iter().any(|(_, shared)| *shared == name) {
+                self.fail(
+                    span,
+                    &format!("the block of `super share({name}) {{ ... }}` must be given at its first use"),
+                );
+            }
+
+            self.lifts.push(Lift {
+                attrs: TokenStream::new(),
+                ident: name.clone(),
+                labelled: true,
+                after: None,
+                block: block.clone(),
+                span,
+            });
+        }
+
+        // Every use is a clone, until the last is found
+        let ident = self.temp(&Group::new(Delimiter::Brace, name.to_token_stream()), span);
+        output.append(ident.clone());
+
+        self.shares.push((self.lifts.len(), name.clone()));
+        self.lifts.push(Lift {
+            attrs: TokenStream::new(),
+            ident,
+            labelled: false,
+            after: None,
+            block: Group::new(Delimiter::Brace, quote_spanned!(span=> #name.clone())),
+            span,
+        });
+
+        Some(next)
+    }
+
+    /// Makes the last use of each shared super block take its value, rather than
+    /// a clone.
+    fn take_last_shares(&mut self) {
+        let mut taken = Vec::<String>::new();
+
+        for (index, name) in self.shares.iter().rev() {
+            if !taken.contains(&name.to_string()) {
+                taken.push(name.to_string());
+                self.lifts[*index].block = Group::new(Delimiter::Brace, name.to_token_stream());
+            }
+        }
+    }
+
+    /// Processes the `super span` marker at `tokens[index]`, returning the index
+    /// after the async block or closure it applies to.
+    fn span_marker(
+        &mut self,
+        tokens: &[TokenTree],
+        index: usize,
+        output: &mut TokenStream,
+    ) -> usize {
+        let span = tokens[index].span();
+        let mut next = index + 2;
+
+        // super span(...) or super span
+        let (expr, naming) = match group(tokens.get(next), Delimiter::Parenthesis) {
+            Some(args) => {
+                next += 1;
+                (args.stream(), args.stream())
+            }
+            None => (
+                quote_spanned!(span=> ::tracing::Span::current()),
+                quote_spanned!(span=> span),
+            ),
+        };
+
+        let start = next;
+
+        while next < tokens.len()
+            && group(tokens.get(next), Delimiter::Brace).is_none()
+            && !is_punct(tokens.get(next), ',')
+            && !is_punct(tokens.get(next), ';')
+        {
+            next += 1;
+        }
+
+        let (head, block) = match group(tokens.get(next), Delimiter::Brace) {
+            Some(block) => (&tokens[start..next], block),
+            None => {
+                self.fail(
+                    span,
+                    "`super span` must be followed by an async block, or a closure with a block body",
+                );
+                return next;
+            }
+        };
+
+        let is_async = is_ident(head.first(), "async");
+        let is_async_block = match head {
+            [_] => is_async,
+            [_, second] => is_async && is_ident(Some(second), "move"),
+            _ => false,
+        };
+
+        if is_async && !is_async_block {
+            self.fail(
+                span,
+                "`super span` can't be used with async closures, use an async block instead",
+            );
+            return next + 1;
+        }
+
+        let ident = self.temp(&Group::new(Delimiter::Brace, naming), span);
+
+        self.lifts.push(Lift {
+            attrs: TokenStream::new(),
+            ident: ident.clone(),
+            labelled: false,
+            after: None,
+            block: Group::new(Delimiter::Brace, expr),
+            span,
+        });
+
+        let head = self.stream(head.iter().cloned().collect(), true, false);
+        let body = self.stream(block.stream(), true, true);
+
+        if is_async_block {
+            let mut instrumented = head;
+            instrumented.append(Group::new(Delimiter::Brace, body));
+            output.extend(quote_spanned! {span=>
+                ::tracing::Instrument::instrument(#instrumented, #ident)
+            });
+        } else {
+            let entered = Ident::new("__soupa_entered", Span::mixed_site().located_at(span));
+            output.extend(head);
+            output.append(Group::new(
+                Delimiter::Brace,
+                quote_spanned! {span=>
+                    let #entered = #ident.enter();
+                    #body
+                },
+            ));
+        }
+
+        next + 1
+    }
+
+    /// Lifts the super blocks within the block of a branch into it, as the
+    /// `@lazy` option does.
+    fn branch(&mut self, block: &Group) -> Group {
+        let lifter = Lifter {
+            naming: self.naming,
+        };
+        let lifted = lifter.lift_with(
+            block.stream(),
+            Options {
+                dedup: self.dedup,
+                lazy: true,
+                boxed: None,
+            },
+        );
+
+        if self.error.is_none() {
+            self.error = lifted.error.clone();
+        }
+
+        let mut inner = Group::new(Delimiter::Brace, lifted.into_token_stream());
+        inner.set_span(block.span());
+        inner
+    }
+
+    /// Records an error, keeping only the first.
+    fn fail(&mut self, span: Span, message: &str) {
+        if self.error.is_none() {
+            self.error = Some(syn::Error::new(span, message));
+        }
+    }
+
+    fn temp(&mut self, block: &Group, span: Span) -> Ident {
+        let name = match self.naming {
+            Naming::Temps => {
+                let name = format!("__soupa_temp_{}", temp_suffix(self.temps));
+                self.temps += 1;
+                name
+            }
+            Naming::Readable => {
+                let base = readable_name(block.stream());
+                let mut name = base.clone();
+                let mut count = 1;
+                while self.names.contains(&name) {
+                    count += 1;
+                    name = format!("{base}_{count}");
+                }
+                self.names.push(name.clone());
+                name
+            }
+        };
+
+        Ident::new(&name, Span::mixed_site().located_at(span))
+    }
+}
+
+/// Produces the same suffixes as the temporaries within `soupa!`: `a` to `z`,
+/// then `aa` to `zz`, and so on.
+fn temp_suffix(mut index: usize) -> String {
+    let mut suffix = Vec::new();
+
+    loop {
+        suffix.push(b'a' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+
+    suffix.reverse();
+    String::from_utf8(suffix).unwrap()
+}
+
+/// Derives a name from the first few identifiers within an expression.
+fn readable_name(expr: TokenStream) -> String {
+    const MAX_PARTS: usize = 4;
+    const SKIPPED: &[&str] = &["as", "move", "mut", "ref", "self", "Self", "crate", "super"];
+
+    fn collect(expr: TokenStream, parts: &mut Vec<String>) {
+        for token in expr {
+            if parts.len() >= MAX_PARTS {
+                return;
+            }
+
+            match token {
+                TokenTree::Ident(ident) => {
+                    let ident = ident.to_string();
+                    let ident = ident.trim_start_matches("r#");
+                    if !SKIPPED.contains(&ident) {
+                        parts.push(snake_case(ident));
+                    }
+                }
+                TokenTree::Group(group) => collect(group.stream(), parts),
+                _ => {}
+            }
+        }
+    }
+
+    let mut parts = Vec::new();
+    collect(expr, &mut parts);
+
+    if parts.is_empty() {
+        String::from("__soupa_temp")
+    } else {
+        format!("__soupa_{}", parts.join("_"))
+    }
+}
+
+fn snake_case(ident: &str) -> String {
+    let mut output = String::new();
+
+    for (index, c) in ident.char_indices() {
+        if c.is_uppercase() {
+            if index > 0 && !output.ends_with('_') {
+                output.push('_');
+            }
+            output.extend(c.to_lowercase());
+        } else {
+            output.push(c);
+        }
+    }
+
+    output
+}
+
+/// Orders lifted expressions as `soupa!` would: in the order they appear, except
+/// that an ordered super block waits until the one it follows has been output.
+///
+/// On failure, the lifts are returned in their original order alongside the error.
+fn order(lifts: Vec<Lift>) -> Result<Vec<Lift>, (Vec<Lift>, syn::Error)> {
+    if lifts.iter().all(|lift| lift.after.is_none()) {
+        return Ok(lifts);
+    }
+
+    let mut queue = lifts.iter().cloned().collect::<VecDeque<_>>();
+    let mut done = Vec::<Ident>::new();
+    let mut waiting = Vec::<Lift>::new();
+    let mut ordered = Vec::new();
+
+    while let Some(lift) = queue.pop_front() {
+        if matches!(&lift.after, Some(after) if !done.contains(after)) {
+            waiting.push(lift);
+            continue;
+        }
+
+        done.push(lift.ident.clone());
+
+        let (released, kept) = waiting
+            .into_iter()
+            .partition::<Vec<_>, _>(|waiting| waiting.after.as_ref() == Some(&lift.ident));
+        waiting = kept;
+
+        for released in released.into_iter().rev() {
+            queue.push_front(released);
+        }
+
+        ordered.push(lift);
+    }
+
+    match waiting.first() {
+        None => Ok(ordered),
+        Some(lift) => {
+            let after = lift.after.as_ref().expect("only ordered lifts wait");
+            let error = syn::Error::new(
+                after.span(),
+                format!(
+                    "cannot lift `{}` after `{after}`, as no other super block is labelled `{after}` or the order is circular",
+                    lift.ident,
+                ),
+            );
+
+            Err((lifts, error))
+        }
+    }
+}
+
+/// The leading options which affect the transformation.
+#[derive(Default)]
+struct Options {
+    /// Whether the `@dedup` option was given.
+    dedup: bool,
+    /// Whether the `@lazy` option was given.
+    lazy: bool,
+    /// The trait object given by the `@boxed(...)` option.
+    boxed: Option<TokenStream>,
+}
+
+/// Removes any leading options, which mostly don't affect the transformation.
+/// Also returns those which do, the `@dedup`, `@lazy` and `@boxed(...)` options.
+fn strip_options(input: TokenStream) -> (TokenStream, Options) {
+    let tokens = input.into_iter().collect::<Vec<_>>();
+    let mut index = 0;
+    let mut options = Options::default();
+
+    loop {
+        if !is_punct(tokens.get(index), '@') {
+            break;
+        }
+
+        if is_ident(tokens.get(index + 1), "boxed") {
+            match group(tokens.get(index + 2), Delimiter::Parenthesis) {
+                Some(bound) => options.boxed = Some(bound.stream()),
+                None => break,
+            }
+            index += 3;
+        } else if ["strict", "send", "sync", "static", "dedup", "lazy"]
+            .iter()
+            .any(|option| is_ident(tokens.get(index + 1), option))
+        {
+            options.dedup |= is_ident(tokens.get(index + 1), "dedup");
+            options.lazy |= is_ident(tokens.get(index + 1), "lazy");
+            index += 2;
+        } else {
+            break;
+        }
+    }
+
+    (tokens.into_iter().skip(index).collect(), options)
+}
+
+/// Boxes `output` as a trait object, as the `@boxed(...)` option does.
+fn boxed(bound: &TokenStream, output: TokenStream) -> TokenStream {
+    let mut bound = bound.clone().into_iter().peekable();
+
+    // Future<...> + Bounds, which is also pinned
+    if bound
+        .peek()
+        .map_or(false, |token| is_ident(Some(token), "Future"))
+    {
+        let bound = bound.skip(1).collect::<TokenStream>();
+
+        return quote! {{
+            let __soupa_boxed: ::soupa::__private::Pin<::soupa::__private::Box<dyn ::soupa::__private::Future #bound>> =
+                ::soupa::__private::Box::pin(#output);
+            __soupa_boxed
+        }};
+    }
+
+    let bound = bound.collect::<TokenStream>();
+
+    quote! {{
+        let __soupa_boxed: ::soupa::__private::Box<dyn #bound> = ::soupa::__private::Box::new(#output);
+        __soupa_boxed
+    }}
+}
+
+fn is_punct(token: Option<&TokenTree>, ch: char) -> bool {
+    matches!(token, Some(TokenTree::Punct(punct)) if punct.as_char() == ch)
+}
+
+fn is_ident(token: Option<&TokenTree>, name: &str) -> bool {
+    matches!(token, Some(TokenTree::Ident(ident)) if ident == name)
+}
+
+/// Whether `tokens` start with a `=>` token.
+fn is_fat_arrow(tokens: &[TokenTree]) -> bool {
+    matches!(tokens.first(), Some(TokenTree::Punct(punct)) if punct.as_char() == '=' && punct.spacing() == Spacing::Joint)
+        && is_punct(tokens.get(1), '>')
+}
+
+/// Whether the `=` at `index` is a token on its own, rather than part of one
+/// such as `==`, `<=` or `=>`.
+fn is_lone_eq(tokens: &[TokenTree], index: usize) -> bool {
+    let joined_before = index > 0
+        && matches!(&tokens[index - 1], TokenTree::Punct(punct) if punct.spacing() == Spacing::Joint);
+    let joined_after = matches!(&tokens[index], TokenTree::Punct(punct) if punct.spacing() == Spacing::Joint)
+        && (is_punct(tokens.get(index + 1), '=') || is_punct(tokens.get(index + 1), '>'));
+
+    !joined_before && !joined_after
+}
+
+/// Whether the `<` or `>` at `index` is an angle bracket, on its own or within
+/// `<<` or `>>`, rather than part of a token such as `->`, `=>`, `<=` or `>>=`.
+fn is_angle(tokens: &[TokenTree], index: usize) -> bool {
+    let joined = |index: usize| matches!(tokens.get(index), Some(TokenTree::Punct(punct)) if punct.spacing() == Spacing::Joint);
+    let bracket = match &tokens[index] {
+        TokenTree::Punct(punct) => punct.as_char(),
+        _ => return false,
+    };
+
+    // `->` or `=>`
+    if index > 0
+        && joined(index - 1)
+        && (is_punct(tokens.get(index - 1), '-') || is_punct(tokens.get(index - 1), '='))
+    {
+        return false;
+    }
+
+    // `<=` or `>=`, or the last bracket of `<<=` or `>>=`
+    if joined(index) && is_punct(tokens.get(index + 1), '=') {
+        return false;
+    }
+
+    // The first bracket of `<<=` or `>>=`
+    !(joined(index)
+        && is_punct(tokens.get(index + 1), bracket)
+        && joined(index + 1)
+        && is_punct(tokens.get(index + 2), '='))
+}
+
+/// Whether a lift marker following the tokens `before` it would continue a path.
+///
+/// A marker starting with `::` continues a path after any identifier, including
+/// keywords, while any other marker continues a path after a `::`.
+fn continues_path(before: &[TokenTree], leading: bool) -> bool {
+    if leading {
+        matches!(before.last(), Some(TokenTree::Ident(_)))
+    } else {
+        matches!(before, [.., TokenTree::Punct(first), TokenTree::Punct(second)]
+            if first.as_char() == ':' && first.spacing() == Spacing::Joint && second.as_char() == ':')
+    }
+}
+
+fn group(token: Option<&TokenTree>, delimiter: Delimiter) -> Option<&Group> {
+    match token {
+        Some(TokenTree::Group(group)) if group.delimiter() == delimiter => Some(group),
+        _ => None,
+    }
+}
+
+/// Whether a marker following the tokens `before` it begins a statement, as it
+/// follows a `;` or a block, or starts the contents of a block if `block` is set.
+fn begins_statement(before: &[TokenTree], block: bool) -> bool {
+    match before.last() {
+        None => block,
+        Some(TokenTree::Punct(punct)) => punct.as_char() == ';',
+        Some(TokenTree::Group(group)) => group.delimiter() == Delimiter::Brace,
+        Some(_) => false,
+    }
+}
+
+/// Filters outer attributes down to `#[cfg]` attributes, which are kept where a
+/// super block was written if it begins a statement. Elsewhere they would be
+/// attributes on an expression, which are unstable.
+fn cfg_attrs(attrs: &TokenStream) -> TokenStream {
+    let attrs = attrs.clone().into_iter().collect::<Vec<_>>();
+
+    attrs
+        .chunks(2)
+        .filter(|attr| match attr.get(1) {
+            Some(TokenTree::Group(group)) => {
+                let mut tokens = group.stream().into_iter();
+                is_ident(tokens.next().as_ref(), "cfg")
+            }
+            _ => false,
+        })
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+/// Matches a super block or lift marker, with any preceding outer attributes,
+/// at the start of `tokens`, which follow the tokens `before` it. These are
+/// the contents of a block if `block` is set.
+fn marker(before: &[TokenTree], tokens: &[TokenTree], block: bool) -> Option<Marker> {
+    let mut index = 0;
+
+    while is_punct(tokens.get(index), '#')
+        && group(tokens.get(index + 1), Delimiter::Bracket).is_some()
+    {
+        index += 2;
+    }
+
+    let attrs = tokens[..index].iter().cloned().collect::<TokenStream>();
+    let statement = begins_statement(before, block);
+
+    // super { ... }
+    if is_ident(tokens.get(index), "super") {
+        let span = tokens[index].span();
+
+        if let Some(block) = group(tokens.get(index + 1), Delimiter::Brace) {
+            return Some(Marker {
+                len: index + 2,
+                attrs,
+                statement,
+                label: None,
+                after: None,
+                block: block.clone(),
+                span,
+            });
+        }
+
+        // super(label) { ... } or super(label, after = other) { ... }
+        let args = group(tokens.get(index + 1), Delimiter::Parenthesis)?
+            .stream()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let (label, after) = match args.as_slice() {
+            [TokenTree::Ident(label)] => (label.clone(), None),
+            [TokenTree::Ident(label), TokenTree::Punct(comma), TokenTree::Ident(after), TokenTree::Punct(eq), TokenTree::Ident(other)]
+                if comma.as_char() == ',' && after == "after" && eq.as_char() == '=' =>
+            {
+                (label.clone(), Some(other.clone()))
+            }
+            _ => return None,
+        };
+        let block = group(tokens.get(index + 2), Delimiter::Brace)?;
+
+        return Some(Marker {
+            len: index + 3,
+            attrs,
+            statement,
+            label: Some(label),
+            after,
+            block: block.clone(),
+            span,
+        });
+    }
+
+    // $(::)? $(soupa ::)? lift ! ( ... )
+    let start = index;
+
+    if is_punct(tokens.get(index), ':') && is_punct(tokens.get(index + 1), ':') {
+        index += 2;
+    }
+
+    if is_ident(tokens.get(index), "soupa")
+        && is_punct(tokens.get(index + 1), ':')
+        && is_punct(tokens.get(index + 2), ':')
+    {
+        index += 3;
+    }
+
+    if !is_ident(tokens.get(index), "lift") || !is_punct(tokens.get(index + 1), '!') {
+        return None;
+    }
+
+    // A path ending in lift!, such as other::lift!(...), isn't a marker
+    if continues_path(before, index > start && is_punct(tokens.get(start), ':')) {
+        return None;
+    }
+
+    let args = match tokens.get(index + 2) {
+        Some(TokenTree::Group(args)) if args.delimiter() != Delimiter::None => args,
+        _ => return None,
+    };
+
+    let mut block = Group::new(Delimiter::Brace, args.stream());
+    block.set_span(args.span());
+
+    Some(Marker {
+        len: index + 3,
+        attrs,
+        statement,
+        label: None,
+        after: None,
+        block,
+        span: tokens[start].span(),
+    })
+}
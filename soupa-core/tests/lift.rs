@@ -0,0 +1,359 @@
+use quote::quote;
+use soupa::explain;
+use soupa_core::{Lifter, Naming};
+
+/// Checks that `soupa_core::expand` produces the same tokens as `soupa!`, as
+/// reported by `soupa::explain!`.
+macro_rules! assert_same_as_macro {
+    ($($body:tt)*) => {
+        assert_eq!(
+            normalize(&soupa_core::expand(quote!($($body)*)).to_string()),
+            normalize(explain!($($body)*)),
+        );
+    };
+}
+
+/// Removes whitespace, which neither `stringify!` nor `TokenStream` make
+/// guarantees about.
+fn normalize(tokens: &str) -> String {
+    tokens.split_whitespace().collect()
+}
+
+#[test]
+fn same_as_macro() {
+    assert_same_as_macro!(move || foo);
+    assert_same_as_macro!(move || *super { foo.clone() });
+    assert_same_as_macro!(move || (super { a() }, [super { b() }], { super { c() } }));
+    assert_same_as_macro!(move || {
+        #[cfg(unix)]
+        super(a) { a() }.b();
+        #[allow(unused)]
+        #[cfg(unix)]
+        super { b() }.c();
+    });
+    assert_same_as_macro!(move || {
+        let x = #[allow(unused)] super(a) { a() };
+        let y = #[allow(unused)] super(b, after = a) { b() };
+    });
+    assert_same_as_macro!(move || {
+        let x = #[cfg(unix)] super { a() };
+        if x {} #[cfg(unix)] super { b() }.c();
+        (#[cfg(unix)] super { d() }, [#[cfg(unix)] super(e) { e() }])
+    });
+    assert_same_as_macro!(move || (lift!(a()), soupa::lift![b()], ::soupa::lift! { c() }));
+    assert_same_as_macro!(move || (other::lift!(a()), other::soupa::lift!(b()), ::lift!(c())));
+    assert_same_as_macro!(if ::lift!(a()) {
+        move || (::lift!(b()), return ::lift!(c()))
+    });
+    assert_same_as_macro!(move || soupa!(move || super { foo.clone() }));
+    assert_same_as_macro!(@strict foo(super { a() }, move || super { b() }));
+    assert_same_as_macro!(@lazy move || f::<A, B>() + if x { super { a() } } else { super { b() } });
+    assert_same_as_macro!(@lazy move |x: Map<A, B>| x >= f::<Vec<Vec<u8>>, fn() -> A, B>() + if z {
+        super { a() }
+    } else {
+        super { b() }
+    });
+    assert_same_as_macro!(@send @static @sync move || super { foo.clone() });
+    assert_same_as_macro!(Handlers {
+        on_open: move || super { a() },
+        on_close: move |code| (code, super share(b) { b() }),
+        on_error: move || super share(b),
+    });
+    assert_same_as_macro!(move || (
+        super(b, after = a) { b() },
+        super { c() },
+        super(a) { a() },
+        super(d, after = b) { d() },
+    ));
+    assert_same_as_macro!(
+        pub(super) fn foo() {
+            super::bar()
+        }
+    );
+}
+
+#[test]
+fn tricky_tokens() {
+    assert_same_as_macro!(move || 'a: { break 'a super { x.clone() } });
+    assert_same_as_macro!(move || 'a: loop { break 'a *super { x.clone() } });
+    assert_same_as_macro!(move || (vec![super { a() }], format!("{}", super { b() }), !super { c() }));
+    assert_same_as_macro!(move || super::module::f({ super { a() } }));
+    assert_same_as_macro!(move || super::Foo { a: super { a() } });
+    assert_same_as_macro!({
+        pub(super) fn f() {}
+        pub(in super::m) struct S;
+        use super::{a, b};
+        #[allow(unused)]
+        super::super::g(super { x });
+    });
+    assert_same_as_macro!(move || {
+        #![allow(unused)]
+        (::core::mem::drop(super { a() }), self::super::f)
+    });
+}
+
+#[test]
+fn lifts_in_order() {
+    let lifts = soupa_core::lifts(quote!(move || (super(x) { a() }, { super { b() } })))
+        .map(|lift| {
+            (
+                lift.ident().to_string(),
+                lift.expr().to_string(),
+                lift.is_labelled(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        lifts,
+        [
+            (String::from("x"), String::from("a ()"), true),
+            (String::from("__soupa_temp_a"), String::from("b ()"), false),
+        ]
+    );
+}
+
+#[test]
+fn statements() {
+    let (stmts, body) =
+        soupa_core::lift(quote!(move || #[cfg(unix)] super { foo.clone() })).unwrap();
+
+    assert_eq!(stmts.len(), 1);
+    assert!(matches!(&stmts[0], syn::Stmt::Local(local) if local.attrs.len() == 1));
+    // The cfg attribute is only kept in place on a statement
+    assert_eq!(body.to_string(), quote!(move || __soupa_temp_a).to_string());
+
+    let (stmts, body) =
+        soupa_core::lift(quote!(move || { #[cfg(unix)] super { foo.clone() }.bar(); })).unwrap();
+
+    assert!(matches!(&stmts[0], syn::Stmt::Local(local) if local.attrs.len() == 1));
+    assert_eq!(
+        body.to_string(),
+        quote!(move || {
+            #[cfg(unix)]
+            __soupa_temp_a.bar();
+        })
+        .to_string()
+    );
+}
+
+#[test]
+fn readable_names() {
+    let lifted = Lifter::new()
+        .naming(Naming::Readable)
+        .lift(quote!(move || (super { foo.clone() }, super { foo.clone() }, super { Arc::clone(&bar) }, super { 1 })));
+
+    let names = lifted
+        .iter()
+        .map(|lift| lift.ident().to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        names,
+        [
+            "__soupa_foo_clone",
+            "__soupa_foo_clone_2",
+            "__soupa_arc_clone_bar",
+            "__soupa_temp"
+        ]
+    );
+}
+
+#[test]
+fn many_temps() {
+    let values = 0..300usize;
+    let lifted = Lifter::new().lift(quote!(#(super { #values })*));
+    let names = lifted
+        .iter()
+        .map(|lift| lift.ident().to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(names[25], "__soupa_temp_z");
+    assert_eq!(names[26], "__soupa_temp_aa");
+    assert_eq!(names[259], "__soupa_temp_iz");
+}
+
+#[test]
+fn ordering() {
+    let lifted = Lifter::new().lift(quote! {
+        move || (super(b, after = a) { b() }, super(a) { a() })
+    });
+
+    let order = lifted
+        .iter()
+        .map(|lift| lift.ident().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(order, ["a", "b"]);
+    assert_eq!(lifted.lifts()[1].after().unwrap(), "a");
+
+    let lifted = Lifter::new().lift(quote! {
+        move || super(b, after = a) { b() }
+    });
+
+    assert_eq!(
+        lifted.error().unwrap().to_string(),
+        "cannot lift `b` after `a`, as no other super block is labelled `a` or the order is circular",
+    );
+    assert!(lifted.into_stmts().is_err());
+}
+
+#[test]
+fn span_markers() {
+    // soupa! goes through its own re-exports of tracing, which soupa_core doesn't
+    fn tracing_paths(explained: &str) -> String {
+        normalize(explained)
+            .replace(
+                "$crate::__private::current_span()",
+                "::tracing::Span::current()",
+            )
+            .replace(
+                "$crate::__private::instrument",
+                "::tracing::Instrument::instrument",
+            )
+    }
+
+    macro_rules! assert_same_as_macro_with_tracing {
+        ($($body:tt)*) => {
+            assert_eq!(
+                normalize(&soupa_core::expand(quote!($($body)*)).to_string()),
+                tracing_paths(explain!($($body)*)),
+            );
+        };
+    }
+
+    assert_same_as_macro_with_tracing!(super span move || { super { foo.clone() } });
+    assert_same_as_macro_with_tracing!(super span(info_span!("job")) move |x: u32| -> u32 { x });
+    assert_same_as_macro_with_tracing!(spawn(super span async move { super { foo.clone() } }));
+    assert_same_as_macro_with_tracing!(super span(a()) async { b().await });
+
+    let lifted = Lifter::new()
+        .naming(Naming::Readable)
+        .lift(quote!(super span async move {}));
+    assert_eq!(lifted.lifts()[0].ident(), "__soupa_span");
+
+    let lifted = Lifter::new().lift(quote!(super span move || work()));
+    assert_eq!(
+        lifted.error().unwrap().to_string(),
+        "`super span` must be followed by an async block, or a closure with a block body",
+    );
+
+    let lifted = Lifter::new().lift(quote!(super span async move || {}));
+    assert_eq!(
+        lifted.error().unwrap().to_string(),
+        "`super span` can't be used with async closures, use an async block instead",
+    );
+}
+
+#[test]
+fn capture_adapters() {
+    // soupa! refers to itself through $crate, which soupa_core can't
+    macro_rules! assert_same_as_macro_with_soupa {
+        ($($body:tt)*) => {
+            assert_eq!(
+                normalize(&soupa_core::expand(quote!($($body)*)).to_string()),
+                normalize(explain!($($body)*)).replace("$crate::", "::soupa::"),
+            );
+        };
+    }
+
+    assert_same_as_macro_with_soupa!(move || super with Cloned { &foo });
+    assert_same_as_macro_with_soupa!(move || super with soupa::adapters::Downgrade { &foo }.is_some());
+    assert_same_as_macro_with_soupa!(move || (
+        super(b, after = a) with Cloned { &foo },
+        super(a) with Length { "foo" },
+    ));
+
+    let lifted = Lifter::new()
+        .naming(Naming::Readable)
+        .lift(quote!(move || super with Cloned { &foo }));
+    assert_eq!(lifted.lifts()[0].ident(), "__soupa_cloned_foo");
+
+    // Without a block, `super with` is passed through for the compiler to reject
+    let lifted = Lifter::new().lift(quote!(move || super with Cloned));
+    assert!(lifted.lifts().is_empty());
+}
+
+#[test]
+fn shared_super_blocks() {
+    assert_same_as_macro!((move || super share(a) { a() }, move || super share(a)));
+    assert_same_as_macro!((
+        move || (super share(x) { 1 }, super share(y) { 2 }),
+        move || (super share(y), super(z, after = x) { 3 }, super share(x)),
+        move || super share(y),
+    ));
+    assert_same_as_macro!(move || super share(a));
+
+    let lifted = Lifter::new()
+        .naming(Naming::Readable)
+        .lift(quote!((move || super share(a) { a() }, move || super share(a))));
+    let names = lifted
+        .iter()
+        .map(|lift| lift.ident().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["a", "__soupa_a", "__soupa_a_2"]);
+
+    let lifted =
+        Lifter::new().lift(quote!((move || super share(a), move || super share(a) { a() })));
+    assert_eq!(
+        lifted.error().unwrap().to_string(),
+        "the block of `super share(a) { ... }` must be given at its first use",
+    );
+}
+
+#[test]
+fn boxed_output() {
+    // soupa! refers to itself through $crate, which soupa_core can't
+    macro_rules! assert_same_as_macro_with_soupa {
+        ($($body:tt)*) => {
+            assert_eq!(
+                normalize(&soupa_core::expand(quote!($($body)*)).to_string()),
+                normalize(explain!($($body)*)).replace("$crate::", "::soupa::"),
+            );
+        };
+    }
+
+    assert_same_as_macro_with_soupa!(@boxed(Fn(Event) + Send) move |event| super { a() }.record(event));
+    assert_same_as_macro_with_soupa!(@send @boxed(Future<Output = ()> + Send + 'static) async move {
+        super { a() }.await
+    });
+    assert_same_as_macro_with_soupa!(@dedup @boxed(Fn() -> usize) move || super { a() } + super { a() });
+
+    let (stmts, body) = soupa_core::lift(quote!(@boxed(Fn()) move || super { a() })).unwrap();
+    assert_eq!(stmts.len(), 1);
+    assert_eq!(
+        normalize(&body.to_string()),
+        "{let__soupa_boxed:::soupa::__private::Box<dynFn()>=::soupa::__private::Box::new(move||__soupa_temp_a);__soupa_boxed}"
+    );
+}
+
+#[test]
+fn deduplication() {
+    assert_same_as_macro!(@dedup (move || super { a() }, move || (super { b() }, super { a() })));
+    assert_same_as_macro!(@strict @dedup (
+        move || (super { a() }, lift!(a()), super(x) { a() }, #[cfg(all())] super { a() }),
+        move || (super share(s) { a() }, super(y, after = x) { a() }, super share(s)),
+    ));
+    assert_same_as_macro!((move || super { a() }, move || super { a() }));
+
+    let lifted = Lifter::new().lift(quote!(@dedup (move || super { a() }, move || super { a() })));
+    assert_eq!(
+        lifted.lifts()[1].expr().to_string(),
+        "__soupa_temp_a . clone ()"
+    );
+}
+
+#[test]
+fn lazy_branches() {
+    assert_eq!(
+        normalize(&soupa_core::expand(quote!(@lazy if let Some(x) = y { move || super { a() } } else { move || super { b() } })).to_string()),
+        "{ifletSome(x)=y{{let__soupa_temp_a={a()};move||__soupa_temp_a}}else{{let__soupa_temp_a={b()};move||__soupa_temp_a}}}"
+    );
+    assert_eq!(
+        normalize(&soupa_core::expand(quote!(@lazy match x { 1 => { move || super { a() } } _ => move || super { b() } })).to_string()),
+        "{let__soupa_temp_a={b()};matchx{1=>{{let__soupa_temp_a={a()};move||__soupa_temp_a}}_=>move||__soupa_temp_a}}"
+    );
+    assert_eq!(
+        normalize(&soupa_core::expand(quote!(@lazy move || if x { super { a() } } else { super { b() } })).to_string()),
+        "{let__soupa_temp_a={a()};let__soupa_temp_b={b()};move||ifx{__soupa_temp_a}else{__soupa_temp_b}}"
+    );
+}
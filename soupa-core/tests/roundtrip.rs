@@ -0,0 +1,252 @@
+//! Property-based checks of the transformation, using random token trees.
+//!
+//! Each case is generated from its seed, and checked against two invariants:
+//!
+//! * Input without super blocks is output unchanged, wrapped in a single block.
+//! * Each super block outside of another becomes exactly one init statement, in
+//!   the order they appear, and is replaced by the name it binds.
+//!
+//! The checks are written against [`Backend`], so are shared by `soupa_core` and
+//! `soupa!`.
+//! As `soupa!` can only expand tokens known at compile time, its cases are kept in
+//! `roundtrip/cases.rs`, which is regenerated by running this test with
+//! `SOUPA_ROUNDTRIP=overwrite`.
+
+use std::{collections::HashMap, env, fs, path::Path};
+
+use proc_macro2::{Delimiter, Group, Ident, TokenStream, TokenTree};
+use quote::ToTokens;
+use soupa::explain;
+use soupa_core::{Lifter, Naming};
+
+#[path = "roundtrip/generate.rs"]
+mod generate;
+
+/// The number of cases checked against `soupa_core`.
+const CASES: u64 = 2000;
+
+/// The number of cases checked against `soupa!`, which are compiled into this test.
+const MACRO_CASES: u64 = 64;
+
+/// Performs the transformation of `soupa!`.
+trait Backend {
+    fn expand(&self, input: &TokenStream) -> TokenStream;
+}
+
+impl Backend for Lifter {
+    fn expand(&self, input: &TokenStream) -> TokenStream {
+        self.lift(input.clone()).into_token_stream()
+    }
+}
+
+/// `soupa!` itself, which can only expand the cases in `roundtrip/cases.rs`.
+struct MacroRules(HashMap<String, TokenStream>);
+
+impl MacroRules {
+    fn new() -> Self {
+        macro_rules! case {
+            ($($tokens:tt)*) => {
+                explain!($($tokens)*)
+            };
+        }
+
+        let cases: &[&str] = &include!("roundtrip/cases.rs");
+
+        // Cases are in the order of their seeds
+        MacroRules(
+            (0..)
+                .zip(cases)
+                .map(|(seed, output)| (generate::case(seed).to_string(), parse(output)))
+                .collect(),
+        )
+    }
+}
+
+impl Backend for MacroRules {
+    fn expand(&self, input: &TokenStream) -> TokenStream {
+        match self.0.get(&input.to_string()) {
+            Some(output) => output.clone(),
+            None => {
+                panic!("`{input}` isn't in roundtrip/cases.rs, run with SOUPA_ROUNDTRIP=overwrite")
+            }
+        }
+    }
+}
+
+fn parse(tokens: &str) -> TokenStream {
+    tokens.parse().unwrap()
+}
+
+/// Checks every case up to `cases` against `backend`.
+fn check_backend(backend: &dyn Backend, cases: u64) {
+    for seed in 0..cases {
+        let input = generate::case(seed);
+        let output = backend.expand(&input);
+
+        if let Err(error) = check(&input, &output) {
+            panic!("case {seed} failed: {error}\ninput: {input}\noutput: {output}");
+        }
+    }
+}
+
+/// Checks the output of a backend for `input` upholds both invariants.
+fn check(input: &TokenStream, output: &TokenStream) -> Result<(), String> {
+    let mut trees = output.clone().into_iter();
+
+    let block = match (trees.next(), trees.next()) {
+        (Some(TokenTree::Group(block)), None) if block.delimiter() == Delimiter::Brace => block,
+        _ => return Err("output isn't a single block".into()),
+    };
+
+    let mut blocks = Vec::new();
+    super_blocks(input.clone(), &mut blocks);
+
+    // Each init statement is `let name = { ... };`
+    let mut tokens = block.stream().into_iter().collect::<Vec<_>>();
+    let mut names = Vec::new();
+
+    for (index, expected) in blocks.iter().enumerate() {
+        match &tokens[..] {
+            [TokenTree::Ident(let_), TokenTree::Ident(name), TokenTree::Punct(eq), TokenTree::Group(init), TokenTree::Punct(semi), ..]
+                if let_ == "let" && eq.as_char() == '=' && semi.as_char() == ';' =>
+            {
+                if init.stream().to_string() != expected.to_string() {
+                    return Err(format!(
+                        "init statement {index} is `{init}`, rather than `{{ {expected} }}`"
+                    ));
+                }
+                names.push(name.clone());
+            }
+            _ => {
+                return Err(format!(
+                    "expected {} init statements, found {index}",
+                    blocks.len()
+                ))
+            }
+        }
+        tokens.drain(..5);
+    }
+
+    // Everything else is the input, with each super block replaced by its name
+    let mut names = names.into_iter();
+    let body = replace(input.clone(), &mut names);
+    let rest = tokens.into_iter().collect::<TokenStream>();
+
+    if rest.to_string() != body.to_string() {
+        return Err(format!("body is `{rest}`, rather than `{body}`"));
+    }
+
+    Ok(())
+}
+
+/// Collects the contents of each super block which isn't within another.
+fn super_blocks(input: TokenStream, blocks: &mut Vec<TokenStream>) {
+    let mut trees = input.into_iter().peekable();
+
+    while let Some(tree) = trees.next() {
+        match tree {
+            TokenTree::Ident(ident) if ident == "super" => {
+                if let Some(TokenTree::Group(block)) = trees.next() {
+                    blocks.push(block.stream());
+                }
+            }
+            TokenTree::Group(group) => super_blocks(group.stream(), blocks),
+            _ => {}
+        }
+    }
+}
+
+/// Replaces each super block which isn't within another with the next of `names`.
+fn replace(input: TokenStream, names: &mut dyn Iterator<Item = Ident>) -> TokenStream {
+    let mut output = TokenStream::new();
+    let mut trees = input.into_iter();
+
+    while let Some(tree) = trees.next() {
+        match tree {
+            TokenTree::Ident(ident) if ident == "super" => {
+                trees.next();
+                output.extend([TokenTree::Ident(names.next().unwrap())]);
+            }
+            TokenTree::Group(group) => {
+                let mut replaced = Group::new(group.delimiter(), replace(group.stream(), names));
+                replaced.set_span(group.span());
+                output.extend([TokenTree::Group(replaced)]);
+            }
+            tree => output.extend([tree]),
+        }
+    }
+
+    output
+}
+
+#[test]
+fn without_super_blocks() {
+    // Only checks the first invariant, with the generator's super blocks removed
+    for seed in 0..CASES {
+        let input = strip(generate::case(seed));
+        let output = soupa_core::expand(input.clone());
+
+        assert_eq!(output.to_string(), format!("{{ {input} }}"), "case {seed}");
+    }
+}
+
+#[test]
+fn proc_macro() {
+    check_backend(&Lifter::new(), CASES);
+    check_backend(&Lifter::new().naming(Naming::Readable), CASES);
+}
+
+#[test]
+fn macro_rules() {
+    check_backend(&MacroRules::new(), MACRO_CASES);
+}
+
+#[test]
+fn cases_are_current() {
+    let mut cases = String::from(
+        "// Generated by the `roundtrip` test, run with `SOUPA_ROUNDTRIP=overwrite` to update.\n[\n",
+    );
+
+    for seed in 0..MACRO_CASES {
+        cases.push_str(&format!("    case! {{ {} }},\n", generate::case(seed)));
+    }
+
+    cases.push_str("]\n");
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roundtrip/cases.rs");
+
+    if env::var("SOUPA_ROUNDTRIP").as_deref() == Ok("overwrite") {
+        fs::write(path, cases).unwrap();
+    } else {
+        assert!(
+            fs::read_to_string(path).unwrap() == cases,
+            "roundtrip/cases.rs is out of date, run with SOUPA_ROUNDTRIP=overwrite"
+        );
+    }
+}
+
+/// Replaces each super block with its contents.
+fn strip(input: TokenStream) -> TokenStream {
+    let mut output = TokenStream::new();
+    let mut trees = input.into_iter();
+
+    while let Some(tree) = trees.next() {
+        match tree {
+            TokenTree::Ident(ident) if ident == "super" => {
+                if let Some(TokenTree::Group(block)) = trees.next() {
+                    output.extend([TokenTree::Group(Group::new(
+                        Delimiter::Brace,
+                        strip(block.stream()),
+                    ))]);
+                }
+            }
+            TokenTree::Group(group) => output.extend([TokenTree::Group(Group::new(
+                group.delimiter(),
+                strip(group.stream()),
+            ))]),
+            tree => output.extend([tree]),
+        }
+    }
+
+    output
+}
@@ -0,0 +1,67 @@
+// Generated by the `roundtrip` test, run with `SOUPA_ROUNDTRIP=overwrite` to update.
+[
+    case! { , - },
+    case! { { () super { } 'a 'static } super { } self | 's' (super { [> * /] { & "soupa" } & } ; in) },
+    case! { () [] super { super { 24usize [/ < 14.0 'static] 's' } 's' } [] },
+    case! { (- "soupa" ()) "soupa" 'a { 'static [self clone 6.0 (self)] } super { } ("soupa" ["soupa" 'a] super { (+ % move) super { : } a % }) 43usize { loop } },
+    case! { 'a { () { { for 'static } b } 48usize b"soupa" } [((b"soupa") { self 'a 'static } 'static clone)] (? b"soupa" b"soupa") super { clone { } [- ['a 'static 'static]] self } clone },
+    case! { 'static { (super { for b"soupa" } > ['static 'static =]) { for 's' super { 's' } } > + } },
+    case! { ! 'a { (; 14.75) } 0.25 'static [< move - [super { > ? . 17.25 } in (8.5 98usize . <) (for 'static 20.5)]] 'static foo },
+    case! { super { in [{ b 'static }] } { ; { super { "soupa" 'a < loop } , { } } 'static } 'static },
+    case! { [{ [12.5 'a in] [< , 'a] () foo }] },
+    case! { * 's' b"soupa" () move },
+    case! { { } in 'static },
+    case! { "soupa" : b { clone (() (11.25 clone 93usize) super { "soupa" , 's' 'a } super { in }) } "soupa" () () },
+    case! { a clone clone 'a a },
+    case! { (super { clone b"soupa" { loop % loop } [6usize = move in] } 'a { + } (super { 9usize } 'static)) 3.5 { (loop "soupa") } % },
+    case! { { } (('static)) { [! ['static loop 's'] super { 38usize } { 'static }] clone } () b 'a },
+    case! { { { ! ['a] super { b"soupa" 'a 'static } } 's' } 'a },
+    case! { + { < { } { { 'a } { 'static 0.0 'a } 'a } } move super { { super { b"soupa" } } } },
+    case! { in = < { [('a 'a foo)] for () } super { . super { [loop b self 11.0] [in 'a in] < } } ({ [foo 'static] ? } ? in) ! b"soupa" },
+    case! { 21.25 clone },
+    case! { () move [] super { } (() [{ > 'a }] "soupa") },
+    case! { { super { b"soupa" 'static < } super { 'static in super { a 3.5 "soupa" loop } } { } } ("soupa" [super { b"soupa" / } 'static ['a ? 69usize]] loop 'static) { } [] { (b"soupa" 'static) < "soupa" self } { } = },
+    case! { 18.25 super { { 'a [>] () super { } } } foo },
+    case! { [super { move { clone } 21.5 [2.5 60usize 'static] } [super { 'static 16.25 88usize } 'a] 22.75 []] [b 1.75] { (70usize) [] (/ clone) } 'static ('static) },
+    case! { - },
+    case! { 'static { { } + } ({ 'a super { } 'a & }) },
+    case! { 'a b"soupa" },
+    case! { < in clone [self > ({ 'a move 'a b"soupa" })] { } 'a for super { } },
+    case! { * super { } [super { (= "soupa" b) } super { self super { * 'a ; } "soupa" : } ((b"soupa") foo) super { foo }] ('static super { ['a |] }) },
+    case! { { { 's' } } { 'a loop 'static [super { } for] } },
+    case! { { super { } } move = [move "soupa"] { loop / } "soupa" },
+    case! { (in ('a [clone 'a b"soupa" b"soupa"] self 'static) ;) { 'a move } ? 'static },
+    case! { b"soupa" in 2.75 ['a 5.5 'static [{ "soupa" in in } ['a 76usize 91usize]]] ["soupa" 23.0 super { } foo] 'a | },
+    case! { clone 'static [*] [] { loop ! } },
+    case! { 'a },
+    case! { for super { } b"soupa" 15usize move { a { (16.75 a ;) { "soupa" } 'static clone } } { { 's' ['a *] } [(in) 'static :] 'a } },
+    case! { clone { [* ; 'static] self ([92usize for >] (&) super { 'static ! 'a b"soupa" } { 'static & 'static = }) { ('static 'a =) ('static % b"soupa" 21.75) [] } } [] },
+    case! { { [] ([] 'a { "soupa" | 'a "soupa" } ('a , 'a a)) { super { 'static } move super { 'a : } } [super { 35usize 89usize }] } },
+    case! { { () self { () } } b { } - { super { } (super { / 'static } 18.5 (move) super { 'static 's' }) } super { clone super { super { foo } 60usize () super { in 'a 90usize } } "soupa" } { 'static ('a { 'a / }) for } * },
+    case! { b = },
+    case! { (super { b"soupa" } b clone) / "soupa" (!) [[super { a a 's' ? }] a b"soupa" b"soupa"] 'a },
+    case! { super { ! } "soupa" { } [40usize] : / [b"soupa" { 'static } b { super { 's' 'a 'a } : { ! ; } }] foo },
+    case! { 'a super { a } 'a b"soupa" },
+    case! { [] super { { 'a 'a a } { foo ['static] 'static } move } super { { * } super { for super { } } } "soupa" super { [] super { super { } } { { 's' } , ['static 'static 'a 's'] } super { } } & },
+    case! { super { super { 'a super { % } 'a = } } },
+    case! { ? 'a loop },
+    case! { ? 95usize super { } super { } super { super { 9.25 'a { ! a / / } [] } [77usize 's' +] 'static { [79usize ; 22.25] foo clone } } [] b"soupa" },
+    case! { 90usize },
+    case! { "soupa" 'a { 'a } | b"soupa" },
+    case! { ([] 21.75 [for] +) for / 'static b ? 'static },
+    case! { in [[[b"soupa"]] self { 99usize } super { super { 'static 10.25 & 21usize } [+] { } }] 'a super { { clone } } super { } super { - super { ['static & 's' 37usize] > 24usize { clone 11usize } } : super { 'static super { } self } } },
+    case! { super { } 'static 'static (.) },
+    case! { 'a a 'a super { (['static & 'static] (; 'static) 's') { loop 'a ? [34usize 'a foo] } super { } { super { * } } } (/ 'a [a { self 9.75 } self] foo) (super { / super { b"soupa" | } } 's') super { ("soupa" a [] super { b move 's' }) 'a clone ! } { ['static [< self a &]] [{ b"soupa" 's' 'static 13.0 } a + *] ! } },
+    case! { for b"soupa" { ('static { self b"soupa" } [] !) { ("soupa" 'static 'static) (16usize) } { } > } super { super { clone } 's' ["soupa" in (75usize - % 'static) 'static] super { foo (% + 'a) } } b 48usize },
+    case! { (.) super { : ("soupa" { } (clone 's' 'static)) [self { 's' 'a } "soupa"] } },
+    case! { 's' super { a } [{ }] ['static super { loop } 16.0] 'static super { super { 'static { 'a } () for } for "soupa" } { super { + "soupa" } super { [in a >] ; super { foo } } } 'static },
+    case! { move ('static 'static 87usize) ({ super { move self self } } 12.25) },
+    case! { { } },
+    case! { { : [clone ('a .) 40usize] { [] { b"soupa" ; } ['static 16.0] { b 's' clone 'a } } } 'a (for <) 'a (/ <) },
+    case! { ['s'] ["soupa" *] < },
+    case! { [self b"soupa" b"soupa"] 'a | self ('s' = 22.75 /) ; ({ 82usize }) },
+    case! { 'a [(['static 'static loop .]) for 's' b] { 'static { "soupa" } } 'static 'static },
+    case! { a { } 1.75 'static },
+    case! { [] b"soupa" "soupa" (9.75 'a) super { 's' { } } super { in { "soupa" super { a 'a } } & } },
+    case! { + 11.75 },
+]
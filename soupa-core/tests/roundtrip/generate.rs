@@ -0,0 +1,101 @@
+//! Generates random balanced token trees, containing super blocks.
+
+use proc_macro2::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+/// Identifiers which aren't treated specially by either backend outside of a
+/// super block, along with some which are only used for lints.
+const IDENTS: &[&str] = &[
+    "a", "b", "foo", "clone", "move", "for", "loop", "in", "self",
+];
+
+/// Punctuation which isn't treated specially by either backend.
+///
+/// `#` is left out, as attributes before a super block are copied onto its
+/// init statement, and `@` as leading options change the transformation.
+const PUNCTS: &[char] = &[
+    '+', '-', '*', '/', '.', ',', ';', ':', '=', '<', '>', '!', '&', '|', '?', '%',
+];
+
+/// A small xorshift generator, so cases can be reproduced from their seed.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Zero is a fixed point of xorshift
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+/// Generates the token trees for the case with the given seed.
+pub fn case(seed: u64) -> TokenStream {
+    let mut rng = Rng::new(seed);
+    let len = 1 + rng.below(8);
+    stream(&mut rng, 3, len)
+}
+
+fn stream(rng: &mut Rng, depth: usize, len: usize) -> TokenStream {
+    let mut tokens = TokenStream::new();
+
+    for _ in 0..len {
+        tokens.extend(tree(rng, depth));
+    }
+
+    tokens
+}
+
+fn tree(rng: &mut Rng, depth: usize) -> TokenStream {
+    let span = Span::call_site();
+    let len = rng.below(5);
+
+    match rng.below(if depth == 0 { 4 } else { 8 }) {
+        0 => TokenTree::Ident(Ident::new(IDENTS[rng.below(IDENTS.len())], span)).into(),
+        1 => TokenTree::Punct(Punct::new(*rng.pick(PUNCTS), Spacing::Alone)).into(),
+        2 => TokenTree::Literal(literal(rng)).into(),
+        3 => {
+            // 'a, or 'static
+            let name = if rng.below(2) == 0 { "a" } else { "static" };
+            let mut tokens = TokenStream::from(TokenTree::Punct(Punct::new('\'', Spacing::Joint)));
+            tokens.extend([TokenTree::Ident(Ident::new(name, span))]);
+            tokens
+        }
+        4 => group(Delimiter::Parenthesis, stream(rng, depth - 1, len)),
+        5 => group(Delimiter::Bracket, stream(rng, depth - 1, len)),
+        6 => group(Delimiter::Brace, stream(rng, depth - 1, len)),
+        _ => {
+            // super { ... }, which may itself contain super blocks
+            let mut tokens = TokenStream::from(TokenTree::Ident(Ident::new("super", span)));
+            tokens.extend(group(Delimiter::Brace, stream(rng, depth - 1, len)));
+            tokens
+        }
+    }
+}
+
+fn group(delimiter: Delimiter, stream: TokenStream) -> TokenStream {
+    TokenTree::Group(Group::new(delimiter, stream)).into()
+}
+
+fn literal(rng: &mut Rng) -> Literal {
+    match rng.below(5) {
+        // Suffixed, as stringify! can join an unsuffixed integer and a following `.`
+        0 => Literal::usize_suffixed(rng.below(100)),
+        1 => Literal::f64_unsuffixed(rng.below(100) as f64 / 4.0),
+        2 => Literal::string("soupa"),
+        3 => Literal::character('s'),
+        _ => Literal::byte_string(b"soupa"),
+    }
+}
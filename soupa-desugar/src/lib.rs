@@ -0,0 +1,679 @@
+//! Rewrites `soupa!` invocations in Rust sources into the plain Rust they expand to.
+//!
+//! Each invocation is replaced with the block produced by [`soupa_core`], formatted,
+//! and with its temporaries given readable names.
+//! Everything else in the source, including comments, is left untouched.
+//! Comments within an invocation are not preserved, and neither are any lints
+//! `soupa!` would emit, such as those enabled by `@strict`.
+//! Invocations using `@send`, `@sync` or `@static` are left as they are, as the
+//! checks those options add would be lost, and so are invocations of `soupa`'s
+//! other macros, such as `explain!` and `spawn!`.
+//! The output of `@boxed(...)` names the boxing types through `std`, while
+//! invocations using capture adapters, as in `super with Cloned { ... }`, are
+//! left as they are, since restoring their values relies on items private to
+//! `soupa`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! let desugared = soupa_desugar::desugar(
+//!     "let func = soupa!(move || super { foo.clone() });",
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(
+//!     desugared.source,
+//!     "let func = {\n    let __soupa_foo_clone = { foo.clone() };\n    move || __soupa_foo_clone\n};",
+//! );
+//! ```
+
+use std::{error, fmt, ops::Range};
+
+use proc_macro2::{Delimiter, LineColumn, Spacing, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+
+use soupa_core::Lifter;
+pub use soupa_core::Naming;
+use syn::{spanned::Spanned, visit::Visit};
+
+/// Rewrites all `soupa!` invocations within `source` using the default configuration.
+///
+/// # Errors
+///
+/// Fails if `source` could not be tokenized.
+pub fn desugar(source: &str) -> Result<Desugared, Error> {
+    Desugarer::new().desugar(source)
+}
+
+/// Configurable entry point for rewriting sources.
+#[derive(Clone, Debug)]
+pub struct Desugarer {
+    naming: Naming,
+    strip_imports: bool,
+}
+
+impl Default for Desugarer {
+    fn default() -> Self {
+        Self {
+            naming: Naming::Readable,
+            strip_imports: true,
+        }
+    }
+}
+
+/// The result of rewriting a source file.
+#[derive(Clone, Debug)]
+pub struct Desugared {
+    /// The rewritten source.
+    pub source: String,
+    /// The number of invocations which were rewritten.
+    pub rewritten: usize,
+    /// The location of invocations which could not be rewritten, as their
+    /// expansion was not a valid expression or relied on items private to
+    /// `soupa`, they use an option adding checks which would be lost, or they
+    /// are of a macro other than `soupa!`.
+    pub skipped: Vec<LineColumn>,
+}
+
+/// An error encountered while rewriting a source file.
+#[derive(Debug)]
+pub enum Error {
+    /// The source could not be tokenized.
+    Lex(proc_macro2::LexError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Lex(error) => write!(f, "failed to tokenize source: {error}"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<proc_macro2::LexError> for Error {
+    fn from(error: proc_macro2::LexError) -> Self {
+        Error::Lex(error)
+    }
+}
+
+impl Desugarer {
+    /// Creates a [`Desugarer`] with the default configuration.
+    ///
+    /// By default, temporaries are given readable names and imports of the
+    /// `soupa!` macro are removed once no invocations remain.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how the temporaries for unlabelled super blocks are named.
+    ///
+    /// Using [`Naming::Temps`] produces exactly the output of `soupa!`.
+    pub fn naming(mut self, naming: Naming) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Sets whether imports of the `soupa!` macro are removed once every
+    /// invocation within a source has been rewritten.
+    ///
+    /// Only the `soupa` name is removed from a `use soupa::...` item, so other
+    /// items imported alongside it are kept.
+    /// A `use soupa` or `extern crate soupa` item is only removed if nothing
+    /// else from the crate is referenced.
+    pub fn strip_imports(mut self, strip_imports: bool) -> Self {
+        self.strip_imports = strip_imports;
+        self
+    }
+
+    /// Rewrites all `soupa!` invocations within `source`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `source` could not be tokenized.
+    pub fn desugar(&self, source: &str) -> Result<Desugared, Error> {
+        let tokens = source.parse::<TokenStream>()?;
+
+        let mut invocations = Vec::new();
+        let mut skipped = Vec::new();
+        find_invocations(tokens, &mut invocations, &mut skipped);
+
+        let mut edits = Vec::new();
+
+        for invocation in &invocations {
+            if !supported(&invocation.body) {
+                skipped.push(invocation.start);
+                continue;
+            }
+
+            let Some(expansion) = public_paths(self.expand(invocation.body.clone())) else {
+                skipped.push(invocation.start);
+                continue;
+            };
+
+            match format_expr(expansion) {
+                Some(formatted) => {
+                    let indent = line_indent(source, invocation.range.start);
+                    let formatted = formatted.replace('\n', &format!("\n{indent}"));
+                    edits.push((invocation.range.clone(), formatted));
+                }
+                None => skipped.push(invocation.start),
+            }
+        }
+
+        skipped.sort_by_key(|start| (start.line, start.column));
+
+        let rewritten = edits.len();
+
+        if self.strip_imports && skipped.is_empty() && !edits.is_empty() {
+            let imports = strip_imports(source, &apply(source, edits.clone()));
+            edits.extend(imports);
+        }
+
+        Ok(Desugared {
+            source: apply(source, edits),
+            rewritten,
+            skipped,
+        })
+    }
+
+    /// Expands the body of a `soupa!` invocation, including any nested invocations.
+    fn expand(&self, body: TokenStream) -> TokenStream {
+        let expansion = Lifter::new()
+            .naming(self.naming)
+            .lift(body)
+            .into_token_stream();
+
+        self.expand_nested(expansion)
+    }
+
+    fn expand_nested(&self, tokens: TokenStream) -> TokenStream {
+        let tokens = tokens.into_iter().collect::<Vec<_>>();
+        let mut output = TokenStream::new();
+        let mut index = 0;
+
+        while index < tokens.len() {
+            if let Some((len, body)) = invocation(&tokens[index..]) {
+                output.extend(self.expand(body));
+                index += len;
+                continue;
+            }
+
+            match &tokens[index] {
+                TokenTree::Group(group) => {
+                    let mut inner = proc_macro2::Group::new(
+                        group.delimiter(),
+                        self.expand_nested(group.stream()),
+                    );
+                    inner.set_span(group.span());
+                    output.extend([TokenTree::Group(inner)]);
+                }
+                token => output.extend([token.clone()]),
+            }
+
+            index += 1;
+        }
+
+        output
+    }
+}
+
+/// A `soupa!` invocation found within a source.
+struct Invocation {
+    range: Range<usize>,
+    start: LineColumn,
+    body: TokenStream,
+}
+
+fn is_punct(token: Option<&TokenTree>, ch: char) -> bool {
+    matches!(token, Some(TokenTree::Punct(punct)) if punct.as_char() == ch)
+}
+
+fn is_ident(token: Option<&TokenTree>, name: &str) -> bool {
+    matches!(token, Some(TokenTree::Ident(ident)) if ident == name)
+}
+
+/// Matches `$(::)? $(soupa ::)? soupa ! (...)` at the start of `tokens`,
+/// returning the number of tokens matched and the body of the invocation.
+fn invocation(tokens: &[TokenTree]) -> Option<(usize, TokenStream)> {
+    let mut index = 0;
+
+    if is_punct(tokens.first(), ':') && is_punct(tokens.get(1), ':') {
+        index += 2;
+    }
+
+    if is_ident(tokens.get(index), "soupa")
+        && is_punct(tokens.get(index + 1), ':')
+        && is_punct(tokens.get(index + 2), ':')
+    {
+        index += 3;
+    }
+
+    if !is_ident(tokens.get(index), "soupa") || !is_punct(tokens.get(index + 1), '!') {
+        return None;
+    }
+
+    match tokens.get(index + 2) {
+        Some(TokenTree::Group(group)) if group.delimiter() != Delimiter::None => {
+            Some((index + 3, group.stream()))
+        }
+        _ => None,
+    }
+}
+
+/// Matches `$(::)? $(soupa ::)? $(thread ::)? name ! (...)` at the start of
+/// `tokens`, for the macros of `soupa` other than `soupa!`, returning the number
+/// of tokens matched.
+fn other_invocation(tokens: &[TokenTree]) -> Option<usize> {
+    let mut index = 0;
+
+    if is_punct(tokens.first(), ':') && is_punct(tokens.get(1), ':') {
+        index += 2;
+    }
+
+    for module in ["soupa", "thread"] {
+        if is_ident(tokens.get(index), module)
+            && is_punct(tokens.get(index + 1), ':')
+            && is_punct(tokens.get(index + 2), ':')
+        {
+            index += 3;
+        }
+    }
+
+    if !["explain", "spawn", "spawn_named"]
+        .iter()
+        .any(|name| is_ident(tokens.get(index), name))
+        || !is_punct(tokens.get(index + 1), '!')
+    {
+        return None;
+    }
+
+    match tokens.get(index + 2) {
+        Some(TokenTree::Group(group)) if group.delimiter() != Delimiter::None => Some(index + 3),
+        _ => None,
+    }
+}
+
+/// Whether a macro at the start of `tokens`, which follow the tokens `before`
+/// it, would continue a path, such as `other::soupa!(...)`.
+fn continues_path(before: &[TokenTree], tokens: &[TokenTree]) -> bool {
+    if is_punct(tokens.first(), ':') {
+        matches!(before.last(), Some(TokenTree::Ident(_)))
+    } else {
+        matches!(before, [.., TokenTree::Punct(first), TokenTree::Punct(second)]
+            if first.as_char() == ':' && first.spacing() == Spacing::Joint && second.as_char() == ':')
+    }
+}
+
+/// Finds the outermost `soupa!` invocations within `tokens`, along with the
+/// location of any invocations of `soupa`'s other macros, which can't be
+/// rewritten.
+fn find_invocations(
+    tokens: TokenStream,
+    invocations: &mut Vec<Invocation>,
+    skipped: &mut Vec<LineColumn>,
+) {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let mut index = 0;
+
+    while index < tokens.len() {
+        if continues_path(&tokens[..index], &tokens[index..]) {
+            index += 1;
+            continue;
+        }
+
+        if let Some((len, body)) = invocation(&tokens[index..]) {
+            let start = tokens[index].span();
+            let end = tokens[index + len - 1].span();
+
+            invocations.push(Invocation {
+                range: start.byte_range().start..end.byte_range().end,
+                start: start.start(),
+                body,
+            });
+
+            index += len;
+            continue;
+        }
+
+        if let Some(len) = other_invocation(&tokens[index..]) {
+            skipped.push(tokens[index].span().start());
+            index += len;
+            continue;
+        }
+
+        if let TokenTree::Group(group) = &tokens[index] {
+            find_invocations(group.stream(), invocations, skipped);
+        }
+
+        index += 1;
+    }
+}
+
+/// Whether the body of a `soupa!` invocation can be rewritten, which it can't
+/// if it or any invocation nested within it uses an option adding checks, or it
+/// contains an invocation of another of `soupa`'s macros.
+fn supported(body: &TokenStream) -> bool {
+    let tokens = body.clone().into_iter().collect::<Vec<_>>();
+
+    // Options are `@name` optionally followed by a group, as in `@boxed(..)`.
+    let mut index = 0;
+    while is_punct(tokens.get(index), '@') {
+        let name = tokens.get(index + 1);
+        if ["send", "sync", "static"]
+            .iter()
+            .any(|checked| is_ident(name, checked))
+        {
+            return false;
+        }
+
+        index += 2;
+        if let Some(TokenTree::Group(_)) = tokens.get(index) {
+            index += 1;
+        }
+    }
+
+    let mut invocations = Vec::new();
+    let mut skipped = Vec::new();
+    find_invocations(body.clone(), &mut invocations, &mut skipped);
+
+    skipped.is_empty()
+        && invocations
+            .iter()
+            .all(|invocation| supported(&invocation.body))
+}
+
+/// Replaces the paths to `soupa`'s private items used by `@boxed(...)` with their
+/// equivalents in `std`, returning [`None`] if any other private item is used.
+fn public_paths(tokens: TokenStream) -> Option<TokenStream> {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let mut output = TokenStream::new();
+    let mut index = 0;
+
+    while index < tokens.len() {
+        if let Some(name) = private_path(&tokens[index..]) {
+            output.extend(match name.to_string().as_str() {
+                "Box" => quote!(::std::boxed::Box),
+                "Pin" => quote!(::std::pin::Pin),
+                "Future" => quote!(::std::future::Future),
+                _ => return None,
+            });
+            index += 9;
+            continue;
+        }
+
+        match &tokens[index] {
+            TokenTree::Group(group) => {
+                let mut inner =
+                    proc_macro2::Group::new(group.delimiter(), public_paths(group.stream())?);
+                inner.set_span(group.span());
+                output.extend([TokenTree::Group(inner)]);
+            }
+            TokenTree::Ident(ident) if ident == "__private" => return None,
+            token => output.extend([token.clone()]),
+        }
+
+        index += 1;
+    }
+
+    Some(output)
+}
+
+/// Matches `::soupa::__private::$name` at the start of `tokens`, returning the name.
+fn private_path(tokens: &[TokenTree]) -> Option<&proc_macro2::Ident> {
+    let path = is_punct(tokens.first(), ':')
+        && is_punct(tokens.get(1), ':')
+        && is_ident(tokens.get(2), "soupa")
+        && is_punct(tokens.get(3), ':')
+        && is_punct(tokens.get(4), ':')
+        && is_ident(tokens.get(5), "__private")
+        && is_punct(tokens.get(6), ':')
+        && is_punct(tokens.get(7), ':');
+
+    match tokens.get(8) {
+        Some(TokenTree::Ident(name)) if path => Some(name),
+        _ => None,
+    }
+}
+
+/// Formats an expression, returning [`None`] if it could not be parsed.
+fn format_expr(tokens: TokenStream) -> Option<String> {
+    let expr = syn::parse2::<syn::Expr>(tokens).ok()?;
+    let file = syn::parse2::<syn::File>(quote!(fn __soupa_desugar() { #expr })).ok()?;
+    let formatted = prettyplease::unparse(&file);
+
+    // Strip the wrapping function, leaving the expression at the outermost indentation
+    let lines = formatted.lines().collect::<Vec<_>>();
+    let inner = lines.get(1..lines.len().checked_sub(1)?)?;
+
+    let lines = inner
+        .iter()
+        .map(|line| line.strip_prefix("    ").unwrap_or(line))
+        .collect::<Vec<_>>();
+
+    Some(lines.join("\n"))
+}
+
+/// Returns the leading whitespace of the line containing `offset`.
+fn line_indent(source: &str, offset: usize) -> &str {
+    let start = source[..offset].rfind('\n').map_or(0, |index| index + 1);
+    let line = &source[start..];
+    let end = line.len() - line.trim_start().len();
+
+    &line[..end]
+}
+
+/// Extends `range` to cover the whole lines it occupies, provided nothing else
+/// shares those lines.
+fn whole_lines(source: &str, range: Range<usize>) -> Range<usize> {
+    let before = &source[..range.start];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+    let after = &source[range.end..];
+    let line_end = after
+        .find('\n')
+        .map_or(source.len(), |index| range.end + index + 1);
+
+    if before[line_start..].trim().is_empty() && source[range.end..line_end].trim().is_empty() {
+        line_start..line_end
+    } else {
+        range
+    }
+}
+
+/// Applies non-overlapping edits to `source`.
+fn apply(source: &str, mut edits: Vec<(Range<usize>, String)>) -> String {
+    edits.sort_by_key(|(range, _)| range.start);
+
+    let mut output = String::with_capacity(source.len());
+    let mut cursor = 0;
+
+    for (range, replacement) in edits {
+        output.push_str(&source[cursor..range.start]);
+        output.push_str(&replacement);
+        cursor = range.end;
+    }
+
+    output.push_str(&source[cursor..]);
+    output
+}
+
+/// Returns the edits to `source` removing imports of the `soupa!` macro, which
+/// is no longer used by `rewritten`.
+fn strip_imports(source: &str, rewritten: &str) -> Vec<(Range<usize>, String)> {
+    let (Ok(file), Ok(rewritten)) = (syn::parse_file(source), syn::parse_file(rewritten)) else {
+        return Vec::new();
+    };
+
+    let mut imports = Imports::default();
+    imports.visit_file(&file);
+
+    let mut references = References::default();
+    references.visit_file(&rewritten);
+
+    let mut edits = Vec::new();
+    let mut referenced = references.found;
+
+    for item in imports.uses {
+        let range = item.span().byte_range();
+
+        match without_macro(&item.tree) {
+            Remains::Unchanged => referenced = true,
+            Remains::Changed(tree) => {
+                let item = syn::Item::Use(syn::ItemUse { tree, ..item });
+                let file = syn::File {
+                    shebang: None,
+                    attrs: Vec::new(),
+                    items: vec![item],
+                };
+
+                edits.push((range, prettyplease::unparse(&file).trim_end().to_owned()));
+                referenced = true;
+            }
+            Remains::Nothing => edits.push((whole_lines(source, range), String::new())),
+        }
+    }
+
+    if !referenced {
+        for range in imports.crates {
+            edits.push((whole_lines(source, range), String::new()));
+        }
+    }
+
+    edits
+}
+
+/// What remains of a use tree once the `soupa!` macro is removed from it.
+enum Remains {
+    Nothing,
+    Unchanged,
+    Changed(syn::UseTree),
+}
+
+/// Removes the `soupa!` macro from a use tree starting with `soupa::`.
+fn without_macro(tree: &syn::UseTree) -> Remains {
+    let syn::UseTree::Path(path) = tree else {
+        return Remains::Unchanged;
+    };
+
+    let is_macro = |tree: &syn::UseTree| match tree {
+        syn::UseTree::Name(name) => name.ident == "soupa",
+        syn::UseTree::Rename(rename) => rename.ident == "soupa",
+        _ => false,
+    };
+
+    let inner = match &*path.tree {
+        tree if is_macro(tree) => return Remains::Nothing,
+        syn::UseTree::Group(group) if group.items.iter().any(is_macro) => {
+            let mut items = group
+                .items
+                .iter()
+                .filter(|tree| !is_macro(tree))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            match items.len() {
+                0 => return Remains::Nothing,
+                1 => items.remove(0),
+                _ => syn::UseTree::Group(syn::UseGroup {
+                    items: items.into_iter().collect(),
+                    ..group.clone()
+                }),
+            }
+        }
+        _ => return Remains::Unchanged,
+    };
+
+    Remains::Changed(syn::UseTree::Path(syn::UsePath {
+        tree: Box::new(inner),
+        ..path.clone()
+    }))
+}
+
+/// Collects the items which import `soupa`.
+#[derive(Default)]
+struct Imports {
+    /// Items importing from within the crate, such as `use soupa::soupa;`.
+    uses: Vec<syn::ItemUse>,
+    /// The ranges of items importing the crate itself, such as `use soupa;` or
+    /// `extern crate soupa;`.
+    crates: Vec<Range<usize>>,
+}
+
+impl<'ast> Visit<'ast> for Imports {
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        match &item.tree {
+            syn::UseTree::Path(path) if path.ident == "soupa" => self.uses.push(item.clone()),
+            syn::UseTree::Name(name) if name.ident == "soupa" => {
+                self.crates.push(item.span().byte_range());
+            }
+            syn::UseTree::Rename(rename) if rename.ident == "soupa" => {
+                self.crates.push(item.span().byte_range());
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_item_extern_crate(&mut self, item: &'ast syn::ItemExternCrate) {
+        if item.ident == "soupa" {
+            self.crates.push(item.span().byte_range());
+        }
+    }
+}
+
+/// Finds whether anything from `soupa` is referenced outside of its imports,
+/// either through a path starting with `soupa` or one of its macros.
+#[derive(Default)]
+struct References {
+    found: bool,
+}
+
+impl References {
+    fn visit_tokens(&mut self, tokens: TokenStream) {
+        let tokens = tokens.into_iter().collect::<Vec<_>>();
+
+        for (index, token) in tokens.iter().enumerate() {
+            match token {
+                TokenTree::Ident(ident) if ident == "soupa" => self.found = true,
+                TokenTree::Ident(ident)
+                    if is_punct(tokens.get(index + 1), '!')
+                        && ["explain", "lift", "spawn"]
+                            .iter()
+                            .any(|name| ident == name) =>
+                {
+                    self.found = true;
+                }
+                TokenTree::Group(group) => self.visit_tokens(group.stream()),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for References {
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        let root = match &item.tree {
+            syn::UseTree::Path(path) => Some(&path.ident),
+            syn::UseTree::Name(name) => Some(&name.ident),
+            syn::UseTree::Rename(rename) => Some(&rename.ident),
+            _ => None,
+        };
+
+        // Imports of soupa are accounted for separately
+        if !matches!(root, Some(root) if root == "soupa") {
+            syn::visit::visit_item_use(self, item);
+        }
+    }
+
+    fn visit_item_extern_crate(&mut self, _: &'ast syn::ItemExternCrate) {}
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        self.visit_tokens(path.to_token_stream());
+        syn::visit::visit_path(self, path);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        self.visit_tokens(mac.to_token_stream());
+    }
+}
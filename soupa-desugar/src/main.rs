@@ -0,0 +1,136 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use soupa_desugar::{Desugarer, Naming};
+
+const USAGE: &str = "\
+Rewrites soupa! invocations into the plain Rust they expand to.
+
+Usage: soupa-desugar [OPTIONS] <PATH>...
+
+Each PATH may be a source file, or a directory which will be searched for
+source files. Without --in-place, the rewritten sources are printed.
+
+Options:
+  --in-place      Overwrite each source with its rewritten form
+  --temps         Name temporaries exactly as soupa! would
+  --keep-imports  Keep `use soupa::...` and `extern crate soupa` items
+  -h, --help      Print this message
+";
+
+fn main() -> ExitCode {
+    let mut desugarer = Desugarer::new();
+    let mut in_place = false;
+    let mut paths = Vec::new();
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--in-place" => in_place = true,
+            "--temps" => desugarer = desugarer.naming(Naming::Temps),
+            "--keep-imports" => desugarer = desugarer.strip_imports(false),
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            flag if flag.starts_with('-') => {
+                eprint!("error: unknown option `{flag}`\n\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if paths.is_empty() {
+        eprint!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+
+    let mut files = Vec::new();
+    for path in &paths {
+        if let Err(error) = collect_sources(path, &mut files) {
+            eprintln!("error: failed to read `{}`: {error}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let mut status = ExitCode::SUCCESS;
+
+    for file in &files {
+        let result = fs::read_to_string(file)
+            .map_err(|error| error.to_string())
+            .and_then(|source| {
+                desugarer
+                    .desugar(&source)
+                    .map(|desugared| (source, desugared))
+                    .map_err(|error| error.to_string())
+            });
+
+        let (source, desugared) = match result {
+            Ok(result) => result,
+            Err(error) => {
+                eprintln!("error: {}: {error}", file.display());
+                status = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        for skipped in &desugared.skipped {
+            eprintln!(
+                "warning: {}:{}:{}: invocation could not be rewritten",
+                file.display(),
+                skipped.line,
+                skipped.column + 1,
+            );
+        }
+
+        if in_place {
+            if desugared.source != source {
+                if let Err(error) = fs::write(file, &desugared.source) {
+                    eprintln!("error: failed to write `{}`: {error}", file.display());
+                    status = ExitCode::FAILURE;
+                }
+            }
+        } else {
+            if files.len() > 1 {
+                println!("// {}", file.display());
+            }
+            print!("{}", desugared.source);
+        }
+    }
+
+    status
+}
+
+/// Collects `path` if it is a file, or all Rust sources beneath it if it is a
+/// directory, skipping hidden and `target` directories.
+fn collect_sources(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_owned());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for entry in entries {
+        let name = entry
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+
+        if entry.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                collect_sources(&entry, files)?;
+            }
+        } else if name.ends_with(".rs") {
+            files.push(entry);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,290 @@
+//! Checks the rewritten `tests/expand` fixtures of `soupa` against their
+//! expansions, as produced by `cargo expand`.
+
+use std::{fs, path::Path};
+
+use quote::ToTokens;
+use soupa_desugar::{Desugarer, Naming};
+use syn::visit_mut::VisitMut;
+
+/// Removes statements disabled by `#[cfg(any())]` and the `#[cfg(all())]`
+/// attributes which always pass, as `cargo expand` would.
+struct TrivialCfg;
+
+impl VisitMut for TrivialCfg {
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        block.stmts.retain(|stmt| {
+            let attrs = match stmt {
+                syn::Stmt::Local(local) => &local.attrs,
+                syn::Stmt::Expr(syn::Expr::MethodCall(call), _) => &call.attrs,
+                _ => return true,
+            };
+
+            !attrs
+                .iter()
+                .any(|attr| attr.to_token_stream().to_string() == "# [cfg (any ())]")
+        });
+
+        syn::visit_mut::visit_block_mut(self, block);
+    }
+
+    fn visit_attributes_mut(&mut self, attrs: &mut Vec<syn::Attribute>) {
+        attrs.retain(|attr| attr.to_token_stream().to_string() != "# [cfg (all ())]");
+    }
+}
+
+/// Removes the calls through which `soupa!` emits lints, which aren't reproduced.
+struct Lints;
+
+impl VisitMut for Lints {
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        block.stmts.retain(|stmt| match stmt {
+            syn::Stmt::Expr(syn::Expr::Call(call), _) => !call
+                .func
+                .to_token_stream()
+                .to_string()
+                .starts_with(":: soupa :: __private ::"),
+            _ => true,
+        });
+
+        syn::visit_mut::visit_block_mut(self, block);
+    }
+}
+
+/// Finds the body of `test_body` within a source.
+fn test_body(source: &str) -> syn::Block {
+    let mut file = syn::parse_file(source).unwrap();
+    TrivialCfg.visit_file_mut(&mut file);
+    Lints.visit_file_mut(&mut file);
+
+    file.items
+        .into_iter()
+        .find_map(|item| match item {
+            syn::Item::Fn(item) if item.sig.ident == "test_body" => Some(*item.block),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn fixtures() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests/expand");
+    let desugarer = Desugarer::new().naming(Naming::Temps);
+
+    let mut checked = 0;
+
+    for entry in fs::read_dir(&fixtures).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_str().unwrap();
+
+        if !name.ends_with(".rs") || name.ends_with(".expanded.rs") {
+            continue;
+        }
+
+        let source = fs::read_to_string(&path).unwrap();
+        let expanded = fs::read_to_string(path.with_extension("expanded.rs")).unwrap();
+
+        let desugared = desugarer.desugar(&source).unwrap();
+        assert!(desugared.skipped.is_empty(), "{name}: invocations skipped");
+        assert!(
+            !desugared.source.contains("soupa!"),
+            "{name}: soupa! remains"
+        );
+
+        let desugared = test_body(&desugared.source);
+        let expanded = test_body(&expanded);
+
+        assert_eq!(desugared.stmts.len(), expanded.stmts.len(), "{name}");
+
+        for (desugared, expanded) in desugared.stmts.iter().zip(&expanded.stmts) {
+            // Other macros, such as assert_eq!, are expanded by cargo expand
+            if matches!(desugared, syn::Stmt::Macro(_)) {
+                continue;
+            }
+
+            assert_eq!(
+                desugared.to_token_stream().to_string(),
+                expanded.to_token_stream().to_string(),
+                "{name}",
+            );
+        }
+
+        checked += 1;
+    }
+
+    assert!(checked > 0);
+}
+
+#[test]
+fn readable_names() {
+    let source = r#"use soupa::soupa;
+
+fn main() {
+    // Comments outside of invocations are kept
+    let func = soupa!(move || (super { foo.clone() }, super(bar) { Bar::new() }));
+}
+"#;
+
+    let desugared = soupa_desugar::desugar(source).unwrap();
+
+    assert_eq!(desugared.rewritten, 1);
+    assert_eq!(
+        desugared.source,
+        r#"
+fn main() {
+    // Comments outside of invocations are kept
+    let func = {
+        let __soupa_foo_clone = { foo.clone() };
+        let bar = { Bar::new() };
+        move || (__soupa_foo_clone, bar)
+    };
+}
+"#,
+    );
+}
+
+#[test]
+fn skipped() {
+    let source = r#"use soupa::soupa;
+
+macro_rules! wrap {
+    ($body:expr) => { soupa!(move || $body) };
+}
+
+fn main() {
+    let func = soupa!(move || super { foo.clone() });
+}
+"#;
+
+    let desugared = soupa_desugar::desugar(source).unwrap();
+
+    assert_eq!(desugared.rewritten, 1);
+    assert_eq!(desugared.skipped.len(), 1);
+    assert_eq!(desugared.skipped[0].line, 4);
+
+    // Imports are still required by the skipped invocation
+    assert!(desugared.source.starts_with("use soupa::soupa;"));
+}
+
+#[test]
+fn other_imports_kept() {
+    let source = r#"use soupa::{adapters::Cloned, soupa};
+
+fn main() {
+    let func = soupa!(move || super { Cloned.capture(&foo) });
+}
+"#;
+
+    let desugared = soupa_desugar::desugar(source).unwrap();
+
+    assert_eq!(desugared.rewritten, 1);
+    assert!(desugared
+        .source
+        .starts_with("use soupa::adapters::Cloned;\n\nfn main() {"));
+}
+
+#[test]
+fn crate_kept_while_referenced() {
+    let source = r#"#[macro_use]
+extern crate soupa;
+
+fn main() {
+    let func = soupa!(move || super { foo.clone() });
+    let other = soupa::capture(foo.clone());
+}
+"#;
+
+    let desugared = soupa_desugar::desugar(source).unwrap();
+
+    assert_eq!(desugared.rewritten, 1);
+    assert!(desugared
+        .source
+        .starts_with("#[macro_use]\nextern crate soupa;"));
+
+    let source = r#"#[macro_use]
+extern crate soupa;
+
+fn main() {
+    let func = soupa!(move || super { foo.clone() });
+}
+"#;
+
+    let desugared = soupa_desugar::desugar(source).unwrap();
+
+    assert!(desugared.source.starts_with("\nfn main() {"));
+}
+
+#[test]
+fn other_macros_skipped() {
+    let source = r#"use soupa::{explain, soupa};
+
+fn main() {
+    let func = soupa!(move || super { foo.clone() });
+    let explained = explain!(move || super { foo.clone() });
+    let handle = soupa::thread::spawn!(move || super { foo.clone() });
+    let func = soupa!(@send move || super { foo.clone() });
+    let func = soupa!(move || soupa!(@sync move || super { foo.clone() }));
+}
+"#;
+
+    let desugared = soupa_desugar::desugar(source).unwrap();
+
+    assert_eq!(desugared.rewritten, 1);
+    assert_eq!(
+        desugared
+            .skipped
+            .iter()
+            .map(|start| start.line)
+            .collect::<Vec<_>>(),
+        [5, 6, 7, 8],
+    );
+    assert!(desugared.source.starts_with("use soupa::{explain, soupa};"));
+}
+
+#[test]
+fn options_after_groups_checked() {
+    let source = r#"use soupa::soupa;
+
+fn main() {
+    let func = soupa!(@boxed(Fn() -> usize) @send move || super { foo.clone() }.len());
+    let func = soupa!(@boxed(Fn() -> usize) @lazy @static move || super { foo.clone() }.len());
+}
+"#;
+
+    let desugared = soupa_desugar::desugar(source).unwrap();
+
+    assert_eq!(desugared.rewritten, 0);
+    assert_eq!(
+        desugared
+            .skipped
+            .iter()
+            .map(|start| start.line)
+            .collect::<Vec<_>>(),
+        [4, 5],
+    );
+}
+
+#[test]
+fn private_items_avoided() {
+    let source = r#"use soupa::soupa;
+
+fn main() {
+    let func = soupa!(@boxed(Fn() -> usize) move || super { foo.clone() }.len());
+    let fut = soupa!(@boxed(Future<Output = usize>) async move { super { foo.clone() }.len() });
+    let func = soupa!(move || super with Cloned { &foo }.len());
+}
+"#;
+
+    let desugared = soupa_desugar::desugar(source).unwrap();
+
+    assert_eq!(desugared.rewritten, 2);
+    assert_eq!(desugared.skipped.len(), 1);
+    assert_eq!(desugared.skipped[0].line, 6);
+    assert!(!desugared.source.contains("__private"));
+    assert!(desugared
+        .source
+        .contains("let __soupa_boxed: ::std::boxed::Box<dyn Fn() -> usize>"));
+    assert!(desugared
+        .source
+        .contains("::std::boxed::Box<dyn ::std::future::Future<Output = usize>>"));
+}
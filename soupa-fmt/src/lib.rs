@@ -0,0 +1,620 @@
+//! Formats the bodies of `soupa!` invocations with rustfmt.
+//!
+//! rustfmt leaves macro invocations untouched unless their bodies can be parsed
+//! as Rust, which `super { ... }` blocks can't be.
+//! To work around this, each super block is temporarily replaced with a call to
+//! a placeholder function, `super { ... }` becoming `_s000({ ... })`, which is
+//! parseable and occupies the same width.
+//! Span markers are masked similarly, `super span move || { ... }` becoming
+//! `_s001(move || { ... })`, and so are super blocks with a capture adapter,
+//! `super with Cloned { ... }` becoming `_s002({ ... })`, and further uses of a
+//! shared super block, `super share(name)` becoming `_s003()`.
+//! Once rustfmt has formatted the source, the placeholders are mapped back.
+//!
+//! Placeholders are all the same width within a source, and are given a prefix
+//! which no identifier in the source starts with, such as `_t` if one starts
+//! with `_s`, so they can't be confused with calls written in it.
+//!
+//! As rustfmt operates on the whole source, comments are kept.
+//! Invocations delimited by braces, `soupa! { ... }`, are left untouched by
+//! rustfmt, as with any other macro.
+//!
+//! # Examples
+//!
+//! ```rust
+//! let formatted = soupa_fmt::format("fn main() { let f = soupa!(move||super{foo . clone()}); }\n")
+//!     .unwrap();
+//!
+//! assert_eq!(
+//!     formatted,
+//!     "fn main() {\n    let f = soupa!(move || super { foo.clone() });\n}\n",
+//! );
+//! ```
+
+use std::{
+    collections::BTreeSet,
+    error, fmt,
+    io::{self, Write},
+    ops::Range,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use proc_macro2::{Delimiter, Group, TokenStream, TokenTree};
+
+/// Formats `source` using the default configuration.
+///
+/// # Errors
+///
+/// Fails if `source` could not be tokenized, or if rustfmt fails.
+pub fn format(source: &str) -> Result<String, Error> {
+    Formatter::new().format(source)
+}
+
+/// Configurable entry point for formatting sources.
+#[derive(Clone, Debug)]
+pub struct Formatter {
+    rustfmt: PathBuf,
+    edition: String,
+    config_path: Option<PathBuf>,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self {
+            rustfmt: std::env::var_os("RUSTFMT")
+                .map_or_else(|| PathBuf::from("rustfmt"), PathBuf::from),
+            edition: String::from("2021"),
+            config_path: None,
+        }
+    }
+}
+
+impl Formatter {
+    /// Creates a [`Formatter`] with the default configuration.
+    ///
+    /// By default, the `rustfmt` found through the `RUSTFMT` environment variable
+    /// or on the `PATH` is used, formatting for the 2021 edition.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the rustfmt executable to use.
+    pub fn rustfmt(mut self, rustfmt: impl Into<PathBuf>) -> Self {
+        self.rustfmt = rustfmt.into();
+        self
+    }
+
+    /// Sets the edition sources are formatted for.
+    pub fn edition(mut self, edition: impl Into<String>) -> Self {
+        self.edition = edition.into();
+        self
+    }
+
+    /// Sets the rustfmt configuration file, or directory containing one, to use.
+    pub fn config_path(mut self, config_path: impl Into<PathBuf>) -> Self {
+        self.config_path = Some(config_path.into());
+        self
+    }
+
+    /// Formats `source`, including the bodies of any `soupa!` invocations.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `source` could not be tokenized, or if rustfmt fails.
+    pub fn format(&self, source: &str) -> Result<String, Error> {
+        let masked = mask(source)?;
+        let formatted = self.run_rustfmt(&masked.source)?;
+
+        masked.unmask(&formatted)
+    }
+
+    fn run_rustfmt(&self, source: &str) -> Result<String, Error> {
+        let mut command = Command::new(&self.rustfmt);
+        command
+            .arg("--edition")
+            .arg(&self.edition)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        if let Some(config_path) = &self.config_path {
+            command.arg("--config-path").arg(config_path);
+        }
+
+        let mut child = command.spawn()?;
+
+        // Write from another thread, so a full stdout pipe can't deadlock rustfmt
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let input = source.to_owned();
+        let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+        let output = child.wait_with_output()?;
+        writer.join().expect("writer does not panic")?;
+
+        if !output.status.success() {
+            return Err(Error::Rustfmt(
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ));
+        }
+
+        String::from_utf8(output.stdout)
+            .map_err(|error| Error::Io(io::Error::new(io::ErrorKind::InvalidData, error)))
+    }
+}
+
+/// An error encountered while formatting a source.
+#[derive(Debug)]
+pub enum Error {
+    /// The source could not be tokenized.
+    Lex(proc_macro2::LexError),
+    /// rustfmt could not be run.
+    Io(io::Error),
+    /// rustfmt failed, with the provided output.
+    Rustfmt(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Lex(error) => write!(f, "failed to tokenize source: {error}"),
+            Error::Io(error) => write!(f, "failed to run rustfmt: {error}"),
+            Error::Rustfmt(output) => write!(f, "rustfmt failed:\n{output}"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Lex(error) => Some(error),
+            Error::Io(error) => Some(error),
+            Error::Rustfmt(_) => None,
+        }
+    }
+}
+
+impl From<proc_macro2::LexError> for Error {
+    fn from(error: proc_macro2::LexError) -> Self {
+        Error::Lex(error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+/// A source with every super block within a `soupa!` invocation replaced by a
+/// parseable placeholder.
+///
+/// This allows sources to be formatted by tools other than rustfmt.
+#[derive(Clone, Debug)]
+pub struct Masked {
+    /// The source with placeholders.
+    pub source: String,
+    prefix: String,
+    width: usize,
+    headers: Vec<String>,
+}
+
+impl Masked {
+    /// Restores the super blocks within `source`, which must be [`Masked::source`]
+    /// after formatting.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `source` could not be tokenized.
+    pub fn unmask(&self, source: &str) -> Result<String, Error> {
+        let mut source = source.to_owned();
+
+        // Only the outermost placeholders are restored by each pass
+        loop {
+            let tokens = source.parse::<TokenStream>()?;
+
+            let mut edits = Vec::new();
+            self.find_placeholders(&source, tokens, &mut edits);
+
+            if edits.is_empty() {
+                return Ok(source);
+            }
+
+            source = apply(&source, edits);
+        }
+    }
+
+    fn find_placeholders(
+        &self,
+        source: &str,
+        tokens: TokenStream,
+        edits: &mut Vec<(Range<usize>, String)>,
+    ) {
+        let tokens = tokens.into_iter().collect::<Vec<_>>();
+        let mut index = 0;
+
+        while index < tokens.len() {
+            if let Some((header, kept)) = self.placeholder(&tokens[index..]) {
+                // Keep the block exactly as it was formatted
+                // Placeholders within it are restored by the next pass
+                let start = tokens[index].span().byte_range().start;
+                let end = tokens[index + 1].span().byte_range().end;
+
+                let restored = if kept.is_empty() {
+                    header.to_owned()
+                } else {
+                    format!("{header} {}", &source[kept])
+                };
+
+                edits.push((start..end, restored));
+                index += 2;
+                continue;
+            }
+
+            if let TokenTree::Group(group) = &tokens[index] {
+                self.find_placeholders(source, group.stream(), edits);
+            }
+
+            index += 1;
+        }
+    }
+
+    /// Matches `_sNNN({ ... })` at the start of `tokens`, returning the header it
+    /// replaced and the range of the source to keep after it.
+    fn placeholder(&self, tokens: &[TokenTree]) -> Option<(&str, Range<usize>)> {
+        let index = match tokens.first()? {
+            TokenTree::Ident(ident) => {
+                let ident = ident.to_string();
+                let digits = ident.strip_prefix(&self.prefix)?;
+
+                if digits.len() != self.width {
+                    return None;
+                }
+
+                digits.parse::<usize>().ok()?
+            }
+            _ => return None,
+        };
+
+        let header = self.headers.get(index)?;
+        let args = group(tokens.get(1), Delimiter::Parenthesis)?;
+        let mut args = args.stream().into_iter().collect::<Vec<_>>();
+
+        // rustfmt may add a trailing comma if it lays the arguments out vertically
+        if matches!(args.last(), Some(TokenTree::Punct(punct)) if punct.as_char() == ',') {
+            args.pop();
+        }
+
+        // A further use of a shared super block has no block to keep
+        if args.is_empty() && header.starts_with("super share") {
+            return Some((header, 0..0));
+        }
+
+        // A span marker keeps the whole closure or async block it applies to
+        if !header.starts_with("super span") {
+            match args.as_slice() {
+                [TokenTree::Group(block)] if block.delimiter() == Delimiter::Brace => {}
+                _ => return None,
+            }
+        }
+
+        let start = args.first()?.span().byte_range().start;
+        let end = args.last()?.span().byte_range().end;
+
+        Some((header, start..end))
+    }
+}
+
+/// Replaces every super block within a `soupa!` invocation in `source` with a
+/// parseable placeholder.
+///
+/// # Errors
+///
+/// Fails if `source` could not be tokenized.
+pub fn mask(source: &str) -> Result<Masked, Error> {
+    let tokens = source.parse::<TokenStream>()?;
+
+    let mut idents = BTreeSet::new();
+    collect_idents(tokens.clone(), &mut idents);
+
+    let mut masker = Masker {
+        source,
+        edits: Vec::new(),
+        placeholders: Vec::new(),
+        headers: Vec::new(),
+    };
+    masker.find_invocations(tokens);
+
+    // Placeholders are only named once they're all found, so they share a width
+    let prefix = prefix(&idents);
+    let width = masker
+        .headers
+        .len()
+        .saturating_sub(1)
+        .to_string()
+        .len()
+        .max(3);
+
+    let mut edits = masker.edits;
+    edits.extend(
+        masker
+            .placeholders
+            .into_iter()
+            .enumerate()
+            .map(|(index, range)| (range, format!("{prefix}{index:0width$}("))),
+    );
+
+    Ok(Masked {
+        source: apply(source, edits),
+        prefix,
+        width,
+        headers: masker.headers,
+    })
+}
+
+/// Chooses the prefix of placeholders, `_s` unless an identifier in the source
+/// starts with it, in which case another letter, then more underscores, are tried.
+fn prefix(idents: &BTreeSet<String>) -> String {
+    (1..)
+        .flat_map(|underscores| {
+            "stuvwxyzabcdefghijklmnopqr"
+                .chars()
+                .map(move |letter| format!("{}{letter}", "_".repeat(underscores)))
+        })
+        .find(|prefix| {
+            !idents
+                .iter()
+                .any(|ident| ident.starts_with(prefix.as_str()))
+        })
+        .expect("a source has finitely many identifiers")
+}
+
+/// Collects every identifier within `tokens`, at any depth.
+fn collect_idents(tokens: TokenStream, idents: &mut BTreeSet<String>) {
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ident) => {
+                let ident = ident.to_string();
+                idents.insert(ident.trim_start_matches("r#").to_owned());
+            }
+            TokenTree::Group(group) => collect_idents(group.stream(), idents),
+            _ => {}
+        }
+    }
+}
+
+struct Masker<'a> {
+    source: &'a str,
+    edits: Vec<(Range<usize>, String)>,
+    /// The range replaced by the opening of each placeholder, by its index.
+    placeholders: Vec<Range<usize>>,
+    headers: Vec<String>,
+}
+
+impl Masker<'_> {
+    /// Finds `soupa!` and `explain!` invocations within `tokens`.
+    fn find_invocations(&mut self, tokens: TokenStream) {
+        let tokens = tokens.into_iter().collect::<Vec<_>>();
+        let mut index = 0;
+
+        while index < tokens.len() {
+            let name = matches!(&tokens[index], TokenTree::Ident(ident) if ident == "soupa" || ident == "explain");
+            let bang = matches!(tokens.get(index + 1), Some(TokenTree::Punct(punct)) if punct.as_char() == '!');
+
+            match (&tokens[index], tokens.get(index + 2)) {
+                // rustfmt doesn't format the bodies of brace-delimited invocations
+                (_, Some(TokenTree::Group(body)))
+                    if name
+                        && bang
+                        && matches!(
+                            body.delimiter(),
+                            Delimiter::Parenthesis | Delimiter::Bracket
+                        ) =>
+                {
+                    self.find_super_blocks(body.stream());
+                    index += 3;
+                    continue;
+                }
+                (TokenTree::Group(group), _) => self.find_invocations(group.stream()),
+                _ => {}
+            }
+
+            index += 1;
+        }
+    }
+
+    /// Finds `super { ... }`, `super(...) { ... }` and `super with ... { ... }`
+    /// blocks, and `super span` markers, within the body of an invocation.
+    fn find_super_blocks(&mut self, tokens: TokenStream) {
+        let tokens = tokens.into_iter().collect::<Vec<_>>();
+
+        for (index, token) in tokens.iter().enumerate() {
+            match token {
+                TokenTree::Ident(ident)
+                    if ident == "super"
+                        && matches!(tokens.get(index + 1), Some(TokenTree::Ident(span)) if span == "span") =>
+                {
+                    self.span_marker(&tokens, index);
+                }
+                TokenTree::Ident(ident) if ident == "super" => {
+                    if self.adapter_marker(&tokens, index) || self.share_marker(&tokens, index) {
+                        continue;
+                    }
+
+                    let block = match (tokens.get(index + 1), tokens.get(index + 2)) {
+                        (Some(TokenTree::Group(block)), _)
+                            if block.delimiter() == Delimiter::Brace =>
+                        {
+                            Some(block)
+                        }
+                        (Some(TokenTree::Group(label)), Some(TokenTree::Group(block)))
+                            if label.delimiter() == Delimiter::Parenthesis
+                                && block.delimiter() == Delimiter::Brace =>
+                        {
+                            Some(block)
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(block) = block {
+                        let start = ident.span().byte_range().start;
+                        let block = block.span().byte_range();
+
+                        let header = header(&tokens[index + 1..], block.start);
+
+                        self.mask(start..block.start, header);
+                        self.edits.push((block.end..block.end, String::from(")")));
+                    }
+                }
+                TokenTree::Group(group) => self.find_super_blocks(group.stream()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Masks the span marker at `tokens[index]`, up to the end of the block it
+    /// applies to.
+    fn span_marker(&mut self, tokens: &[TokenTree], index: usize) {
+        let mut head = index + 2;
+        let mut header = String::from("super span");
+
+        // The span is kept as written, as it isn't formatted
+        if let Some(args) = group(tokens.get(head), Delimiter::Parenthesis) {
+            header = format!("super span{}", &self.source[args.span().byte_range()]);
+            head += 1;
+        }
+
+        let block = tokens[head..]
+            .iter()
+            .take_while(|token| !matches!(token, TokenTree::Punct(punct) if matches!(punct.as_char(), ',' | ';')))
+            .find_map(|token| group(Some(token), Delimiter::Brace));
+
+        // Leave markers which don't apply to a block for soupa! to report
+        let (Some(block), Some(first)) = (block, tokens.get(head)) else {
+            return;
+        };
+
+        let start = tokens[index].span().byte_range().start;
+        let end = block.span().byte_range().end;
+
+        self.mask(start..first.span().byte_range().start, header);
+        self.edits.push((end..end, String::from(")")));
+    }
+
+    /// Masks a super block with a capture adapter at `tokens[index]`, returning
+    /// whether there was one.
+    fn adapter_marker(&mut self, tokens: &[TokenTree], index: usize) -> bool {
+        let mut with = index + 1;
+
+        if group(tokens.get(with), Delimiter::Parenthesis).is_some() {
+            with += 1;
+        }
+
+        if !matches!(tokens.get(with), Some(TokenTree::Ident(ident)) if ident == "with") {
+            return false;
+        }
+
+        let block = tokens[with + 1..]
+            .iter()
+            .take_while(|token| !matches!(token, TokenTree::Punct(punct) if matches!(punct.as_char(), ',' | ';')))
+            .find_map(|token| group(Some(token), Delimiter::Brace));
+
+        let Some(block) = block else {
+            return false;
+        };
+
+        // The adapter is kept as written, as it isn't formatted
+        let start = tokens[index].span().byte_range().start;
+        let block = block.span().byte_range();
+        let header = format!(
+            "{} {}",
+            header(&tokens[index + 1..], block.start),
+            self.source[tokens[with].span().byte_range().start..block.start].trim_end(),
+        );
+
+        self.mask(start..block.start, header);
+        self.edits.push((block.end..block.end, String::from(")")));
+
+        true
+    }
+
+    /// Masks a shared super block at `tokens[index]`, returning whether there
+    /// was one.
+    fn share_marker(&mut self, tokens: &[TokenTree], index: usize) -> bool {
+        if !matches!(tokens.get(index + 1), Some(TokenTree::Ident(share)) if share == "share") {
+            return false;
+        }
+
+        let Some(args) = group(tokens.get(index + 2), Delimiter::Parenthesis) else {
+            return false;
+        };
+
+        let start = tokens[index].span().byte_range().start;
+        let args = args.span().byte_range();
+        let header = format!("super share{}", &self.source[args.clone()]);
+
+        match group(tokens.get(index + 3), Delimiter::Brace) {
+            Some(block) => {
+                let block = block.span().byte_range();
+                self.mask(start..block.start, header);
+                self.edits.push((block.end..block.end, String::from(")")));
+            }
+            // A further use is masked as a call without arguments
+            None => {
+                self.mask(start..args.end, header);
+                self.edits.push((args.end..args.end, String::from(")")));
+            }
+        }
+
+        true
+    }
+
+    /// Replaces `range` with the opening of a placeholder for `header`.
+    fn mask(&mut self, range: Range<usize>, header: String) {
+        self.placeholders.push(range);
+        self.headers.push(header);
+    }
+}
+
+/// Formats the header of a super block, `super` followed by any arguments
+/// before the block at `block_start`, as `super(label, after = other)`.
+fn header(tokens: &[TokenTree], block_start: usize) -> String {
+    match tokens.first() {
+        Some(TokenTree::Group(args)) if args.span().byte_range().start < block_start => {
+            let args = args
+                .stream()
+                .into_iter()
+                .map(|token| token.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+                .replace(" ,", ",");
+
+            format!("super({args})")
+        }
+        _ => String::from("super"),
+    }
+}
+
+fn group(token: Option<&TokenTree>, delimiter: Delimiter) -> Option<Group> {
+    match token {
+        Some(TokenTree::Group(group)) if group.delimiter() == delimiter => Some(group.clone()),
+        _ => None,
+    }
+}
+
+/// Applies non-overlapping edits to `source`.
+fn apply(source: &str, mut edits: Vec<(Range<usize>, String)>) -> String {
+    edits.sort_by_key(|(range, _)| (range.start, range.end));
+
+    let mut output = String::with_capacity(source.len());
+    let mut cursor = 0;
+
+    for (range, replacement) in edits {
+        output.push_str(&source[cursor..range.start]);
+        output.push_str(&replacement);
+        cursor = range.end;
+    }
+
+    output.push_str(&source[cursor..]);
+    output
+}
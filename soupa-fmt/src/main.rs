@@ -0,0 +1,165 @@
+use std::{
+    env, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use soupa_fmt::Formatter;
+
+const USAGE: &str = "\
+Formats Rust sources with rustfmt, including the bodies of soupa! invocations.
+
+Usage: soupa-fmt [OPTIONS] [PATH]...
+
+Each PATH may be a source file, or a directory which will be searched for
+source files, and is formatted in place. Without any PATH, a source is read
+from stdin and the formatted source is printed.
+
+Options:
+  --check               Print the sources which aren't formatted, and exit with
+                        an error if there are any, instead of formatting them
+  --edition <EDITION>   The edition to format for [default: 2021]
+  --config-path <PATH>  The rustfmt configuration to use
+  -h, --help            Print this message
+";
+
+fn main() -> ExitCode {
+    let mut formatter = Formatter::new();
+    let mut check = false;
+    let mut paths = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--edition" | "--config-path" => {
+                let Some(value) = args.next() else {
+                    eprint!("error: `{arg}` requires a value\n\n{USAGE}");
+                    return ExitCode::FAILURE;
+                };
+
+                formatter = if arg == "--edition" {
+                    formatter.edition(value)
+                } else {
+                    formatter.config_path(value)
+                };
+            }
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            flag if flag.starts_with('-') => {
+                eprint!("error: unknown option `{flag}`\n\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if paths.is_empty() {
+        return format_stdin(&formatter, check);
+    }
+
+    let mut files = Vec::new();
+    for path in &paths {
+        if let Err(error) = collect_sources(path, &mut files) {
+            eprintln!("error: failed to read `{}`: {error}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let mut status = ExitCode::SUCCESS;
+
+    for file in &files {
+        let result = fs::read_to_string(file)
+            .map_err(|error| error.to_string())
+            .and_then(|source| {
+                formatter
+                    .format(&source)
+                    .map(|formatted| (source, formatted))
+                    .map_err(|error| error.to_string())
+            });
+
+        let (source, formatted) = match result {
+            Ok(result) => result,
+            Err(error) => {
+                eprintln!("error: {}: {error}", file.display());
+                status = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        if formatted == source {
+            continue;
+        }
+
+        if check {
+            println!("{}", file.display());
+            status = ExitCode::FAILURE;
+        } else if let Err(error) = fs::write(file, &formatted) {
+            eprintln!("error: failed to write `{}`: {error}", file.display());
+            status = ExitCode::FAILURE;
+        }
+    }
+
+    status
+}
+
+fn format_stdin(formatter: &Formatter, check: bool) -> ExitCode {
+    let mut source = String::new();
+    if let Err(error) = io::stdin().read_to_string(&mut source) {
+        eprintln!("error: failed to read stdin: {error}");
+        return ExitCode::FAILURE;
+    }
+
+    match formatter.format(&source) {
+        Ok(formatted) if check => {
+            if formatted == source {
+                ExitCode::SUCCESS
+            } else {
+                println!("<stdin>");
+                ExitCode::FAILURE
+            }
+        }
+        Ok(formatted) => {
+            print!("{formatted}");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("error: <stdin>: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Collects `path` if it is a file, or all Rust sources beneath it if it is a
+/// directory, skipping hidden and `target` directories.
+fn collect_sources(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_owned());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for entry in entries {
+        let name = entry
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+
+        if entry.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                collect_sources(&entry, files)?;
+            }
+        } else if name.ends_with(".rs") {
+            files.push(entry);
+        }
+    }
+
+    Ok(())
+}
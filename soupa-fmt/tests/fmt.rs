@@ -0,0 +1,156 @@
+use std::fs;
+
+use soupa_fmt::{format, mask};
+
+/// Checks that `source` formats to `expected`, and that `expected` is stable.
+fn assert_formats(source: &str, expected: &str) {
+    let formatted = format(source).unwrap();
+    assert_eq!(formatted, expected);
+    assert_eq!(format(expected).unwrap(), expected);
+}
+
+#[test]
+fn super_blocks() {
+    assert_formats(
+        "fn main() {\n    let func = soupa!(move ||{ work(super{foo.clone( )},super (bar) { Arc::clone(&bar) }, bar, baz, qux_quux_corge_grault) });\n}\n",
+        "fn main() {\n    let func = soupa!(move || {\n        work(\n            super { foo.clone() },\n            super(bar) { Arc::clone(&bar) },\n            bar,\n            baz,\n            qux_quux_corge_grault,\n        )\n    });\n}\n",
+    );
+}
+
+#[test]
+fn comments() {
+    assert_formats(
+        "fn main() {\n    let func = soupa!(move || {\n            // Kept\n        super {   foo.clone() /* also kept */ }\n    });\n}\n",
+        "fn main() {\n    let func = soupa!(move || {\n        // Kept\n        super {\n            foo.clone() /* also kept */\n        }\n    });\n}\n",
+    );
+}
+
+#[test]
+fn attributes() {
+    assert_formats(
+        "fn main() {\n    let func = soupa!(move || #[cfg(all())]   super { foo.clone() });\n}\n",
+        "fn main() {\n    let func = soupa!(move || #[cfg(all())]\n    super { foo.clone() });\n}\n",
+    );
+}
+
+#[test]
+fn brace_delimited() {
+    // rustfmt doesn't format brace-delimited macro invocations
+    let source = "fn main() {\n    let func = soupa! { move ||   super{foo.clone()} };\n}\n";
+    assert_formats(source, source);
+}
+
+#[test]
+fn masking() {
+    let masked = mask("soupa!(move || super { foo } + super(bar) { bar })").unwrap();
+    assert_eq!(
+        masked.source,
+        "soupa!(move || _s000({ foo }) + _s001({ bar }))"
+    );
+
+    let restored = masked
+        .unmask("soupa!(move || _s000({ foo }) + _s001({ bar }))")
+        .unwrap();
+    assert_eq!(
+        restored,
+        "soupa!(move || super { foo } + super(bar) { bar })"
+    );
+
+    let masked = mask("soupa!(move || super ( b,after=a ) { b })").unwrap();
+    assert_eq!(
+        masked.unmask(&masked.source).unwrap(),
+        "soupa!(move || super(b, after = a) { b })",
+    );
+
+    // Super blocks outside of invocations are left alone
+    assert_eq!(mask("super { foo }").unwrap().source, "super { foo }");
+}
+
+#[test]
+fn placeholder_names() {
+    // Calls written in the source aren't mistaken for placeholders
+    let source = "soupa!(move || _s000(super { foo }, _s001()))";
+    let masked = mask(source).unwrap();
+    assert_eq!(
+        masked.source,
+        "soupa!(move || _s000(_t000({ foo }), _s001()))"
+    );
+    assert_eq!(masked.unmask(&masked.source).unwrap(), source);
+
+    // Placeholders share a width, however many there are
+    let source = format!("soupa!(move || ({}))", "super { a }, ".repeat(1001));
+    let masked = mask(&source).unwrap();
+    assert!(masked
+        .source
+        .starts_with("soupa!(move || (_s0000({ a }), _s0001({ a }), "));
+    assert_eq!(masked.unmask(&masked.source).unwrap(), source);
+}
+
+#[test]
+fn span_markers() {
+    assert_formats(
+        "fn main() {\n    spawn(soupa!(super span async move{ work(super{foo.clone()}).await }));\n}\n",
+        "fn main() {\n    spawn(soupa!(super span async move { work(super { foo.clone() }).await }));\n}\n",
+    );
+    assert_formats(
+        "fn main() {\n    let func = soupa!(super span(info_span!(\"job\")) move |x|{ x+1 });\n}\n",
+        "fn main() {\n    let func = soupa!(super span(info_span!(\"job\")) move |x| { x + 1 });\n}\n",
+    );
+
+    let masked = mask("soupa!(super span(a()) move || { super { b } })").unwrap();
+    assert_eq!(masked.source, "soupa!(_s000(move || { _s001({ b }) }))");
+
+    // Markers which don't apply to a block are left for soupa! to report
+    let masked = mask("soupa!(super span move || work())").unwrap();
+    assert_eq!(masked.source, "soupa!(super span move || work())");
+}
+
+#[test]
+fn capture_adapters() {
+    assert_formats(
+        "fn main() {\n    let func = soupa!(move||super with Cloned{&foo}.len());\n}\n",
+        "fn main() {\n    let func = soupa!(move || super with Cloned { &foo }.len());\n}\n",
+    );
+    assert_formats(
+        "fn main() {\n    let func = soupa!(move||super(a) with adapters::Downgrade {&foo});\n}\n",
+        "fn main() {\n    let func = soupa!(move || super(a) with adapters::Downgrade { &foo });\n}\n",
+    );
+
+    let masked = mask("soupa!(move || super with Cloned { super { b } })").unwrap();
+    assert_eq!(masked.source, "soupa!(move || _s000({ _s001({ b }) }))");
+}
+
+#[test]
+fn shared_super_blocks() {
+    assert_formats(
+        "fn main() {\n    let f = soupa!((move||super share(a){a()}, move||super share(a).len()));\n}\n",
+        "fn main() {\n    let f = soupa!((move || super share(a) { a() }, move || super share(a).len()));\n}\n",
+    );
+
+    let masked = mask("soupa!((move || super share(a) { a() }, move || super share(a)))").unwrap();
+    assert_eq!(
+        masked.source,
+        "soupa!((move || _s000({ a() }), move || _s001()))"
+    );
+}
+
+#[test]
+fn fixtures() {
+    for fixture in ["c", "d", "e", "f"] {
+        let path = format!(
+            "{}/../tests/expand/{fixture}.rs",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let source = fs::read_to_string(path).unwrap();
+
+        // Collapse the indentation of every line, which formatting restores
+        let messy = source
+            .lines()
+            .map(str::trim_start)
+            .collect::<Vec<_>>()
+            .join("\n")
+            + "\n";
+
+        assert_eq!(format(&messy).unwrap(), source, "fixture `{fixture}`");
+    }
+}
@@ -0,0 +1,143 @@
+//! Capture adapters, which customise how a super block captures its value.
+//!
+//! A super block written as `super with Adapter { ... }` passes the value of its
+//! block to the adapter's [`SoupaCapture::capture`] before the closure or async
+//! block is created, and [`SoupaCapture::restore`] is called with the result
+//! where the super block was written.
+//! The adapter is given as a path to a value, such as a unit struct, a constant
+//! or a local variable.
+//!
+//! ```rust
+//! # #[cfg(feature = "std")] {
+//! # use soupa::soupa;
+//! # use std::sync::Arc;
+//! use soupa::adapters::Downgrade;
+//!
+//! let foo = Arc::new(123usize);
+//!
+//! // Only a weak reference to foo is kept by the closure
+//! let func = soupa!(move || super with Downgrade { &foo }.map(|foo| *foo));
+//!
+//! drop(foo);
+//!
+//! assert_eq!(func(), None);
+//! # }
+//! ```
+//!
+//! Plain super blocks aren't written as adapters, as the value they move into the
+//! closure or async block is used as-is, rather than restored from a borrow of
+//! it. Neither are `super span` markers, as they wrap the closure or async block
+//! they apply to, rather than a value within it.
+
+/// Captures a value of type `T` in the scope surrounding a closure or async
+/// block, and restores it within.
+///
+/// # Examples
+///
+/// ```rust
+/// # use soupa::soupa;
+/// use soupa::SoupaCapture;
+/// use std::cell::RefCell;
+///
+/// /// Captures a snapshot of the current contents of a `RefCell`.
+/// struct Snapshot;
+///
+/// impl<'a, T: Clone> SoupaCapture<&'a RefCell<T>> for Snapshot {
+///     type Captured = T;
+///     type Output = T;
+///
+///     fn capture(&self, value: &'a RefCell<T>) -> T {
+///         value.borrow().clone()
+///     }
+///
+///     fn restore(captured: &T) -> T {
+///         captured.clone()
+///     }
+/// }
+///
+/// let config = RefCell::new(String::from("before"));
+///
+/// let func = soupa!(move || super with Snapshot { &config });
+///
+/// *config.borrow_mut() = String::from("after");
+///
+/// assert_eq!(func(), "before");
+/// assert_eq!(func(), "before");
+/// ```
+pub trait SoupaCapture<T> {
+    /// The value moved into the closure or async block.
+    type Captured;
+
+    /// The value the super block evaluates to where it was written.
+    type Output;
+
+    /// Captures `value`, before the closure or async block is created.
+    fn capture(&self, value: T) -> Self::Captured;
+
+    /// Restores a captured value, where the super block was written.
+    ///
+    /// The captured value is borrowed, so a closure which restores it can be
+    /// called more than once.
+    fn restore(captured: &Self::Captured) -> Self::Output;
+}
+
+/// Captures a clone of a borrowed value.
+///
+/// `super with Cloned { &foo }` is equivalent to `super { foo.clone() }`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Cloned;
+
+impl<'a, T: Clone> SoupaCapture<&'a T> for Cloned {
+    type Captured = T;
+    type Output = T;
+
+    #[inline(always)]
+    fn capture(&self, value: &'a T) -> T {
+        value.clone()
+    }
+
+    #[inline(always)]
+    fn restore(captured: &T) -> T {
+        captured.clone()
+    }
+}
+
+/// Captures a weak reference to a borrowed [`Arc`](std::sync::Arc) or
+/// [`Rc`](std::rc::Rc), which is upgraded when restored.
+///
+/// This requires the `std` feature.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Downgrade;
+
+#[cfg(feature = "std")]
+impl<'a, T: ?Sized> SoupaCapture<&'a std::sync::Arc<T>> for Downgrade {
+    type Captured = std::sync::Weak<T>;
+    type Output = Option<std::sync::Arc<T>>;
+
+    #[inline(always)]
+    fn capture(&self, value: &'a std::sync::Arc<T>) -> Self::Captured {
+        std::sync::Arc::downgrade(value)
+    }
+
+    #[inline(always)]
+    fn restore(captured: &Self::Captured) -> Self::Output {
+        captured.upgrade()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, T: ?Sized> SoupaCapture<&'a std::rc::Rc<T>> for Downgrade {
+    type Captured = std::rc::Weak<T>;
+    type Output = Option<std::rc::Rc<T>>;
+
+    #[inline(always)]
+    fn capture(&self, value: &'a std::rc::Rc<T>) -> Self::Captured {
+        std::rc::Rc::downgrade(value)
+    }
+
+    #[inline(always)]
+    fn restore(captured: &Self::Captured) -> Self::Output {
+        captured.upgrade()
+    }
+}
@@ -0,0 +1,115 @@
+//! Plain functions for creating closures from captured values, without a macro.
+//!
+//! [`capture`] takes the values a closure needs, evaluated where it is called,
+//! exactly as the values of super blocks are.
+//! The returned [`Captured`] then produces the closure, which receives them
+//! alongside its argument.
+//!
+//! ```rust
+//! # use std::sync::Arc;
+//! let a = Arc::new(1usize);
+//! let b = Arc::new(2usize);
+//!
+//! let func = soupa::capture((a.clone(), b.clone())).into_fn(|(a, b), x: usize| **a + **b + x);
+//!
+//! // a and b weren't moved into the closure
+//! assert_eq!(func(3), *a + *b + 3);
+//! ```
+//!
+//! The closure takes a single argument, so several are passed as a tuple, and
+//! none as `()`.
+
+/// Captures `values` for a closure created by one of the methods of [`Captured`].
+///
+/// This is equivalent to placing `values` within a super block.
+#[inline(always)]
+pub fn capture<T>(values: T) -> Captured<T> {
+    Captured { values }
+}
+
+/// Values captured by [`capture`], which can be turned into a closure.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Captured<T> {
+    values: T,
+}
+
+impl<T> Captured<T> {
+    /// Creates an [`Fn`] closure, which borrows the captured values on each call.
+    ///
+    /// ```rust
+    /// let name = String::from("soupa");
+    ///
+    /// let greet = soupa::capture(name.clone())
+    ///     .into_fn(|name, greeting: &str| format!("{greeting}, {name}"));
+    ///
+    /// assert_eq!(greet("Hello"), "Hello, soupa");
+    /// assert_eq!(greet("Bye"), "Bye, soupa");
+    /// ```
+    #[inline(always)]
+    pub fn into_fn<A, R>(self, func: impl Fn(&T, A) -> R) -> impl Fn(A) -> R {
+        let values = self.values;
+        move |arg| func(&values, arg)
+    }
+
+    /// Creates an [`FnMut`] closure, which mutably borrows the captured values on
+    /// each call.
+    ///
+    /// ```rust
+    /// let mut count = soupa::capture(0usize).into_fn_mut(|count, step: usize| {
+    ///     *count += step;
+    ///     *count
+    /// });
+    ///
+    /// assert_eq!(count(1), 1);
+    /// assert_eq!(count(2), 3);
+    /// ```
+    #[inline(always)]
+    pub fn into_fn_mut<A, R>(self, mut func: impl FnMut(&mut T, A) -> R) -> impl FnMut(A) -> R {
+        let mut values = self.values;
+        move |arg| func(&mut values, arg)
+    }
+
+    /// Creates an [`FnOnce`] closure, which takes the captured values.
+    ///
+    /// ```rust
+    /// let name = String::from("soupa");
+    ///
+    /// let take = soupa::capture(name.clone()).into_fn_once(|name, ()| name);
+    ///
+    /// assert_eq!(take(()), name);
+    /// ```
+    #[inline(always)]
+    pub fn into_fn_once<A, R>(self, func: impl FnOnce(T, A) -> R) -> impl FnOnce(A) -> R {
+        let values = self.values;
+        move |arg| func(values, arg)
+    }
+
+    /// Creates an [`Fn`] closure, which is given its own clone of the captured
+    /// values on each call.
+    ///
+    /// This is equivalent to `super { ... }.clone()` within a closure.
+    ///
+    /// ```rust
+    /// let names = soupa::capture(vec![String::from("a")]).into_fn_cloned(|mut names, name: &str| {
+    ///     names.push(String::from(name));
+    ///     names
+    /// });
+    ///
+    /// assert_eq!(names("b"), ["a", "b"]);
+    /// assert_eq!(names("c"), ["a", "c"]);
+    /// ```
+    #[inline(always)]
+    pub fn into_fn_cloned<A, R>(self, func: impl Fn(T, A) -> R) -> impl Fn(A) -> R
+    where
+        T: Clone,
+    {
+        let values = self.values;
+        move |arg| func(values.clone(), arg)
+    }
+
+    /// Takes back the captured values.
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        self.values
+    }
+}
@@ -0,0 +1,602 @@
+//! A guide to the syntax and options of [`soupa!`], beyond the super blocks
+//! shown in its examples.
+//!
+//! # Grammar
+//!
+//! A `super` token starts a super block only when it is followed by one of the
+//! forms below.
+//! These are found at any depth within the invocation, including within labelled
+//! blocks and loops, after `!` and other operators, and within the arguments of
+//! macro calls such as `vec![...]`.
+//!
+//! ```text
+//! SuperBlock :
+//!       OuterAttribute* `super` BlockExpression
+//!     | OuterAttribute* `super` `(` IDENTIFIER ( `,` `after` `=` IDENTIFIER )? `)` BlockExpression
+//!     | `super` ( `(` IDENTIFIER ( `,` `after` `=` IDENTIFIER )? `)` )? `with` Path BlockExpression
+//!     | `super` `share` `(` IDENTIFIER `)` BlockExpression?
+//!     | `super` `span` ( `(` Expression `)` )?
+//!
+//! LiftMarker :
+//!       OuterAttribute* `::`? ( `soupa` `::` )? `lift` `!` DelimTokenTree
+//! ```
+//!
+//! Any other use of `super` is passed through unchanged, so paths such as
+//! `super::module::f({ ... })`, visibilities such as `pub(super)`, and
+//! attributes which aren't directly before a super block keep their meaning
+//! within the body.
+//! `r#super` isn't a valid raw identifier, so is rejected by the compiler before
+//! reaching [`soupa!`].
+//!
+//! ```rust
+//! # use std::sync::Arc;
+//! # use soupa::soupa;
+//! mod outer {
+//!     pub fn double(x: usize) -> usize {
+//!         x * 2
+//!     }
+//!
+//!     pub mod inner {
+//!         # use soupa::soupa;
+//!         # use std::sync::Arc;
+//!         pub fn run(foo: &Arc<usize>) -> usize {
+//!             let func = soupa!(move || 'a: loop {
+//!                 break 'a super::double(*super { foo.clone() });
+//!             });
+//!
+//!             func()
+//!         }
+//!     }
+//! }
+//!
+//! assert_eq!(outer::inner::run(&Arc::new(123)), 246);
+//! ```
+//!
+//! # Attributes
+//!
+//! Outer attributes placed directly before a `super { ... }` block are copied
+//! onto its init statement.
+//! `#[cfg]` attributes are also kept on the block if it begins a statement,
+//! which allows lifted expressions to be conditionally compiled.
+//! Elsewhere, they would be attributes on an expression, which are unstable, so
+//! like other attributes, such as `#[allow]`, they only apply to the init
+//! statement.
+//!
+//! ```rust
+//! # use std::sync::Arc;
+//! # use soupa::soupa;
+//! let foo = Arc::new(123usize);
+//!
+//! let func = soupa!(move || {
+//!     // Neither the init statement nor the use of the temporary are emitted.
+//!     #[cfg(feature = "does-not-exist")]
+//!     super { foo.clone() }.does_not_exist();
+//! });
+//!
+//! func();
+//!
+//! // The lint attribute applies to the init statement alone
+//! let func = soupa!(move || {
+//!     let bar = #[allow(unused)] super { foo.clone() };
+//!     *bar
+//! });
+//!
+//! assert_eq!(func(), 123);
+//! ```
+//!
+//! # Labels
+//!
+//! By default, lifted values are stored in generated temporaries named
+//! `__soupa_temp_a`, `__soupa_temp_b`, and so on.
+//! A super block can instead be given a label with `super(label) { ... }`, which
+//! is used as the name of its temporary.
+//! Borrow checker errors and debugger locals will then refer to the label, and
+//! point at where it was written.
+//!
+//! ```rust
+//! # use std::sync::Arc;
+//! # use soupa::soupa;
+//! let foo = Arc::new(123usize);
+//!
+//! let func = soupa!(move || *super(foo_clone) { foo.clone() });
+//!
+//! let _ = foo;
+//!
+//! assert_eq!(func(), 123);
+//! ```
+//!
+//! Note that the label is an ordinary binding in the scope of the body, so it
+//! will shadow any variable of the same name within it.
+//! It is also in scope for the super blocks evaluated after it, which is what
+//! allows an ordered super block to use it, and shadows variables within those
+//! too.
+//!
+//! ```rust
+//! # use soupa::soupa;
+//! let x = 10;
+//!
+//! // The second super block adds one to the label, rather than to the outer `x`
+//! let func = soupa!(move || super(x) { 1 } + super { x + 1 });
+//!
+//! assert_eq!(func(), 3);
+//! ```
+//!
+//! `soupa!` doesn't derive names for unlabelled temporaries from their
+//! expressions, as a `macro_rules!` macro can't build new identifiers.
+//! Procedural macros lifting super blocks through `soupa-core` can instead name
+//! them after the expression, such as `__soupa_foo_clone` for
+//! `super { foo.clone() }`, with `Naming::Readable`.
+//! Those names are spanned at the super block they were lifted from.
+//!
+//! # Editions
+//!
+//! [`soupa!`] can be used from a crate of any edition.
+//! Anything left unchanged by it keeps the edition it was written in, while a
+//! closure or async block containing a super block is partly written by
+//! [`soupa!`] itself, and follows the rules of edition 2015.
+//!
+//! This means such a closure doesn't support the disjoint captures of edition
+//! 2021, and captures whole variables rather than only the fields it uses.
+//! A closure takes its edition from the tokens it begins and ends with, and a
+//! `macro_rules!` macro can't end a body it has rewritten with the caller's
+//! braces, so this is a known limitation rather than something [`soupa!`] can
+//! fix.
+//! Fields can instead be lifted into the closure by super blocks, or the
+//! variable destructured beforehand.
+//! `soupa-desugar` rewrites invocations into plain Rust, which keeps the
+//! caller's edition.
+//!
+//! ```rust
+//! # use std::sync::Arc;
+//! # use soupa::soupa;
+//! struct Config {
+//!     name: String,
+//!     limit: Arc<usize>,
+//! }
+//!
+//! let config = Config { name: String::from("config"), limit: Arc::new(3) };
+//!
+//! // Only the name is moved out of config, by the super block lifting it
+//! let func = soupa!(move || super { config.name }.len() + *super { config.limit.clone() });
+//!
+//! let limit = config.limit;
+//!
+//! assert_eq!(func(), 9);
+//! assert_eq!(*limit, 3);
+//! ```
+//!
+//! Likewise, a `let` condition in a let chain which ends in a super block is
+//! only accepted if that super block is labelled, as its label is written by
+//! the caller while a temporary isn't.
+//!
+//! ```rust,edition2024
+//! # use std::sync::Arc;
+//! # use soupa::soupa;
+//! let foo = Arc::new(Some(123usize));
+//!
+//! let func = soupa!(move || {
+//!     if let Some(value) = *super(foo) { foo.clone() } && value > 100 {
+//!         value
+//!     } else {
+//!         0
+//!     }
+//! });
+//!
+//! assert_eq!(func(), 123);
+//! ```
+//!
+//! # Evaluation Order
+//!
+//! Lifted expressions are evaluated in the order their super blocks appear
+//! within the invocation: depth-first, left to right, including those within
+//! nested invocations.
+//! This order is part of the API, so setup with side effects, such as taking a
+//! lock and then reading through it, can rely on it.
+//!
+//! An ordered super block, `super(label, after = other) { ... }`, is instead
+//! evaluated after the super block labelled `other`, wherever that appears.
+//!
+//! ```rust
+//! # use std::sync::Mutex;
+//! # use soupa::soupa;
+//! let config = Mutex::new(123usize);
+//!
+//! let func = soupa!(move || {
+//!     let value = super(value, after = guard) { *guard };
+//!     let _guard = super(guard) { config.lock().unwrap() };
+//!     value
+//! });
+//!
+//! assert_eq!(func(), 123);
+//! ```
+//!
+//! Other super blocks keep their order, and several super blocks may follow the
+//! same label.
+//! Following a label which doesn't exist, or ordering super blocks in a cycle,
+//! is a compilation error.
+//!
+//! ```rust,compile_fail
+//! # use soupa::soupa;
+//! let func = soupa!(move || super(value, after = guard) { 123usize });
+//! ```
+//!
+//! # Shared Super Blocks
+//!
+//! A shared super block, `super share(name) { ... }`, is evaluated once, and can
+//! then be used again elsewhere within the invocation by writing `super share(name)`.
+//! Each use is given its own clone of the value, except for the last, which
+//! takes the value itself.
+//! This suits invocations which create several closures needing the same value.
+//!
+//! ```rust
+//! # use std::sync::Arc;
+//! # use soupa::soupa;
+//! let log = Arc::new(String::from("log"));
+//!
+//! let (on_ok, on_err) = soupa!((
+//!     move |value: usize| (super share(log) { log.clone() }, value),
+//!     move |error: &str| (super share(log), error.len()),
+//! ));
+//!
+//! assert_eq!(on_ok(1), (log.clone(), 1));
+//! assert_eq!(on_err("oops"), (log, 4));
+//! ```
+//!
+//! The block is evaluated where its super block appears, so it must be given at
+//! the first use, and giving it at a later use is an error.
+//! If no use gives a block, the variable `name` from the surrounding scope is
+//! shared instead.
+//!
+//! ```rust,compile_fail
+//! # use std::sync::Arc;
+//! # use soupa::soupa;
+//! let log = Arc::new(String::from("log"));
+//!
+//! // The first use would clone the log from the surrounding scope
+//! let (on_ok, on_err) = soupa!((
+//!     move |value: usize| (super share(log), value),
+//!     move |error: &str| (super share(log) { Arc::new(String::new()) }, error.len()),
+//! ));
+//! ```
+//!
+//! # Struct Literals
+//!
+//! A struct full of callbacks, such as a table of handlers, can be built within
+//! a single invocation.
+//! Each field's closure ends at the `,` after it, so the super blocks of every
+//! field are lifted into the same place, before the struct is built.
+//!
+//! ```rust
+//! # use std::sync::Arc;
+//! # use soupa::soupa;
+//! struct Handlers<O, C> {
+//!     on_open: O,
+//!     on_close: C,
+//! }
+//!
+//! let log = Arc::new(String::from("log"));
+//!
+//! let handlers = soupa!(Handlers {
+//!     on_open: move || super share(log) { log.clone() }.len(),
+//!     on_close: move |code: usize| (super share(log), code),
+//! });
+//!
+//! assert_eq!((handlers.on_open)(), 3);
+//! assert_eq!((handlers.on_close)(1), (log, 1));
+//! ```
+//!
+//! A field which must be `Fn` can't move a lifted value out of its closure, as
+//! the value is only captured once.
+//! The compiler reports it as a move out of a captured variable, which points at
+//! the whole invocation unless the super block is labelled, in which case it
+//! points at where the value is moved.
+//! The temporaries of unlabelled super blocks have no span of their own, as
+//! `soupa!` can only name a temporary with a span from where it was written by
+//! using its label.
+//! Cloning it within the closure, as in `super(label) { ... }.clone()`, avoids
+//! the move.
+//!
+//! ```rust,compile_fail
+//! # use soupa::soupa;
+//! struct Handlers<F: Fn()> {
+//!     on_close: F,
+//! }
+//!
+//! let name = String::from("name");
+//!
+//! // error[E0507]: cannot move out of value, a captured variable in an `Fn` closure
+//! let handlers = soupa!(Handlers {
+//!     on_close: move || drop(super(owned) { name.clone() }),
+//! });
+//! ```
+//!
+//! # Deduplication
+//!
+//! With the `@dedup` option, super blocks with identical tokens are only
+//! evaluated once, and each repeat is given a clone of the first's value.
+//! A repeated super block whose value can't be cloned is a compilation error.
+//!
+//! ```rust
+//! # use std::cell::Cell;
+//! # use soupa::soupa;
+//! let loads = Cell::new(0);
+//! let load = || {
+//!     loads.set(loads.get() + 1);
+//!     String::from("config")
+//! };
+//!
+//! let (a, b) = soupa!(@dedup (
+//!     move || super { load() }.len(),
+//!     move || super { load() },
+//! ));
+//!
+//! assert_eq!(loads.get(), 1);
+//! assert_eq!((a(), b()), (6, String::from("config")));
+//! ```
+//!
+//! Labelled, attributed and shared super blocks, along with those with a capture
+//! adapter, are never deduplicated.
+//!
+//! ```rust,compile_fail
+//! # use soupa::soupa;
+//! struct NotClone;
+//!
+//! // error[E0599]: no method named `clone` found for struct `NotClone`
+//! let funcs = soupa!(@dedup (move || super { NotClone }, move || super { NotClone }));
+//! ```
+//!
+//! # Lift Markers
+//!
+//! As `super { ... }` is not valid Rust syntax, tools such as rust-analyzer and
+//! rustfmt can't understand a body which contains one.
+//! The [`lift!`] marker can be used in the same positions as an alternative
+//! which _is_ valid syntax.
+//!
+//! ```rust
+//! # use std::sync::Arc;
+//! # use soupa::soupa;
+//! let foo = Arc::new(123usize);
+//!
+//! let func = soupa!(move || *soupa::lift!(foo.clone()));
+//!
+//! let _ = foo;
+//!
+//! assert_eq!(func(), 123);
+//! ```
+//!
+//! A marker isn't recognised when it continues a path, such as
+//! `other::lift!(...)`, which is passed through unchanged.
+//! As an identifier followed by `::` is taken as the start of a path, a marker
+//! beginning with `::` also isn't recognised directly after any identifier or
+//! keyword, such as `return`, so is written as `return lift!(...)` instead.
+//!
+//! # Loops
+//!
+//! A super block within a loop is still evaluated only once, before the
+//! closure is created.
+//! If its value is moved within the loop, the borrow checker will report a use
+//! of a moved value within the generated code.
+//!
+//! ```rust,compile_fail
+//! # use std::sync::mpsc::{channel, Sender};
+//! # use soupa::soupa;
+//! # fn work(_: Sender<usize>) {}
+//! let (tx, rx) = channel::<usize>();
+//!
+//! // The clone of tx would be moved on every iteration
+//! let func = soupa!(move || for _ in 0..3 { work(super { tx.clone() }) });
+//! ```
+//!
+//! Instead, clone the lifted value on each use.
+//!
+//! ```rust
+//! # use std::sync::mpsc::{channel, Sender};
+//! # use soupa::soupa;
+//! # fn work(_: Sender<usize>) {}
+//! let (tx, rx) = channel::<usize>();
+//!
+//! let func = soupa!(move || for _ in 0..3 { work(super { tx.clone() }.clone()) });
+//! ```
+//!
+//! In [strict mode](#strict-mode), a warning pointing out the problem is
+//! emitted alongside it.
+//!
+//! # Lazy Branches
+//!
+//! By default, every super block is evaluated at the start of the invocation,
+//! even when it sits in a branch which isn't taken.
+//! With the `@lazy` option, a super block within the block of an `if`, `else`
+//! or match arm is instead lifted to the start of that block, so it is only
+//! evaluated if the branch is.
+//!
+//! ```rust
+//! # use std::cell::Cell;
+//! # use soupa::soupa;
+//! let evaluated = Cell::new(false);
+//! let expensive = || {
+//!     evaluated.set(true);
+//!     123usize
+//! };
+//!
+//! let func: Box<dyn Fn() -> usize> = soupa!(@lazy if false {
+//!     Box::new(move || super { expensive() })
+//! } else {
+//!     Box::new(|| 0)
+//! });
+//!
+//! assert!(!evaluated.get());
+//! ```
+//!
+//! Only branches outside of any closure are lifted into, as a branch within a
+//! closure is evaluated each time it is called.
+//! A closure without a block body ends at the next `;`, or `,` which isn't
+//! within angle brackets, such as those of `collect::<HashMap<K, V>>()`.
+//! A match arm must have a block to be lifted into, otherwise its super blocks
+//! are lifted along with the match.
+//! Each block is expanded as an invocation of its own, with the `@lazy`,
+//! `@strict` and `@dedup` options, so shared, deduplicated and ordered super
+//! blocks only refer to others within the same block.
+//! [`explain!`] shows each block as the invocation it is expanded by.
+//!
+//! # Strict Mode
+//!
+//! Lifting a super block which isn't within a closure or async block only
+//! changes when it is evaluated, which is more likely to surprise a reader than
+//! to be useful.
+//! Starting an invocation with `@strict` warns about any such super blocks.
+//!
+//! ```rust,compile_fail
+//! # #![deny(deprecated)]
+//! # use std::sync::Arc;
+//! # use soupa::soupa;
+//! # fn work(_: Arc<usize>) {}
+//! let foo = Arc::new(123usize);
+//!
+//! // Warns, as nothing is deferred
+//! soupa!(@strict work(super { foo.clone() }));
+//! ```
+//!
+//! A super block counts as within a closure if it follows a `|`, `||` or `async`
+//! token within the same statement or argument, or is nested within a group
+//! which does.
+//!
+//! Strict mode also warns about super blocks which may be moved within a loop,
+//! as their value is only evaluated once.
+//!
+//! ```rust,compile_fail
+//! # #![deny(deprecated)]
+//! # use std::sync::Arc;
+//! # use soupa::soupa;
+//! # fn work(_: Arc<usize>) {}
+//! let foo = Arc::new(123usize);
+//!
+//! // Warns, as the clone of foo would be moved on every iteration
+//! soupa!(@strict move || for _ in 0..3 { work(super { foo.clone() }) });
+//! ```
+//!
+//! A super block counts as within a loop if it is nested within the body of a
+//! `for`, `while` or `loop` written within the invocation.
+//! No warning is emitted when the super block is immediately borrowed, indexed
+//! or has a method called on it.
+//! Moving a value which is `Copy` is fine, but can't be told apart from other
+//! moves, which is why this warning is only emitted in strict mode.
+//!
+//! Each warning is emitted as a use of a deprecated item, so can be silenced with
+//! `#[allow(deprecated)]`.
+//!
+//! # Assertions
+//!
+//! A closure or future which isn't `Send` or `'static` is usually only reported
+//! where it is passed to a function requiring it, such as `std::thread::spawn`.
+//! Starting an invocation with `@send`, `@sync` or `@static` checks its output
+//! meets that bound where it is written instead.
+//!
+//! ```rust,compile_fail
+//! # use std::rc::Rc;
+//! # use soupa::soupa;
+//! let foo = Rc::new(123usize);
+//!
+//! // error: `Rc<usize>` cannot be sent between threads safely
+//! // note: required by a bound in `foo_clone`
+//! let func = soupa!(@send move || *super(foo_clone) { foo.clone() });
+//! ```
+//!
+//! Each lifted value is checked before the output, so if one is responsible the
+//! error names its label, or its temporary name if it has none.
+//! Options can be combined, and the checks have no cost at runtime.
+//!
+//! ```rust
+//! # use std::sync::Arc;
+//! # use soupa::soupa;
+//! let foo = Arc::new(123usize);
+//!
+//! let func = soupa!(@send @static move || *super { foo.clone() });
+//!
+//! std::thread::spawn(func);
+//! ```
+//!
+//! # Boxed Output
+//!
+//! With the `alloc` feature enabled, starting an invocation with
+//! `@boxed(Trait + Bounds)` boxes its output as `Box<dyn Trait + Bounds>`, or
+//! as `Pin<Box<dyn Future<...> + Bounds>>` when the trait is `Future`.
+//! Any `Send`, `Sync` or lifetime bounds are given alongside the trait, and the
+//! parameters of a boxed closure are inferred from it.
+//!
+//! ```rust
+//! # #[cfg(feature = "alloc")] {
+//! # use std::sync::Arc;
+//! # use soupa::soupa;
+//! struct Event(usize);
+//!
+//! let foo = Arc::new(123usize);
+//!
+//! let mut callbacks: Vec<Box<dyn Fn(Event) -> usize + Send>> = Vec::new();
+//!
+//! callbacks.push(soupa!(@boxed(Fn(Event) -> usize + Send) move |event| {
+//!     event.0 + *super { foo.clone() }
+//! }));
+//!
+//! assert_eq!(callbacks[0](Event(1)), 124);
+//! # }
+//! ```
+//!
+//! `Future` is written without a path, and refers to [`core::future::Future`].
+//! The output is boxed before the checks of any assertion options are applied.
+//!
+//! # Spans
+//!
+//! With the `tracing` feature enabled, writing `super span` before an async block
+//! lifts the current span, and instruments the block with it.
+//! Written before a closure with a block body, the span is instead entered
+//! around the closure's body each time it is called.
+//! Either way, the work is attributed to the span it was created within, rather
+//! than whichever span is current when it runs.
+//!
+//! ```rust,edition2018
+//! # #[cfg(feature = "tracing")] {
+//! # use soupa::soupa;
+//! # fn spawn<T>(_: T) {}
+//! # let state = std::sync::Arc::new(123usize);
+//! spawn(soupa!(super span async move {
+//!     tracing::info!(state = *super { state.clone() });
+//! }));
+//!
+//! // A span can also be given, which is lifted like a super block
+//! let func = soupa!(super span(tracing::info_span!("job")) move || {
+//!     tracing::info!("working");
+//! });
+//! # }
+//! ```
+//!
+//! Async closures aren't supported, as entering a span around their body would
+//! hold it across await points.
+//!
+//! # Capture Adapters
+//!
+//! A super block written as `super with Adapter { ... }` captures its value
+//! through a [`SoupaCapture`] implementation, which can restore it as something
+//! else where the super block was written.
+//! The captured value is only borrowed to restore it, so the closure can be
+//! called more than once.
+//! It can be labelled as `super(label) with Adapter { ... }`.
+//!
+//! ```rust
+//! # use soupa::soupa;
+//! use soupa::adapters::Cloned;
+//!
+//! let foo = String::from("foo");
+//!
+//! let func = soupa!(move || super with Cloned { &foo });
+//!
+//! assert_eq!(func(), foo);
+//! assert_eq!(func(), foo);
+//! ```
+//!
+//! See the [`adapters`] module for the adapters provided.
+//!
+//! [`soupa!`]: crate::soupa
+//! [`lift!`]: crate::lift
+//! [`explain!`]: crate::explain
+//! [`SoupaCapture`]: crate::SoupaCapture
+//! [`adapters`]: crate::adapters
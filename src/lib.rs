@@ -1,6 +1,35 @@
 #![doc = include_str!("../README.md")]
 #![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "tokio")]
+extern crate tokio;
+
+#[cfg(feature = "async-std")]
+extern crate async_std;
+
+#[cfg(feature = "smol")]
+extern crate smol;
+
+#[cfg(feature = "tracing")]
+extern crate tracing;
+
+pub mod adapters;
+pub mod combinators;
+pub mod guide;
+#[cfg(any(feature = "tokio", feature = "async-std", feature = "smol"))]
+pub mod task;
+#[cfg(feature = "std")]
+pub mod thread;
+
+pub use adapters::SoupaCapture;
+pub use combinators::{capture, Captured};
+
 /// Provides access to `super` blocks, a hypothetical language feature which
 /// reorders inline `super { ... }` blocks into init statements at the top of the
 /// inner scope.
@@ -24,302 +53,4367 @@
 ///
 /// func();
 /// ```
+///
+/// See the [`guide`] for the full syntax of super blocks and the options
+/// accepted by [`soupa!`].
 #[macro_export]
 macro_rules! soupa {
     (
+        @config: { dedup $($option:tt)* },
         @temps { $($temp:ident)* },
         @stack: {},
         @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
         @body: { $($body:tt)* },
     ) => {
         // Stack is empty
-        // Output the initialization and body statements
-        {
-            $($init)*
-            $($body)*
+        // Deduplicate super blocks before anything else
+        $crate::soupa! {
+            @dedup,
+            @seen: {},
+            @config: { dedup $($option)* },
+            @lints: { $($lint)* },
+            @init: {},
+            @rest: { $($init)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {},
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Stack is empty
+        // Resolve the uses of shared super blocks before ordering
+        $crate::soupa! {
+            @share,
+            @config: $config,
+            @lints: { $($lint)* },
+            @init: {},
+            @rest: { $($init)* },
+            @body: { $($body)* },
+        }
+    };
+
+    (
+        @dedup,
+        @seen: $seen:tt,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @init: $init:tt,
+        @rest: {},
+        @body: $body:tt,
+    ) => {
+        // All super blocks are deduplicated
+        // Resolve the uses of shared super blocks before ordering
+        $crate::soupa! {
+            @share,
+            @config: $config,
+            @lints: $lints,
+            @init: {},
+            @rest: $init,
+            @body: $body,
+        }
+    };
+    (
+        @dedup,
+        @seen: $seen:tt,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @init: $init:tt,
+        @rest: {
+            @dedup $temp:ident $block:tt
+            $($rest:tt)*
+        },
+        @body: $body:tt,
+    ) => {
+        // A super block can be deduplicated
+        // Find whether an identical one has been seen
+        $crate::soupa! {
+            @dedup_find ($) $temp $block in $seen,
+            @dedup,
+            @seen: $seen,
+            @config: $config,
+            @lints: $lints,
+            @init: $init,
+            @rest: { $($rest)* },
+            @body: $body,
+        }
+    };
+    (
+        @dedup,
+        @seen: $seen:tt,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @init: { $($init:tt)* },
+        @rest: {
+            $(#[$($attr:tt)*])*
+            let $ident:ident = $value:tt;
+            $($rest:tt)*
+        },
+        @body: $body:tt,
+    ) => {
+        // Keep other statements as they are
+        $crate::soupa! {
+            @dedup,
+            @seen: $seen,
+            @config: $config,
+            @lints: $lints,
+            @init: {
+                $($init)*
+                $(#[$($attr)*])*
+                let $ident = $value;
+            },
+            @rest: { $($rest)* },
+            @body: $body,
+        }
+    };
+    (
+        @dedup,
+        @seen: $seen:tt,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @init: { $($init:tt)* },
+        @rest: {
+            @share $name:ident $temp:ident
+            $($rest:tt)*
+        },
+        @body: $body:tt,
+    ) => {
+        // Keep other statements as they are
+        $crate::soupa! {
+            @dedup,
+            @seen: $seen,
+            @config: $config,
+            @lints: $lints,
+            @init: {
+                $($init)*
+                @share $name $temp
+            },
+            @rest: { $($rest)* },
+            @body: $body,
+        }
+    };
+    (
+        @dedup,
+        @seen: $seen:tt,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @init: { $($init:tt)* },
+        @rest: {
+            @after $after:ident $label:ident $stmt:tt
+            $($rest:tt)*
+        },
+        @body: $body:tt,
+    ) => {
+        // Keep other statements as they are
+        $crate::soupa! {
+            @dedup,
+            @seen: $seen,
+            @config: $config,
+            @lints: $lints,
+            @init: {
+                $($init)*
+                @after $after $label $stmt
+            },
+            @rest: { $($rest)* },
+            @body: $body,
+        }
+    };
+    (
+        @dedup_find ($d:tt) $temp:ident $block:tt in { $(($name:ident $seen:tt { $($prev:ident)* }))* },
+        $($state:tt)*
+    ) => {{
+        // Blocks can only be compared by matching them against each other
+        // The names of those seen are passed in, as names written here wouldn't resolve
+        macro_rules! __soupa_dedup {
+            $(
+                ($seen { $($prev)* $d name:ident $d($d names:ident)* } $d($d state:tt)*) => {
+                    $crate::soupa! { @dedup_found $d name $d($d state)* }
+                };
+            )*
+            ($d other:tt $d names:tt $d($d state:tt)*) => {
+                $crate::soupa! { @dedup_missing $d($d state)* }
+            };
+        }
+
+        __soupa_dedup! { $block { $($name)* } $temp $block, $($state)* }
+    }};
+    (
+        @dedup_found $name:ident $temp:ident $block:tt,
+        @dedup,
+        @seen: $seen:tt,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @init: { $($init:tt)* },
+        @rest: $rest:tt,
+        @body: $body:tt,
+    ) => {
+        // An identical super block has been seen, so use a clone of its value
+        $crate::soupa! {
+            @dedup,
+            @seen: $seen,
+            @config: $config,
+            @lints: $lints,
+            @init: {
+                $($init)*
+                let $temp = { $name.clone() };
+            },
+            @rest: $rest,
+            @body: $body,
+        }
+    };
+    (
+        @dedup_missing $temp:ident $block:tt,
+        @dedup,
+        @seen: { $(($name:ident $seen:tt $prev:tt))* },
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @init: { $($init:tt)* },
+        @rest: $rest:tt,
+        @body: $body:tt,
+    ) => {
+        // No identical super block has been seen, so evaluate this one
+        $crate::soupa! {
+            @dedup,
+            @seen: {
+                $(($name $seen $prev))*
+                ($temp $block { $($name)* })
+            },
+            @config: $config,
+            @lints: $lints,
+            @init: {
+                $($init)*
+                let $temp = $block;
+            },
+            @rest: $rest,
+            @body: $body,
+        }
+    };
+    (
+        @share,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @init: $init:tt,
+        @rest: {},
+        @body: $body:tt,
+    ) => {
+        // All uses of shared super blocks are resolved
+        // Order the initialization statements before output
+        $crate::soupa! {
+            @order,
+            @config: $config,
+            @lints: $lints,
+            @done: {},
+            @waiting: {},
+            @init: {},
+            @rest: $init,
+            @body: $body,
+        }
+    };
+    (
+        @share,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @init: $init:tt,
+        @rest: {
+            @share $name:ident $temp:ident
+            $($rest:tt)*
+        },
+        @body: $body:tt,
+    ) => {
+        // A use of a shared super block takes the shared value if it is the last use
+        // Find whether any uses follow it
+        $crate::soupa! {
+            @share_names $name {} {} { $($rest)* },
+            @share_use $name $temp,
+            @config: $config,
+            @lints: $lints,
+            @init: $init,
+            @rest: { $($rest)* },
+            @body: $body,
+        }
+    };
+    (
+        @share,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @init: { $($init:tt)* },
+        @rest: {
+            $(#[$($attr:tt)*])*
+            let $ident:ident = $value:tt;
+            $($rest:tt)*
+        },
+        @body: $body:tt,
+    ) => {
+        // Keep other statements as they are
+        $crate::soupa! {
+            @share,
+            @config: $config,
+            @lints: $lints,
+            @init: {
+                $($init)*
+                $(#[$($attr)*])*
+                let $ident = $value;
+            },
+            @rest: { $($rest)* },
+            @body: $body,
+        }
+    };
+    (
+        @share,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @init: { $($init:tt)* },
+        @rest: {
+            @after $after:ident $label:ident $stmt:tt
+            $($rest:tt)*
+        },
+        @body: $body:tt,
+    ) => {
+        // Keep other statements as they are
+        $crate::soupa! {
+            @share,
+            @config: $config,
+            @lints: $lints,
+            @init: {
+                $($init)*
+                @after $after $label $stmt
+            },
+            @rest: { $($rest)* },
+            @body: $body,
+        }
+    };
+    (
+        @share_names $name:ident $names:tt { $($lets:ident)* } {}, $($state:tt)*
+    ) => {
+        // The names of all following uses and statements have been collected
+        // Check the block of the shared super block isn't given later
+        $crate::soupa! {
+            @find ($) $name in { $($lets)* }, @share_given $name $names, $($state)*
+        }
+    };
+    (
+        @share_names $name:ident { $($names:ident)* } $lets:tt {
+            @share $other:ident $temp:ident
+            $($rest:tt)*
+        }, $($state:tt)*
+    ) => {
+        // Collect the name of a following use
+        $crate::soupa! {
+            @share_names $name { $($names)* $other } $lets { $($rest)* }, $($state)*
+        }
+    };
+    (
+        @share_names $name:ident $names:tt { $($lets:ident)* } {
+            $(#[$($attr:tt)*])*
+            let $ident:ident = $value:tt;
+            $($rest:tt)*
+        }, $($state:tt)*
+    ) => {
+        // Collect the name of a following statement
+        $crate::soupa! {
+            @share_names $name $names { $($lets)* $ident } { $($rest)* }, $($state)*
+        }
+    };
+    (
+        @share_names $name:ident $names:tt $lets:tt {
+            @after $after:ident $label:ident $stmt:tt
+            $($rest:tt)*
+        }, $($state:tt)*
+    ) => {
+        // Skip other statements
+        $crate::soupa! {
+            @share_names $name $names $lets { $($rest)* }, $($state)*
+        }
+    };
+    (
+        @found, @share_given $name:ident $names:tt, $($state:tt)*
+    ) => {
+        // The block is given after this use, which would otherwise clone a variable from the surrounding scope
+        compile_error!(concat!(
+            "the block of `super share(",
+            stringify!($name),
+            ") { ... }` must be given at its first use"
+        ))
+    };
+    (
+        @missing, @share_given $name:ident { $($names:ident)* }, $($state:tt)*
+    ) => {
+        // Find whether any uses follow this one
+        $crate::soupa! {
+            @find ($) $name in { $($names)* }, $($state)*
+        }
+    };
+    (
+        @found,
+        @share_use $name:ident $temp:ident,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @init: { $($init:tt)* },
+        @rest: $rest:tt,
+        @body: $body:tt,
+    ) => {
+        // Another use follows, so this one is given a clone
+        $crate::soupa! {
+            @share,
+            @config: $config,
+            @lints: $lints,
+            @init: {
+                $($init)*
+                let $temp = { $name.clone() };
+            },
+            @rest: $rest,
+            @body: $body,
+        }
+    };
+    (
+        @missing,
+        @share_use $name:ident $temp:ident,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @init: { $($init:tt)* },
+        @rest: $rest:tt,
+        @body: $body:tt,
+    ) => {
+        // This is the last use, so it takes the shared value
+        $crate::soupa! {
+            @share,
+            @config: $config,
+            @lints: $lints,
+            @init: {
+                $($init)*
+                let $temp = { $name };
+            },
+            @rest: $rest,
+            @body: $body,
+        }
+    };
+
+    (
+        @span $span:tt $head:tt $copy:tt { { $($block:tt)* } $($rest:tt)* }, $($state:tt)*
+    ) => {
+        // Found the block the span marker applies to
+        $crate::soupa! {
+            @span_block $span $head $copy { $($block)* } { $($rest)* }, $($state)*
+        }
+    };
+    (
+        @span $span:tt $head:tt $copy:tt { , $($rest:tt)* }, $($state:tt)*
+    ) => {
+        // The span marker doesn't apply to a block
+        compile_error!("`super span` must be followed by an async block, or a closure with a block body")
+    };
+    (
+        @span $span:tt $head:tt $copy:tt { ; $($rest:tt)* }, $($state:tt)*
+    ) => {
+        // The span marker doesn't apply to a block
+        compile_error!("`super span` must be followed by an async block, or a closure with a block body")
+    };
+    (
+        @span $span:tt $head:tt $copy:tt {}, $($state:tt)*
+    ) => {
+        // The span marker doesn't apply to a block
+        compile_error!("`super span` must be followed by an async block, or a closure with a block body")
+    };
+    (
+        @span $span:tt { $($head:tt)* } { $($copy:tt)* } { $next:tt $($rest:tt)* }, $($state:tt)*
+    ) => {
+        // Collect the tokens before the block
+        // A copy is kept, as checking them against keywords consumes them
+        $crate::soupa! {
+            @span $span { $($head)* $next } { $($copy)* $next } { $($rest)* }, $($state)*
         }
     };
 
     (
+        @span_block $span:tt { async } { $async:tt } { $($block:tt)* } { $($rest:tt)* },
+        @config: $config:tt,
         @temps { $next_ident:ident $($temp:ident)* },
         @stack: {
             {
-                @paren: $top_paren:tt,
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: $top_scope:tt,
                 @body: { $($top_body:tt)* },
-                @rest: {
-                    super { $($next:tt)* }
-                    $($top_rest:tt)*
-                },
+                @rest: {},
             }
             $($stack:tt)*
         },
         @init: { $($init:tt)* },
-        @body: { $($body:tt)* },
+        @lints: $lints:tt,
+        @body: $body:tt,
     ) => {
-        // Process a super block into an init statement
-        // Place an identifier of the declaration into the top of the stack
+        // The span marker applies to an async block
+        // Lift the span, and instrument the block with it
         $crate::soupa! {
+            @config: $config,
             @temps { $($temp)* },
             @stack: {
                 {
-                    @paren: $top_paren,
+                    @paren: ($top_kind),
+                    @scope: $top_scope,
                     @body: {
                         $($top_body)*
-                        $next_ident
+                        $crate::__private::instrument
+                    },
+                    @rest: {
+                        ($async { $($block)* }, $next_ident)
+                        $($rest)*
+                    },
+                }
+                $($stack)*
+            },
+            @init: {
+                $($init)*
+                let $next_ident = $span;
+            },
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @span_block $span:tt { async move } { $async:tt $move:tt } { $($block:tt)* } { $($rest:tt)* },
+        @config: $config:tt,
+        @temps { $next_ident:ident $($temp:ident)* },
+        @stack: {
+            {
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: $top_scope:tt,
+                @body: { $($top_body:tt)* },
+                @rest: {},
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // The span marker applies to an async move block
+        // Lift the span, and instrument the block with it
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ($top_kind),
+                    @scope: $top_scope,
+                    @body: {
+                        $($top_body)*
+                        $crate::__private::instrument
+                    },
+                    @rest: {
+                        ($async $move { $($block)* }, $next_ident)
+                        $($rest)*
+                    },
+                }
+                $($stack)*
+            },
+            @init: {
+                $($init)*
+                let $next_ident = $span;
+            },
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @span_block $span:tt { async $($head:tt)* } $copy:tt $block:tt $rest:tt, $($state:tt)*
+    ) => {
+        // Entering a span around the body of an async closure would hold it across await points
+        compile_error!("`super span` can't be used with async closures, use an async block instead")
+    };
+    (
+        @span_block $span:tt $head:tt { $($copy:tt)* } { $($block:tt)* } { $($rest:tt)* },
+        @config: $config:tt,
+        @temps { $next_ident:ident $($temp:ident)* },
+        @stack: {
+            {
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: $top_scope:tt,
+                @body: $top_body:tt,
+                @rest: {},
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // The span marker applies to a closure
+        // Lift the span, and enter it for the whole of the closure's body
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ($top_kind),
+                    @scope: $top_scope,
+                    @body: $top_body,
+                    @rest: {
+                        $($copy)* {
+                            let __soupa_entered = $next_ident.enter();
+                            $($block)*
+                        }
+                        $($rest)*
+                    },
+                }
+                $($stack)*
+            },
+            @init: {
+                $($init)*
+                let $next_ident = $span;
+            },
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+
+    (
+        @order,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @done: $done:tt,
+        @waiting: {},
+        @init: $init:tt,
+        @rest: {},
+        @body: $body:tt,
+    ) => {
+        // All initialization statements are ordered
+        // Add any checks they require
+        $crate::soupa! {
+            @assert $config {} $done $init $body $lints
+        }
+    };
+    (
+        @order,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @done: $done:tt,
+        @waiting: {
+            @after $after:ident $label:ident $stmt:tt
+            $($waiting:tt)*
+        },
+        @init: $init:tt,
+        @rest: {},
+        @body: $body:tt,
+    ) => {
+        // A statement is still waiting, so the label it follows doesn't exist
+        compile_error!(concat!(
+            "cannot lift `",
+            stringify!($label),
+            "` after `",
+            stringify!($after),
+            "`, as no other super block is labelled `",
+            stringify!($after),
+            "` or the order is circular",
+        ))
+    };
+    (
+        @order,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @done: { $($done:ident)* },
+        @waiting: {},
+        @init: { $($init:tt)* },
+        @rest: {
+            $(#[$($attr:tt)*])*
+            let $ident:ident = $value:tt;
+            $($rest:tt)*
+        },
+        @body: $body:tt,
+    ) => {
+        // Output the next statement
+        // Nothing is waiting for it
+        $crate::soupa! {
+            @order,
+            @config: $config,
+            @lints: $lints,
+            @done: { $($done)* $ident },
+            @waiting: {},
+            @init: {
+                $($init)*
+                $(#[$($attr)*])*
+                let $ident = $value;
+            },
+            @rest: { $($rest)* },
+            @body: $body,
+        }
+    };
+    (
+        @order,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @done: { $($done:ident)* },
+        @waiting: { $($waiting:tt)* },
+        @init: { $($init:tt)* },
+        @rest: {
+            $(#[$($attr:tt)*])*
+            let $ident:ident = $value:tt;
+            $($rest:tt)*
+        },
+        @body: $body:tt,
+    ) => {
+        // Output the next statement
+        // Release anything waiting for it
+        $crate::soupa! {
+            @release $ident,
+            @config: $config,
+            @lints: $lints,
+            @done: { $($done)* $ident },
+            @waiting: {},
+            @check: { $($waiting)* },
+            @released: {},
+            @init: {
+                $($init)*
+                $(#[$($attr)*])*
+                let $ident = $value;
+            },
+            @rest: { $($rest)* },
+            @body: $body,
+        }
+    };
+    (
+        @order,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @done: { $($done:ident)* },
+        @waiting: $waiting:tt,
+        @init: $init:tt,
+        @rest: {
+            @after $after:ident $label:ident $stmt:tt
+            $($rest:tt)*
+        },
+        @body: $body:tt,
+    ) => {
+        // The next statement must follow another
+        // Output it now if that has been output, otherwise wait for it
+        $crate::soupa! {
+            @find ($) $after in { $($done)* },
+            @config: $config,
+            @lints: $lints,
+            @done: { $($done)* },
+            @waiting: $waiting,
+            @init: $init,
+            @rest: {
+                @after $after $label $stmt
+                $($rest)*
+            },
+            @body: $body,
+        }
+    };
+    (
+        @find ($d:tt) $after:ident in { $($done:ident)* },
+        $($state:tt)*
+    ) => {{
+        // Labels can only be compared by matching them against each other
+        macro_rules! __soupa_find {
+            $(
+                ($done $d($d state:tt)*) => {
+                    $crate::soupa! { @found, $d($d state)* }
+                };
+            )*
+            ($d other:ident $d($d state:tt)*) => {
+                $crate::soupa! { @missing, $d($d state)* }
+            };
+        }
+
+        __soupa_find! { $after $($state)* }
+    }};
+    (
+        @found,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @done: $done:tt,
+        @waiting: $waiting:tt,
+        @init: $init:tt,
+        @rest: {
+            @after $after:ident $label:ident { $($stmt:tt)* }
+            $($rest:tt)*
+        },
+        @body: $body:tt,
+    ) => {
+        // The statement it follows has been output
+        // Output it next
+        $crate::soupa! {
+            @order,
+            @config: $config,
+            @lints: $lints,
+            @done: $done,
+            @waiting: $waiting,
+            @init: $init,
+            @rest: {
+                $($stmt)*
+                $($rest)*
+            },
+            @body: $body,
+        }
+    };
+    (
+        @missing,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @done: $done:tt,
+        @waiting: { $($waiting:tt)* },
+        @init: $init:tt,
+        @rest: {
+            @after $after:ident $label:ident $stmt:tt
+            $($rest:tt)*
+        },
+        @body: $body:tt,
+    ) => {
+        // The statement it follows hasn't been output yet
+        // Wait for it
+        $crate::soupa! {
+            @order,
+            @config: $config,
+            @lints: $lints,
+            @done: $done,
+            @waiting: {
+                $($waiting)*
+                @after $after $label $stmt
+            },
+            @init: $init,
+            @rest: { $($rest)* },
+            @body: $body,
+        }
+    };
+    (
+        @release $ident:ident,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @done: $done:tt,
+        @waiting: $waiting:tt,
+        @check: {},
+        @released: { $($released:tt)* },
+        @init: $init:tt,
+        @rest: { $($rest:tt)* },
+        @body: $body:tt,
+    ) => {
+        // Everything waiting has been checked
+        // Output the released statements next, in their original order
+        $crate::soupa! {
+            @order,
+            @config: $config,
+            @lints: $lints,
+            @done: $done,
+            @waiting: $waiting,
+            @init: $init,
+            @rest: {
+                $($released)*
+                $($rest)*
+            },
+            @body: $body,
+        }
+    };
+    (
+        @release $ident:ident,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @done: $done:tt,
+        @waiting: $waiting:tt,
+        @check: {
+            @after $after:ident $label:ident $stmt:tt
+            $($check:tt)*
+        },
+        @released: $released:tt,
+        @init: $init:tt,
+        @rest: $rest:tt,
+        @body: $body:tt,
+    ) => {
+        // Check whether the next waiting statement follows the one output
+        $crate::soupa! {
+            @find ($) $after in { $ident },
+            @release $ident,
+            @config: $config,
+            @lints: $lints,
+            @done: $done,
+            @waiting: $waiting,
+            @check: {
+                @after $after $label $stmt
+                $($check)*
+            },
+            @released: $released,
+            @init: $init,
+            @rest: $rest,
+            @body: $body,
+        }
+    };
+    (
+        @found,
+        @release $ident:ident,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @done: $done:tt,
+        @waiting: $waiting:tt,
+        @check: {
+            @after $after:ident $label:ident { $($stmt:tt)* }
+            $($check:tt)*
+        },
+        @released: { $($released:tt)* },
+        @init: $init:tt,
+        @rest: $rest:tt,
+        @body: $body:tt,
+    ) => {
+        // The waiting statement follows the one output, so release it
+        $crate::soupa! {
+            @release $ident,
+            @config: $config,
+            @lints: $lints,
+            @done: $done,
+            @waiting: $waiting,
+            @check: { $($check)* },
+            @released: {
+                $($released)*
+                $($stmt)*
+            },
+            @init: $init,
+            @rest: $rest,
+            @body: $body,
+        }
+    };
+    (
+        @missing,
+        @release $ident:ident,
+        @config: $config:tt,
+        @lints: $lints:tt,
+        @done: $done:tt,
+        @waiting: { $($waiting:tt)* },
+        @check: {
+            @after $after:ident $label:ident $stmt:tt
+            $($check:tt)*
+        },
+        @released: $released:tt,
+        @init: $init:tt,
+        @rest: $rest:tt,
+        @body: $body:tt,
+    ) => {
+        // The waiting statement follows something else, so keep it waiting
+        $crate::soupa! {
+            @release $ident,
+            @config: $config,
+            @lints: $lints,
+            @done: $done,
+            @waiting: {
+                $($waiting)*
+                @after $after $label $stmt
+            },
+            @check: { $($check)* },
+            @released: $released,
+            @init: $init,
+            @rest: $rest,
+            @body: $body,
+        }
+    };
+
+    (
+        @assert { explain $($option:tt)* } { $($kept:tt)* } $done:tt { $($init:tt)* } { $($body:tt)* } $lints:tt
+    ) => {
+        // Output is explained rather than evaluated
+        // Checks are not added, as nothing is evaluated, but options before it are kept
+        $crate::soupa! {
+            @emit { $($kept)* explain } $lints {
+                $($init)*
+                $($body)*
+            }
+        }
+    };
+    (
+        @assert { send $($option:tt)* } { $($kept:tt)* } { $($done:ident)* } { $($init:tt)* } $body:tt $lints:tt
+    ) => {
+        // Check each lifted value can be sent to another thread
+        // Each check is named after the value, so errors name the super block responsible
+        $crate::soupa! {
+            @assert { $($option)* } { $($kept)* send } { $($done)* } {
+                $($init)*
+                $(({
+                    fn $done<T: Send>(_: &T) {}
+                    $done
+                })(&$done);)*
+            } $body $lints
+        }
+    };
+    (
+        @assert { sync $($option:tt)* } { $($kept:tt)* } { $($done:ident)* } { $($init:tt)* } $body:tt $lints:tt
+    ) => {
+        // Check each lifted value can be shared between threads
+        $crate::soupa! {
+            @assert { $($option)* } { $($kept)* sync } { $($done)* } {
+                $($init)*
+                $(({
+                    fn $done<T: Sync>(_: &T) {}
+                    $done
+                })(&$done);)*
+            } $body $lints
+        }
+    };
+    (
+        @assert { static $($option:tt)* } { $($kept:tt)* } { $($done:ident)* } { $($init:tt)* } $body:tt $lints:tt
+    ) => {
+        // Check each lifted value doesn't borrow from the surrounding scope
+        $crate::soupa! {
+            @assert { $($option)* } { $($kept)* static } { $($done)* } {
+                $($init)*
+                $(({
+                    fn $done<T: 'static>(_: &T) {}
+                    $done
+                })(&$done);)*
+            } $body $lints
+        }
+    };
+    (
+        @assert { $next:tt $($option:tt)* } { $($kept:tt)* } $done:tt $init:tt $body:tt $lints:tt
+    ) => {
+        // Option doesn't add any checks
+        $crate::soupa! {
+            @assert { $($option)* } { $($kept)* $next } $done $init $body $lints
+        }
+    };
+    (
+        @assert {} $config:tt $done:tt { $($init:tt)* } { $($body:tt)* } $lints:tt
+    ) => {
+        // All checks have been added
+        // Output the initialization statements followed by the body
+        $crate::soupa! {
+            @emit $config $lints {
+                $($init)*
+                $($body)*
+            }
+        }
+    };
+
+    (
+        @emit { send $($option:tt)* } $lints:tt $output:tt
+    ) => {
+        // Check the output can be sent to another thread
+        $crate::soupa! {
+            @emit { $($option)* } $lints {
+                $crate::__private::output_must_be_send($output)
+            }
+        }
+    };
+    (
+        @emit { sync $($option:tt)* } $lints:tt $output:tt
+    ) => {
+        // Check the output can be shared between threads
+        $crate::soupa! {
+            @emit { $($option)* } $lints {
+                $crate::__private::output_must_be_sync($output)
+            }
+        }
+    };
+    (
+        @emit { static $($option:tt)* } $lints:tt $output:tt
+    ) => {
+        // Check the output doesn't borrow from the surrounding scope
+        $crate::soupa! {
+            @emit { $($option)* } $lints {
+                $crate::__private::output_must_be_static($output)
+            }
+        }
+    };
+    (
+        @emit { boxed(Future $($bound:tt)*) $($option:tt)* } $lints:tt $output:tt
+    ) => {
+        // Pin and box the output as a future trait object
+        $crate::soupa! {
+            @emit { $($option)* } $lints {
+                let __soupa_boxed: $crate::__private::Pin<$crate::__private::Box<dyn $crate::__private::Future $($bound)*>> =
+                    $crate::__private::Box::pin($output);
+                __soupa_boxed
+            }
+        }
+    };
+    (
+        @emit { boxed($($bound:tt)*) $($option:tt)* } $lints:tt $output:tt
+    ) => {
+        // Box the output as a trait object, such as a closure
+        // The type is given by a let binding, so it can be used to infer the closure's signature
+        $crate::soupa! {
+            @emit { $($option)* } $lints {
+                let __soupa_boxed: $crate::__private::Box<dyn $($bound)*> = $crate::__private::Box::new($output);
+                __soupa_boxed
+            }
+        }
+    };
+    (
+        @emit { dedup $($option:tt)* } $lints:tt $output:tt
+    ) => {
+        // Super blocks have already been deduplicated
+        $crate::soupa! {
+            @emit { $($option)* } $lints $output
+        }
+    };
+    (
+        @emit { lazy $($option:tt)* } $lints:tt $output:tt
+    ) => {
+        // Super blocks within branches have already been lifted into them
+        $crate::soupa! {
+            @emit { $($option)* } $lints $output
+        }
+    };
+    (
+        @emit { explain } $lints:tt $output:tt
+    ) => {
+        // Output is explained rather than evaluated
+        // Lints are not emitted, as nothing is evaluated
+        stringify!($output)
+    };
+    (
+        @emit { explain $next:tt $($option:tt)* } $lints:tt $output:tt
+    ) => {
+        // Defer explaining until all other options have been applied
+        $crate::soupa! {
+            @emit { $next $($option)* explain } $lints $output
+        }
+    };
+    (
+        @emit { strict $($option:tt)* } { $($lint:tt)* } $output:tt
+    ) => {
+        // Strict mode enables additional lints
+        $crate::soupa! {
+            @emit { $($option)* } { strict $($lint)* } $output
+        }
+    };
+    (
+        @emit {} { strict $($lint:tt)* } $output:tt
+    ) => {
+        // No output options remain
+        // Emit any lints, which are only checked in strict mode
+        $crate::soupa! {
+            @warn { $($lint)* } {} $output
+        }
+    };
+    (
+        @emit {} $lints:tt $output:tt
+    ) => {
+        // No output options remain
+        $output
+    };
+
+    (
+        @warn {} {} $output:tt
+    ) => {
+        // No lints remain and none produced a warning
+        $output
+    };
+    (
+        @warn {} { $($warning:tt)* } { $($output:tt)* }
+    ) => {
+        // No lints remain
+        // Place the warnings ahead of the output
+        {
+            $($warning)*
+            $($output)*
+        }
+    };
+    (
+        @warn { { closure: no, $($next:tt)* } $($lint:tt)* } { $($warning:tt)* } $output:tt
+    ) => {
+        // A super block isn't within a closure, so lifting it only reorders it
+        $crate::soupa! {
+            @warn { $($lint)* } {
+                $($warning)*
+                $crate::__private::super_block_outside_closure();
+            } $output
+        }
+    };
+    (
+        @warn { { closure: $closure:ident, looping: yes, prev: &, next: $next:tt } $($lint:tt)* } $warnings:tt $output:tt
+    ) => {
+        // A super block within a loop is borrowed rather than moved
+        $crate::soupa! {
+            @warn { $($lint)* } $warnings $output
+        }
+    };
+    (
+        @warn { { closure: $closure:ident, looping: yes, prev: mut, next: $next:tt } $($lint:tt)* } $warnings:tt $output:tt
+    ) => {
+        // A super block within a loop is borrowed rather than moved
+        $crate::soupa! {
+            @warn { $($lint)* } $warnings $output
+        }
+    };
+    (
+        @warn { { closure: $closure:ident, looping: yes, prev: $prev:tt, next: . } $($lint:tt)* } $warnings:tt $output:tt
+    ) => {
+        // A super block within a loop is used by a method call rather than moved
+        $crate::soupa! {
+            @warn { $($lint)* } $warnings $output
+        }
+    };
+    (
+        @warn { { closure: $closure:ident, looping: yes, prev: $prev:tt, next: [ $($index:tt)* ] } $($lint:tt)* } $warnings:tt $output:tt
+    ) => {
+        // A super block within a loop is indexed rather than moved
+        $crate::soupa! {
+            @warn { $($lint)* } $warnings $output
+        }
+    };
+    (
+        @warn { { closure: $closure:ident, looping: yes, $($next:tt)* } $($lint:tt)* } { $($warning:tt)* } $output:tt
+    ) => {
+        // A super block within a loop is evaluated once, but may be moved on each iteration
+        $crate::soupa! {
+            @warn { $($lint)* } {
+                $($warning)*
+                $crate::__private::super_block_in_loop();
+            } $output
+        }
+    };
+    (
+        @warn { $next:tt $($lint:tt)* } $warnings:tt $output:tt
+    ) => {
+        // The next lint doesn't produce a warning
+        $crate::soupa! {
+            @warn { $($lint)* } $warnings $output
+        }
+    };
+
+    (
+        @attrs { $($cfg:tt)* } { $($all:tt)* } { #[cfg $($args:tt)*] $($attr:tt)* }
+        $($state:tt)*
+    ) => {
+        // A cfg attribute is kept in place as well as copied onto the declaration,
+        // if the super block begins a statement
+        $crate::soupa! {
+            @attrs { $($cfg)* #[cfg $($args)*] } { $($all)* #[cfg $($args)*] } { $($attr)* }
+            $($state)*
+        }
+    };
+    (
+        @attrs $cfg:tt { $($all:tt)* } { #[$($args:tt)*] $($attr:tt)* }
+        $($state:tt)*
+    ) => {
+        // Other attributes are only copied onto the declaration
+        // In place, they would be attributes on an expression, which are unstable
+        $crate::soupa! {
+            @attrs $cfg { $($all)* #[$($args)*] } { $($attr)* }
+            $($state)*
+        }
+    };
+    (
+        @attrs $cfg:tt $all:tt {}
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                $($top:tt)*
+            }
+            $($stack:tt)*
+        },
+        $($state:tt)*
+    ) => {
+        // The attributes are sorted, so check where the super block is
+        $crate::soupa! {
+            @attrs_kind $top_kind $prev $cfg $all
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: ($top_kind $($top_orig)?),
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: $prev },
+                    $($top)*
+                }
+                $($stack)*
+            },
+            $($state)*
+        }
+    };
+    (@attrs_kind {} $prev:tt $cfg:tt $all:tt $($state:tt)*) => {
+        // Within a block, check the token before it
+        $crate::soupa! { @attrs_prev $prev $cfg $all $($state)* }
+    };
+    (@attrs_kind None $prev:tt $cfg:tt $all:tt $($state:tt)*) => {
+        // The invocation itself is output within a block, after the init statements
+        $crate::soupa! { @attrs_prev $prev $cfg $all $($state)* }
+    };
+    (@attrs_kind $kind:tt $prev:tt $cfg:tt $all:tt $($state:tt)*) => {
+        // Within parentheses or brackets, the super block is within an expression
+        $crate::soupa! { @attrs_place {} $all $($state)* }
+    };
+    (@attrs_prev @ $cfg:tt $all:tt $($state:tt)*) => {
+        // The super block begins a statement, so its cfg attributes can be kept in place
+        $crate::soupa! { @attrs_place $cfg $all $($state)* }
+    };
+    (@attrs_prev ; $cfg:tt $all:tt $($state:tt)*) => {
+        $crate::soupa! { @attrs_place $cfg $all $($state)* }
+    };
+    (@attrs_prev {} $cfg:tt $all:tt $($state:tt)*) => {
+        $crate::soupa! { @attrs_place $cfg $all $($state)* }
+    };
+    (@attrs_prev $prev:tt $cfg:tt $all:tt $($state:tt)*) => {
+        // Otherwise it is within an expression, where attributes are unstable
+        $crate::soupa! { @attrs_place {} $all $($state)* }
+    };
+    (
+        @attrs_place $cfg:tt $all:tt
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: $top_scope:tt,
+                @body: $top_body:tt,
+                @rest: { $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Place the sorted attributes before the super block they apply to
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: $top_scope,
+                    @body: $top_body,
+                    @rest: {
+                        @attrs $cfg $all
+                        $($top_rest)*
+                    },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+
+    (
+        @angle < yes $copy:tt,
+        $($state:tt)*
+    ) => {
+        // Within a closure, `closure` counts the angle brackets it is within, up to four
+        $crate::soupa! {
+            @angle_set angle1 $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle < angle1 $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set angle2 $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle < angle2 $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set angle3 $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle < angle3 $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set angle4 $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle << yes $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set angle2 $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle << angle1 $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set angle3 $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle << angle2 $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set angle4 $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle << angle3 $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set angle4 $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle > angle1 $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set yes $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle > angle2 $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set angle1 $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle > angle3 $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set angle2 $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle > angle4 $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set angle3 $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle >> angle1 $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set yes $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle >> angle2 $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set yes $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle >> angle3 $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set angle1 $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle >> angle4 $copy:tt,
+        $($state:tt)*
+    ) => {
+        $crate::soupa! {
+            @angle_set angle2 $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle $token:tt $closure:ident $copy:tt,
+        $($state:tt)*
+    ) => {
+        // Outside of a closure, or beyond the depth counted, angle brackets are left as is
+        $crate::soupa! {
+            @angle_set $closure $copy,
+            $($state)*
+        }
+    };
+    (
+        @angle_set $closure:ident $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $prev_closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Place the angle bracket in the top scope output, now within as many as counted
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+
+    (
+        @lint { $($entry:tt)* } { $next:tt $($rest:tt)* }
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: $stack:tt,
+        @init: $init:tt,
+        @lints: { $($lint:tt)* },
+        @body: $body:tt,
+    ) => {
+        // Record a lint for a super block, along with the token following it
+        // Only that token is kept, rather than the rest of its scope
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: $stack,
+            @init: $init,
+            @lints: {
+                $($lint)*
+                { $($entry)* next: $next }
+            },
+            @body: $body,
+        }
+    };
+    (
+        @lint { $($entry:tt)* } {}
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: $stack:tt,
+        @init: $init:tt,
+        @lints: { $($lint:tt)* },
+        @body: $body:tt,
+    ) => {
+        // Record a lint for a super block at the end of its scope
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: $stack,
+            @init: $init,
+            @lints: {
+                $($lint)*
+                { $($entry)* next: {} }
+            },
+            @body: $body,
+        }
+    };
+
+    (
+        @peel | $copy:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: no, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A `|` token begins a closure or async block
+        // Anything following it in the current scope is within the closure
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: yes, outer: $outer, looping: $looping, pending: $pending, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel || $copy:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: no, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A `||` token begins a closure or async block
+        // Anything following it in the current scope is within the closure
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: yes, outer: $outer, looping: $looping, pending: $pending, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel async $copy:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: no, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // An `async` token begins a closure or async block
+        // Anything following it in the current scope is within the closure
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: yes, outer: $outer, looping: $looping, pending: $pending, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel ; $copy:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: no, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A `;` token ends any closure in the current scope, even within angle brackets
+        // Anything following it is only within a closure if the scope is
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: no, outer: no, looping: $looping, pending: $pending, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel , $copy:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: yes, outer: no, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A `,` token ends any closure in the current scope, unless it is within angle brackets
+        // Those would be the generic arguments of a type or turbofish within the closure
+        // Anything following it is only within a closure if the scope is
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: no, outer: no, looping: $looping, pending: $pending, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel < $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: no, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: $top_body:tt,
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A `<` token may open generic arguments within a closure
+        // Count how deeply, so a `,` within them doesn't end the closure
+        $crate::soupa! {
+            @angle < $closure $copy,
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: no, looping: $looping, pending: $pending, prev: $prev },
+                    @body: $top_body,
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel << $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: no, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: $top_body:tt,
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A `<<` token may open two levels of generic arguments within a closure
+        // Count how deeply, so a `,` within them doesn't end the closure
+        $crate::soupa! {
+            @angle << $closure $copy,
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: no, looping: $looping, pending: $pending, prev: $prev },
+                    @body: $top_body,
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel > $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: no, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: $top_body:tt,
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A `>` token may close generic arguments within a closure
+        // Count how deeply, so a `,` within them doesn't end the closure
+        $crate::soupa! {
+            @angle > $closure $copy,
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: no, looping: $looping, pending: $pending, prev: $prev },
+                    @body: $top_body,
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel >> $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: no, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: $top_body:tt,
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A `>>` token may close two levels of generic arguments within a closure
+        // Count how deeply, so a `,` within them doesn't end the closure
+        $crate::soupa! {
+            @angle >> $closure $copy,
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: no, looping: $looping, pending: $pending, prev: $prev },
+                    @body: $top_body,
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel for $copy:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: no, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A `for` token begins a loop
+        // The next {} tree in the current scope is its body
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: yes, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel while $copy:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: no, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A `while` token begins a loop
+        // The next {} tree in the current scope is its body
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: yes, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel loop $copy:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: no, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A `loop` token begins a loop
+        // The next {} tree in the current scope is its body
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: yes, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel if $copy:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: no, outer: $outer:ident, looping: $looping:ident, pending: no, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // An `if` token outside of a closure begins a branch
+        // The next {} tree in its condition, outside of any pattern, is its block
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: no, outer: $outer, looping: $looping, pending: condition, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel let $copy:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: condition, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A `let` token within a condition begins a pattern
+        // Any {} tree within it is part of the pattern, rather than the block
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: pattern, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel = $copy:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: pattern, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A `=` token ends the pattern within a condition
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: condition, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel else $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: no, outer: $outer:ident, looping: $looping:ident, pending: no, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: {
+                    $next:tt
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // An `else` token outside of a closure begins a branch if a {} tree follows it
+        // A copy of the tree is kept, so it can be peeled as usual if it isn't one
+        $crate::soupa! {
+            @branch $next $next,
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: no, outer: $outer, looping: $looping, pending: no, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: { $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel => $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: no, outer: $outer:ident, looping: $looping:ident, pending: no, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: {
+                    $next:tt
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A `=>` token outside of a closure begins a match arm, which is a branch if a {} tree follows it
+        // A copy of the tree is kept, so it can be peeled as usual if it isn't one
+        $crate::soupa! {
+            @branch $next $next,
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: no, outer: $outer, looping: $looping, pending: no, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: { $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: $top_scope:tt,
+                @body: $top_body:tt,
+                @rest: {
+                    super span ( $($span:tt)* )
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Process a span marker with a given span
+        // Find the closure or async block it applies to
+        $crate::soupa! {
+            @span { $($span)* } {} {} { $($top_rest)* },
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: $top_scope,
+                    @body: $top_body,
+                    @rest: {},
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: $top_scope:tt,
+                @body: $top_body:tt,
+                @rest: {
+                    super span
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Process a span marker
+        // Find the closure or async block it applies to
+        $crate::soupa! {
+            @span { $crate::__private::current_span() } {} {} { $($top_rest)* },
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: $top_scope,
+                    @body: $top_body,
+                    @rest: {},
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @config: { dedup $($option:tt)* },
+        @temps { $next_ident:ident $($temp:ident)* },
+        @stack: {
+            {
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: {
+                    super { $($next:tt)* }
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Process a super block into an init statement which may be deduplicated
+        // Place an identifier of the declaration into the top of the stack
+        $crate::soupa! {
+            @lint { closure: $closure, looping: $looping, prev: $prev, } { $($top_rest)* }
+            @config: { dedup $($option)* },
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ($top_kind),
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                    @body: {
+                        $($top_body)*
+                        $next_ident
+                    },
+                    @rest: { $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: {
+                $($init)*
+                @dedup $next_ident { $($next)* }
+            },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps { $next_ident:ident $($temp:ident)* },
+        @stack: {
+            {
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: {
+                    super { $($next:tt)* }
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Process a super block into an init statement
+        // Place an identifier of the declaration into the top of the stack
+        $crate::soupa! {
+            @lint { closure: $closure, looping: $looping, prev: $prev, } { $($top_rest)* }
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ($top_kind),
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                    @body: {
+                        $($top_body)*
+                        $next_ident
+                    },
+                    @rest: { $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: {
+                $($init)*
+                let $next_ident = { $($next)* };
+            },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: $top_scope:tt,
+                @body: $top_body:tt,
+                @rest: {
+                    $(#[$($attr:tt)*])+
+                    super $(( $($label:tt)* ))? { $($next:tt)* }
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Sort the attributes of a super block, to find those kept in place
+        $crate::soupa! {
+            @attrs {} {} { $(#[$($attr)*])+ }
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: $top_scope,
+                    @body: $top_body,
+                    @rest: {
+                        super $(( $($label)* ))? { $($next)* }
+                        $($top_rest)*
+                    },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps { $next_ident:ident $($temp:ident)* },
+        @stack: {
+            {
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: {
+                    @attrs { $(#[$($cfg:tt)*])* } { $(#[$($attr:tt)*])+ }
+                    super { $($next:tt)* }
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Process an attributed super block into an init statement
+        // The attributes are copied onto the declaration, and only cfg attributes of a statement are kept in place
+        $crate::soupa! {
+            @lint { closure: $closure, looping: $looping, prev: $prev, } { $($top_rest)* }
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ($top_kind),
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                    @body: {
+                        $($top_body)*
+                        $(#[$($cfg)*])*
+                        $next_ident
+                    },
+                    @rest: { $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: {
+                $($init)*
+                $(#[$($attr)*])+
+                let $next_ident = { $($next)* };
+            },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: {
+                    $(@attrs { $(#[$($cfg:tt)*])* } { $(#[$($attr:tt)*])* })?
+                    super ( $label:tt ) { $($next:tt)* }
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Process a labelled super block into an init statement
+        // The label is used as the declared identifier instead of a temp
+        // It is matched as a tree, so where it's restored keeps the spans it was written with
+        $crate::soupa! {
+            @lint { closure: $closure, looping: $looping, prev: $prev, } { $($top_rest)* }
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ($top_kind),
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                    @body: {
+                        $($top_body)*
+                        $($(#[$($cfg)*])*)?
+                        $label
+                    },
+                    @rest: { $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: {
+                $($init)*
+                $($(#[$($attr)*])*)?
+                let $label = { $($next)* };
+            },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: {
+                    $(@attrs { $(#[$($cfg:tt)*])* } { $(#[$($attr:tt)*])* })?
+                    super ( $label:tt , after = $after:ident ) { $($next:tt)* }
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Process an ordered super block into an init statement
+        // The statement is marked to be output after the one it follows
+        $crate::soupa! {
+            @lint { closure: $closure, looping: $looping, prev: $prev, } { $($top_rest)* }
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ($top_kind),
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                    @body: {
+                        $($top_body)*
+                        $($(#[$($cfg)*])*)?
+                        $label
+                    },
+                    @rest: { $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: {
+                $($init)*
+                @after $after $label {
+                    $($(#[$($attr)*])*)?
+                    let $label = { $($next)* };
+                }
+            },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps { $next_ident:ident $($temp:ident)* },
+        @stack: {
+            {
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: {
+                    super share ( $name:ident ) { $($next:tt)* }
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Process a shared super block into an init statement, and a use of it
+        // Each use is given its own temp, which is cloned from the shared value
+        $crate::soupa! {
+            @lint { closure: $closure, looping: $looping, prev: $prev, } { $($top_rest)* }
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ($top_kind),
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                    @body: {
+                        $($top_body)*
+                        $next_ident
+                    },
+                    @rest: { $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: {
+                $($init)*
+                let $name = { $($next)* };
+                @share $name $next_ident
+            },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps { $next_ident:ident $($temp:ident)* },
+        @stack: {
+            {
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: {
+                    super share ( $name:ident )
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Process a further use of a shared super block
+        // It is given its own temp, which is cloned from the shared value
+        $crate::soupa! {
+            @lint { closure: $closure, looping: $looping, prev: $prev, } { $($top_rest)* }
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ($top_kind),
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                    @body: {
+                        $($top_body)*
+                        $next_ident
+                    },
+                    @rest: { $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: {
+                $($init)*
+                @share $name $next_ident
+            },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:ident },
+                @body: { $($top_body:tt)* },
+                @rest: { :: $after:tt $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // A :: after an identifier is part of a path, rather than the start of a lift marker
+        // Place it in the top scope output, and peel off the token after it in the same step
+        $crate::soupa! {
+            @peel $after $after,
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: :: },
+                    @body: {
+                        $($top_body)*
+                        ::
+                    },
+                    @rest: { $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: $top_scope:tt,
+                @body: { $($top_body:tt)* },
+                @rest: {
+                    $(#[$($attr:tt)*])*
+                    $(::)? $(soupa ::)? lift ! ( $($next:tt)* )
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Rewrite a () lift! marker into the equivalent super block
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: $top_scope,
+                    @body: { $($top_body)* },
+                    @rest: {
+                        $(#[$($attr)*])*
+                        super { $($next)* }
+                        $($top_rest)*
+                    },
+                }
+                $($stack)*
+            },
+            @init: { $($init)* },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: $top_scope:tt,
+                @body: { $($top_body:tt)* },
+                @rest: {
+                    $(#[$($attr:tt)*])*
+                    $(::)? $(soupa ::)? lift ! [ $($next:tt)* ]
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Rewrite a [] lift! marker into the equivalent super block
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: $top_scope,
+                    @body: { $($top_body)* },
+                    @rest: {
+                        $(#[$($attr)*])*
+                        super { $($next)* }
+                        $($top_rest)*
+                    },
+                }
+                $($stack)*
+            },
+            @init: { $($init)* },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: $top_scope:tt,
+                @body: { $($top_body:tt)* },
+                @rest: {
+                    $(#[$($attr:tt)*])*
+                    $(::)? $(soupa ::)? lift ! { $($next:tt)* }
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Rewrite a {} lift! marker into the equivalent super block
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: $top_scope,
+                    @body: { $($top_body)* },
+                    @rest: {
+                        $(#[$($attr)*])*
+                        super { $($next)* }
+                        $($top_rest)*
+                    },
+                }
+                $($stack)*
+            },
+            @init: { $($init)* },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: $top_scope:tt,
+                @body: { $($top_body:tt)* },
+                @rest: {
+                    super with $adapter:path { $($next:tt)* }
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Rewrite a super block with a capture adapter into a super block which captures with it
+        // The captured value is restored by reference where the super block was, by a path output as is
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: $top_scope,
+                    @body: {
+                        $($top_body)*
+                        $crate::__private::restore
+                    },
+                    @rest: {
+                        (&super {
+                            $crate::__private::capture(&$adapter, { $($next)* })
+                        })
+                        $($top_rest)*
+                    },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: $top_scope:tt,
+                @body: { $($top_body:tt)* },
+                @rest: {
+                    super ( $($label:tt)* ) with $adapter:path { $($next:tt)* }
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Rewrite a labelled super block with a capture adapter into a super block which captures with it
+        // The captured value is restored by reference where the super block was, by a path output as is
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: $top_scope,
+                    @body: {
+                        $($top_body)*
+                        $crate::__private::restore
+                    },
+                    @rest: {
+                        (&super($($label)*) {
+                            $crate::__private::capture(&$adapter, { $($next)* })
+                        })
+                        $($top_rest)*
+                    },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @branch { $($next:tt)* } $orig:tt,
+        @config: { $($option:tt)* },
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: { closure: no, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Peel off a {} tree which is the block of a branch outside of a closure
+        // With the lazy option, it is expanded as a separate invocation, so its super blocks are lifted into it
+        $crate::soupa! {
+            @lazy_find { $($option)* } {
+                @config: { $($option)* },
+                @temps $temps,
+                @stack: {
+                    {
+                        @paren: ($top_kind),
+                        @scope: { closure: no, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                        @body: {
+                            $($top_body)*
+                            { $crate::soupa!(@branch_start { $($option)* } {} { $($next)* }) }
+                        },
+                        @rest: $top_rest,
+                    }
+                    $($stack)*
+                },
+                @init: $init,
+                @lints: $lints,
+                @body: $body,
+            } {
+                @peel { $($next)* } $orig,
+                @config: { $($option)* },
+                @temps $temps,
+                @stack: {
+                    {
+                        @paren: ($top_kind $($top_orig)?),
+                        @scope: { closure: no, outer: $outer, looping: $looping, pending: $pending, prev: $prev },
+                        @body: { $($top_body)* },
+                        @rest: $top_rest,
+                    }
+                    $($stack)*
+                },
+                @init: $init,
+                @lints: $lints,
+                @body: $body,
+            }
+        }
+    };
+    (
+        @branch $next:tt $copy:tt,
+        $($state:tt)*
+    ) => {
+        // Not the block of a branch outside of a closure, so peel it off as usual
+        $crate::soupa! {
+            @peel $next $copy,
+            $($state)*
+        }
+    };
+
+    (
+        @lazy_find { lazy $($option:tt)* } { $($then:tt)* } $else:tt
+    ) => {
+        // The lazy option is given
+        $crate::soupa! {
+            $($then)*
+        }
+    };
+    (
+        @lazy_find { $next:tt $($option:tt)* } $then:tt $else:tt
+    ) => {
+        // Options can only be compared by matching them against each other
+        $crate::soupa! {
+            @lazy_find { $($option)* } $then $else
+        }
+    };
+    (
+        @lazy_find {} $then:tt { $($else:tt)* }
+    ) => {
+        // The lazy option isn't given
+        $crate::soupa! {
+            $($else)*
+        }
+    };
+
+    (
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: $top_scope:tt,
+                @body: $top_body:tt,
+                @rest: {
+                    $next:tt
+                    $($top_rest:tt)*
+                },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Peel off any other token, keeping a copy of it
+        // A tree left unchanged is output as the copy, so it keeps the spans it was written with
+        $crate::soupa! {
+            @peel $next $next,
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: $top_scope,
+                    @body: $top_body,
+                    @rest: { $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+
+    (
+        @peel { $($next:tt)* } $orig:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: condition, prev: $prev:tt },
+                @body: $top_body:tt,
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Peel off a {} tree which is the block of an `if` branch
+        $crate::soupa! {
+            @branch { $($next)* } $orig,
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: no, prev: $prev },
+                    @body: $top_body,
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel { $first:tt $($next:tt)* } $orig:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: yes, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Peel off a {} tree which is the body of a loop
+        // Its scope is within a loop, and a closure if the current scope is
+        // Its first token is peeled off in the same step
+        $crate::soupa! {
+            @peel $first $first,
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ({} $orig),
+                    @scope: { closure: $closure, outer: $closure, looping: yes, pending: no, prev: @ },
+                    @body: {},
+                    @rest: { $($next)* },
+                }
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: no, prev: {} },
+                    @body: { $($top_body)* },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: { $($init)* },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @peel { $($next:tt)* } $orig:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: yes, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Peel off a {} tree which is the body of a loop
+        // Its scope is within a loop, and a closure if the current scope is
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ({} $orig),
+                    @scope: { closure: $closure, outer: $closure, looping: yes, pending: no, prev: {} },
+                    @body: {},
+                    @rest: { $($next)* },
+                }
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: no, prev: {} },
+                    @body: { $($top_body)* },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: { $($init)* },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @peel { $first:tt $($next:tt)* } $orig:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Peel off a {} tree and place it onto the top of the stack
+        // Its scope is within a closure if the current scope is
+        // Its first token is peeled off in the same step
+        $crate::soupa! {
+            @peel $first $first,
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ({} $orig),
+                    @scope: { closure: $closure, outer: $closure, looping: $looping, pending: no, prev: @ },
+                    @body: {},
+                    @rest: { $($next)* },
+                }
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                    @body: { $($top_body)* },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: { $($init)* },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @peel { $($next:tt)* } $orig:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Peel off a {} tree and place it onto the top of the stack
+        // Its scope is within a closure if the current scope is
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ({} $orig),
+                    @scope: { closure: $closure, outer: $closure, looping: $looping, pending: no, prev: {} },
+                    @body: {},
+                    @rest: { $($next)* },
+                }
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                    @body: { $($top_body)* },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: { $($init)* },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @peel ( $first:tt $($next:tt)* ) $orig:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Peel off a () tree and place it onto the top of the stack
+        // Its scope is within a closure if the current scope is
+        // Its first token is peeled off in the same step
+        $crate::soupa! {
+            @peel $first $first,
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: (() $orig),
+                    @scope: { closure: $closure, outer: $closure, looping: $looping, pending: no, prev: @ },
+                    @body: {},
+                    @rest: { $($next)* },
+                }
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: () },
+                    @body: { $($top_body)* },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: { $($init)* },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @peel ( $($next:tt)* ) $orig:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Peel off a () tree and place it onto the top of the stack
+        // Its scope is within a closure if the current scope is
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: (() $orig),
+                    @scope: { closure: $closure, outer: $closure, looping: $looping, pending: no, prev: {} },
+                    @body: {},
+                    @rest: { $($next)* },
+                }
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: () },
+                    @body: { $($top_body)* },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: { $($init)* },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @peel [ $first:tt $($next:tt)* ] $orig:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Peel off a [] tree and place it onto the top of the stack
+        // Its scope is within a closure if the current scope is
+        // Its first token is peeled off in the same step
+        $crate::soupa! {
+            @peel $first $first,
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ([] $orig),
+                    @scope: { closure: $closure, outer: $closure, looping: $looping, pending: no, prev: @ },
+                    @body: {},
+                    @rest: { $($next)* },
+                }
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: [] },
+                    @body: { $($top_body)* },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: { $($init)* },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @peel [ $($next:tt)* ] $orig:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Peel off a [] tree and place it onto the top of the stack
+        // Its scope is within a closure if the current scope is
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: ([] $orig),
+                    @scope: { closure: $closure, outer: $closure, looping: $looping, pending: no, prev: {} },
+                    @body: {},
+                    @rest: { $($next)* },
+                }
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: [] },
+                    @body: { $($top_body)* },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: { $($init)* },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+    (
+        @peel super $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: @ },
+                @body: $top_body:tt,
+                @rest: { $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // The first token of a scope may begin a super block or lift marker
+        // Put it back, so the rules matching those can see it
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                    @body: $top_body,
+                    @rest: { $copy $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel # $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: @ },
+                @body: $top_body:tt,
+                @rest: { $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                    @body: $top_body,
+                    @rest: { $copy $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel :: $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: @ },
+                @body: $top_body:tt,
+                @rest: { $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                    @body: $top_body,
+                    @rest: { $copy $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel soupa $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: @ },
+                @body: $top_body:tt,
+                @rest: { $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                    @body: $top_body,
+                    @rest: { $copy $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel lift $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: @ },
+                @body: $top_body:tt,
+                @rest: { $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: {} },
+                    @body: $top_body,
+                    @rest: { $copy $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel $next:tt $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: { super $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Peel off a misc token and place in the top scope output
+        // The token after it may begin a super block or lift marker, so it is left for the rules matching those
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: $next },
+                    @body: {
+                        $($top_body)*
+                        $next
+                    },
+                    @rest: { super $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel $next:tt $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: { # $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: $next },
+                    @body: {
+                        $($top_body)*
+                        $next
+                    },
+                    @rest: { # $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel :: $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: { $after:tt $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Peel off a misc token and place in the top scope output
+        // A :: is followed by the rest of a path, so the token after it is peeled off in the same step
+        $crate::soupa! {
+            @peel $after $after,
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: { $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel $next:tt $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: { soupa :: lift $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: $next },
+                    @body: {
+                        $($top_body)*
+                        $next
+                    },
+                    @rest: { soupa :: lift $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel $next:tt $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: { lift ! $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: $next },
+                    @body: {
+                        $($top_body)*
+                        $next
+                    },
+                    @rest: { lift ! $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel $next:ident $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: { $after:tt $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Peel off a misc token and place in the top scope output
+        // An identifier is only followed by :: as part of a path, so the token after it is peeled off in the same step
+        // The copy is placed in the output, as a keyword matched as an identifier loses its edition
+        $crate::soupa! {
+            @peel $after $after,
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: $copy },
+                    @body: {
+                        $($top_body)*
+                        $copy
+                    },
+                    @rest: { $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel $next:tt $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: { :: soupa $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: $next },
+                    @body: {
+                        $($top_body)*
+                        $next
+                    },
+                    @rest: { :: soupa $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel $next:tt $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: { :: lift $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        $crate::soupa! {
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: $next },
+                    @body: {
+                        $($top_body)*
+                        $next
+                    },
+                    @rest: { :: lift $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel $next:tt $copy:tt,
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: { $after:tt $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Peel off a misc token and place in the top scope output
+        // No other rule matches the token after it, so it is peeled off in the same step
+        $crate::soupa! {
+            @peel $after $after,
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: $next },
+                    @body: {
+                        $($top_body)*
+                        $next
+                    },
+                    @rest: { $($top_rest)* },
+                }
+                $($stack)*
+            },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
+        }
+    };
+    (
+        @peel $next:tt $copy:tt,
+        @config: $config:tt,
+        @temps { $($temp:ident)* },
+        @stack: {
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: $top_rest:tt,
+            }
+            $($stack:tt)*
+        },
+        @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
+        @body: { $($body:tt)* },
+    ) => {
+        // Peel off a misc token and place in the top scope output
+        // It is the last token in the current scope
+        $crate::soupa! {
+            @config: $config,
+            @temps { $($temp)* },
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: $next },
+                    @body: {
+                        $($top_body)*
+                        $next
+                    },
+                    @rest: $top_rest,
+                }
+                $($stack)*
+            },
+            @init: { $($init)* },
+            @lints: { $($lint)* },
+            @body: { $($body)* },
+        }
+    };
+
+    (
+        @config: $config:tt,
+        @temps $temps:tt,
+        @stack: {
+            {
+                @paren: ($next_paren:tt $orig:tt),
+                @scope: $next_scope:tt,
+                @body: $next:tt,
+                @rest: { },
+            }
+            {
+                @paren: $top_paren:tt,
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
+                @body: { $($top_body:tt)* },
+                @rest: { $after:tt $($top_rest:tt)* },
+            }
+            $($stack:tt)*
+        },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
+    ) => {
+        // Top item on the stack is done, and nothing within it was changed
+        // Combine it with the next item down as it was written, so its spans are kept
+        // The token after it is peeled off in the same step
+        $crate::soupa! {
+            @peel $after $after,
+            @config: $config,
+            @temps $temps,
+            @stack: {
+                {
+                    @paren: $top_paren,
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: @ },
+                    @body: {
+                        $($top_body)*
+                        $orig
                     },
                     @rest: { $($top_rest)* },
                 }
                 $($stack)*
             },
-            @init: {
-                $($init)*
-                let $next_ident = { $($next)* };
-            },
-            @body: { $($body)* },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
         }
     };
     (
-        @temps { $($temp:ident)* },
+        @config: $config:tt,
+        @temps $temps:tt,
         @stack: {
+            {
+                @paren: ($next_paren:tt $orig:tt),
+                @scope: $next_scope:tt,
+                @body: $next:tt,
+                @rest: { },
+            }
             {
                 @paren: $top_paren:tt,
+                @scope: $top_scope:tt,
                 @body: { $($top_body:tt)* },
-                @rest: {
-                    { $($next:tt)* }
-                    $($top_rest:tt)*
-                },
+                @rest: $top_rest:tt,
             }
             $($stack:tt)*
         },
-        @init: { $($init:tt)* },
-        @body: { $($body:tt)* },
+        @init: $init:tt,
+        @lints: $lints:tt,
+        @body: $body:tt,
     ) => {
-        // Peel off a {} tree and place it onto the top of the stack
+        // Top item on the stack is done, and nothing within it was changed
+        // Combine it with the next item down as it was written, so its spans are kept
         $crate::soupa! {
-            @temps { $($temp)* },
+            @config: $config,
+            @temps $temps,
             @stack: {
-                {
-                    @paren: {},
-                    @body: {},
-                    @rest: { $($next)* },
-                }
                 {
                     @paren: $top_paren,
-                    @body: { $($top_body)* },
-                    @rest: { $($top_rest)* },
+                    @scope: $top_scope,
+                    @body: {
+                        $($top_body)*
+                        $orig
+                    },
+                    @rest: $top_rest,
                 }
                 $($stack)*
             },
-            @init: { $($init)* },
-            @body: { $($body)* },
+            @init: $init,
+            @lints: $lints,
+            @body: $body,
         }
     };
     (
+        @config: $config:tt,
         @temps { $($temp:ident)* },
         @stack: {
             {
-                @paren: $top_paren:tt,
+                @paren: ({}),
+                @scope: $next_scope:tt,
+                @body: { $($next:tt)* },
+                @rest: { },
+            }
+            {
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
                 @body: { $($top_body:tt)* },
-                @rest: {
-                    ( $($next:tt)* )
-                    $($top_rest:tt)*
-                },
+                @rest: { $after:tt $($top_rest:tt)* },
             }
             $($stack:tt)*
         },
         @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
         @body: { $($body:tt)* },
     ) => {
-        // Peel off a () tree and place it onto the top of the stack
+        // Top item on the stack is done, and something within it was changed
+        // Combine it with the next item down, which is changed as well
+        // The token after it is peeled off in the same step
         $crate::soupa! {
+            @peel $after $after,
+            @config: $config,
             @temps { $($temp)* },
             @stack: {
                 {
-                    @paren: (),
-                    @body: {},
-                    @rest: { $($next)* },
-                }
-                {
-                    @paren: $top_paren,
-                    @body: { $($top_body)* },
+                    @paren: ($top_kind),
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: @ },
+                    @body: {
+                        $($top_body)*
+                        { $($next)* }
+                    },
                     @rest: { $($top_rest)* },
                 }
                 $($stack)*
             },
             @init: { $($init)* },
+            @lints: { $($lint)* },
             @body: { $($body)* },
         }
     };
     (
+        @config: $config:tt,
         @temps { $($temp:ident)* },
         @stack: {
             {
-                @paren: $top_paren:tt,
+                @paren: ({}),
+                @scope: $next_scope:tt,
+                @body: { $($next:tt)* },
+                @rest: { },
+            }
+            {
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: $top_scope:tt,
                 @body: { $($top_body:tt)* },
-                @rest: {
-                    [ $($next:tt)* ]
-                    $($top_rest:tt)*
-                },
+                @rest: { $($top_rest:tt)* },
             }
             $($stack:tt)*
         },
         @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
         @body: { $($body:tt)* },
     ) => {
-        // Peel off a [] tree and place it onto the top of the stack
+        // Top item on the stack is done, and something within it was changed
+        // Combine it with the next item down, which is changed as well
         $crate::soupa! {
+            @config: $config,
             @temps { $($temp)* },
             @stack: {
                 {
-                    @paren: [],
-                    @body: {},
-                    @rest: { $($next)* },
-                }
-                {
-                    @paren: $top_paren,
-                    @body: { $($top_body)* },
+                    @paren: ($top_kind),
+                    @scope: $top_scope,
+                    @body: {
+                        $($top_body)*
+                        { $($next)* }
+                    },
                     @rest: { $($top_rest)* },
                 }
                 $($stack)*
             },
             @init: { $($init)* },
+            @lints: { $($lint)* },
             @body: { $($body)* },
         }
     };
     (
+        @config: $config:tt,
         @temps { $($temp:ident)* },
         @stack: {
             {
-                @paren: $top_paren:tt,
+                @paren: (()),
+                @scope: $next_scope:tt,
+                @body: { $($next:tt)* },
+                @rest: { },
+            }
+            {
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
                 @body: { $($top_body:tt)* },
-                @rest: {
-                    $next:tt
-                    $($top_rest:tt)*
-                },
+                @rest: { $after:tt $($top_rest:tt)* },
             }
             $($stack:tt)*
         },
         @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
         @body: { $($body:tt)* },
     ) => {
-        // Peel off a misc token and place in the top scope output
+        // Top item on the stack is done, and something within it was changed
+        // Combine it with the next item down, which is changed as well
+        // The token after it is peeled off in the same step
         $crate::soupa! {
+            @peel $after $after,
+            @config: $config,
             @temps { $($temp)* },
             @stack: {
                 {
-                    @paren: $top_paren,
+                    @paren: ($top_kind),
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: @ },
                     @body: {
                         $($top_body)*
-                        $next
+                        ( $($next)* )
                     },
                     @rest: { $($top_rest)* },
                 }
                 $($stack)*
             },
             @init: { $($init)* },
+            @lints: { $($lint)* },
             @body: { $($body)* },
         }
     };
-
     (
+        @config: $config:tt,
         @temps { $($temp:ident)* },
         @stack: {
             {
-                @paren: {},
+                @paren: (()),
+                @scope: $next_scope:tt,
                 @body: { $($next:tt)* },
                 @rest: { },
             }
             {
-                @paren: $top_paren:tt,
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: $top_scope:tt,
                 @body: { $($top_body:tt)* },
                 @rest: { $($top_rest:tt)* },
             }
             $($stack:tt)*
         },
         @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
         @body: { $($body:tt)* },
     ) => {
-        // Top item on the stack is done
-        // Combine it with the next item down
+        // Top item on the stack is done, and something within it was changed
+        // Combine it with the next item down, which is changed as well
         $crate::soupa! {
+            @config: $config,
             @temps { $($temp)* },
             @stack: {
                 {
-                    @paren: $top_paren,
+                    @paren: ($top_kind),
+                    @scope: $top_scope,
                     @body: {
                         $($top_body)*
-                        { $($next)* }
+                        ( $($next)* )
                     },
                     @rest: { $($top_rest)* },
                 }
                 $($stack)*
             },
             @init: { $($init)* },
+            @lints: { $($lint)* },
             @body: { $($body)* },
         }
     };
     (
+        @config: $config:tt,
         @temps { $($temp:ident)* },
         @stack: {
             {
-                @paren: (),
+                @paren: ([]),
+                @scope: $next_scope:tt,
                 @body: { $($next:tt)* },
                 @rest: { },
             }
             {
-                @paren: $top_paren:tt,
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: { closure: $closure:ident, outer: $outer:ident, looping: $looping:ident, pending: $pending:ident, prev: $prev:tt },
                 @body: { $($top_body:tt)* },
-                @rest: { $($top_rest:tt)* },
+                @rest: { $after:tt $($top_rest:tt)* },
             }
             $($stack:tt)*
         },
         @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
         @body: { $($body:tt)* },
     ) => {
-        // Top item on the stack is done
-        // Combine it with the next item down
+        // Top item on the stack is done, and something within it was changed
+        // Combine it with the next item down, which is changed as well
+        // The token after it is peeled off in the same step
         $crate::soupa! {
+            @peel $after $after,
+            @config: $config,
             @temps { $($temp)* },
             @stack: {
                 {
-                    @paren: $top_paren,
+                    @paren: ($top_kind),
+                    @scope: { closure: $closure, outer: $outer, looping: $looping, pending: $pending, prev: @ },
                     @body: {
                         $($top_body)*
-                        ( $($next)* )
+                        [ $($next)* ]
                     },
                     @rest: { $($top_rest)* },
                 }
                 $($stack)*
             },
             @init: { $($init)* },
+            @lints: { $($lint)* },
             @body: { $($body)* },
         }
     };
     (
+        @config: $config:tt,
         @temps { $($temp:ident)* },
         @stack: {
             {
-                @paren: [],
+                @paren: ([]),
+                @scope: $next_scope:tt,
                 @body: { $($next:tt)* },
                 @rest: { },
             }
             {
-                @paren: $top_paren:tt,
+                @paren: ($top_kind:tt $($top_orig:tt)?),
+                @scope: $top_scope:tt,
                 @body: { $($top_body:tt)* },
                 @rest: { $($top_rest:tt)* },
             }
             $($stack:tt)*
         },
         @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
         @body: { $($body:tt)* },
     ) => {
-        // Top item on the stack is done
-        // Combine it with the next item down
+        // Top item on the stack is done, and something within it was changed
+        // Combine it with the next item down, which is changed as well
         $crate::soupa! {
+            @config: $config,
             @temps { $($temp)* },
             @stack: {
                 {
-                    @paren: $top_paren,
+                    @paren: ($top_kind),
+                    @scope: $top_scope,
                     @body: {
                         $($top_body)*
                         [ $($next)* ]
@@ -329,28 +4423,34 @@ macro_rules! soupa {
                 $($stack)*
             },
             @init: { $($init)* },
+            @lints: { $($lint)* },
             @body: { $($body)* },
         }
     };
 
     (
+        @config: $config:tt,
         @temps { $($temp:ident)* },
         @stack: {
             {
-                @paren: {},
+                @paren: ({} $($orig:tt)?),
+                @scope: $next_scope:tt,
                 @body: { $($next:tt)* },
                 @rest: { },
             }
         },
         @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
         @body: { $($body:tt)* },
     ) => {
         // Stack fully processed
         // Only a {} wrapped body is left, so output it
         $crate::soupa! {
+            @config: $config,
             @temps { $($temp)* },
             @stack: {},
             @init: { $($init)* },
+            @lints: { $($lint)* },
             @body: {
                 $($body)*
                 { $($next)* }
@@ -358,23 +4458,28 @@ macro_rules! soupa {
         }
     };
     (
+        @config: $config:tt,
         @temps { $($temp:ident)* },
         @stack: {
             {
-                @paren: (),
+                @paren: (() $($orig:tt)?),
+                @scope: $next_scope:tt,
                 @body: { $($next:tt)* },
                 @rest: { },
             }
         },
         @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
         @body: { $($body:tt)* },
     ) => {
         // Stack fully processed
         // Only a () wrapped body is left, so output it
         $crate::soupa! {
+            @config: $config,
             @temps { $($temp)* },
             @stack: {},
             @init: { $($init)* },
+            @lints: { $($lint)* },
             @body: {
                 $($body)*
                 ( $($next)* )
@@ -382,23 +4487,28 @@ macro_rules! soupa {
         }
     };
     (
+        @config: $config:tt,
         @temps { $($temp:ident)* },
         @stack: {
             {
-                @paren: [],
+                @paren: ([] $($orig:tt)?),
+                @scope: $next_scope:tt,
                 @body: { $($next:tt)* },
                 @rest: { },
             }
         },
         @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
         @body: { $($body:tt)* },
     ) => {
         // Stack fully processed
         // Only a [] wrapped body is left, so output it
         $crate::soupa! {
+            @config: $config,
             @temps { $($temp)* },
             @stack: {},
             @init: { $($init)* },
+            @lints: { $($lint)* },
             @body: {
                 $($body)*
                 [ $($next)* ]
@@ -406,23 +4516,28 @@ macro_rules! soupa {
         }
     };
     (
+        @config: $config:tt,
         @temps { $($temp:ident)* },
         @stack: {
             {
-                @paren: None,
+                @paren: (None),
+                @scope: $next_scope:tt,
                 @body: { $($next:tt)* },
                 @rest: { },
             }
         },
         @init: { $($init:tt)* },
+        @lints: { $($lint:tt)* },
         @body: { $($body:tt)* },
     ) => {
         // Stack fully processed
         // Only an unwrapped body is left, so output it
         $crate::soupa! {
+            @config: $config,
             @temps { $($temp)* },
             @stack: {},
             @init: { $($init)* },
+            @lints: { $($lint)* },
             @body: {
                 $($body)*
                 $($next)*
@@ -431,11 +4546,138 @@ macro_rules! soupa {
     };
 
     (
+        @start { $($config:tt)* }
+        @strict
         $($rest:tt)*
     ) => {
-        // No other rule matches
-        // Implies this is user supplied, so initialize with some temp variable names
+        // Collect the strict option
+        $crate::soupa! {
+            @start { $($config)* strict }
+            $($rest)*
+        }
+    };
+    (
+        @start { $($config:tt)* }
+        @send
+        $($rest:tt)*
+    ) => {
+        // Collect the send option
+        $crate::soupa! {
+            @start { $($config)* send }
+            $($rest)*
+        }
+    };
+    (
+        @start { $($config:tt)* }
+        @sync
+        $($rest:tt)*
+    ) => {
+        // Collect the sync option
+        $crate::soupa! {
+            @start { $($config)* sync }
+            $($rest)*
+        }
+    };
+    (
+        @start { $($config:tt)* }
+        @static
+        $($rest:tt)*
+    ) => {
+        // Collect the static option
+        $crate::soupa! {
+            @start { $($config)* static }
+            $($rest)*
+        }
+    };
+    (
+        @start { $($config:tt)* }
+        @lazy
+        $($rest:tt)*
+    ) => {
+        // Collect the lazy option
+        $crate::soupa! {
+            @start { $($config)* lazy }
+            $($rest)*
+        }
+    };
+    (
+        @start { dedup $($config:tt)* }
+        @boxed($($bound:tt)*)
+        $($rest:tt)*
+    ) => {
+        // Collect the boxed option, keeping the dedup option first
+        $crate::soupa! {
+            @start { dedup boxed($($bound)*) $($config)* }
+            $($rest)*
+        }
+    };
+    (
+        @start { $($config:tt)* }
+        @boxed($($bound:tt)*)
+        $($rest:tt)*
+    ) => {
+        // Collect the boxed option, along with the trait object it is boxed as
+        // It is kept first, so the output is boxed before any checks can hide its type
         $crate::soupa! {
+            @start { boxed($($bound)*) $($config)* }
+            $($rest)*
+        }
+    };
+    (
+        @start { $($config:tt)* }
+        @dedup
+        $($rest:tt)*
+    ) => {
+        // Collect the dedup option
+        // It is kept first, so super blocks can check for it
+        $crate::soupa! {
+            @start { dedup $($config)* }
+            $($rest)*
+        }
+    };
+    (
+        @start { $($config:tt)* }
+        $first:tt $($rest:tt)*
+    ) => {
+        // Options have been collected
+        // Initialize with some temp variable names
+        // The first token is peeled off in the same step
+        $crate::soupa! {
+            @peel $first $first,
+            @config: { $($config)* },
+            @temps {
+                __soupa_temp_a __soupa_temp_b __soupa_temp_c __soupa_temp_d __soupa_temp_e __soupa_temp_f __soupa_temp_g __soupa_temp_h __soupa_temp_i __soupa_temp_j __soupa_temp_k __soupa_temp_l __soupa_temp_m __soupa_temp_n __soupa_temp_o __soupa_temp_p __soupa_temp_q __soupa_temp_r __soupa_temp_s __soupa_temp_t __soupa_temp_u __soupa_temp_v __soupa_temp_w __soupa_temp_x __soupa_temp_y __soupa_temp_z
+                __soupa_temp_aa __soupa_temp_ab __soupa_temp_ac __soupa_temp_ad __soupa_temp_ae __soupa_temp_af __soupa_temp_ag __soupa_temp_ah __soupa_temp_ai __soupa_temp_aj __soupa_temp_ak __soupa_temp_al __soupa_temp_am __soupa_temp_an __soupa_temp_ao __soupa_temp_ap __soupa_temp_aq __soupa_temp_ar __soupa_temp_as __soupa_temp_at __soupa_temp_au __soupa_temp_av __soupa_temp_aw __soupa_temp_ax __soupa_temp_ay __soupa_temp_az
+                __soupa_temp_ba __soupa_temp_bb __soupa_temp_bc __soupa_temp_bd __soupa_temp_be __soupa_temp_bf __soupa_temp_bg __soupa_temp_bh __soupa_temp_bi __soupa_temp_bj __soupa_temp_bk __soupa_temp_bl __soupa_temp_bm __soupa_temp_bn __soupa_temp_bo __soupa_temp_bp __soupa_temp_bq __soupa_temp_br __soupa_temp_bs __soupa_temp_bt __soupa_temp_bu __soupa_temp_bv __soupa_temp_bw __soupa_temp_bx __soupa_temp_by __soupa_temp_bz
+                __soupa_temp_ca __soupa_temp_cb __soupa_temp_cc __soupa_temp_cd __soupa_temp_ce __soupa_temp_cf __soupa_temp_cg __soupa_temp_ch __soupa_temp_ci __soupa_temp_cj __soupa_temp_ck __soupa_temp_cl __soupa_temp_cm __soupa_temp_cn __soupa_temp_co __soupa_temp_cp __soupa_temp_cq __soupa_temp_cr __soupa_temp_cs __soupa_temp_ct __soupa_temp_cu __soupa_temp_cv __soupa_temp_cw __soupa_temp_cx __soupa_temp_cy __soupa_temp_cz
+                __soupa_temp_da __soupa_temp_db __soupa_temp_dc __soupa_temp_dd __soupa_temp_de __soupa_temp_df __soupa_temp_dg __soupa_temp_dh __soupa_temp_di __soupa_temp_dj __soupa_temp_dk __soupa_temp_dl __soupa_temp_dm __soupa_temp_dn __soupa_temp_do __soupa_temp_dp __soupa_temp_dq __soupa_temp_dr __soupa_temp_ds __soupa_temp_dt __soupa_temp_du __soupa_temp_dv __soupa_temp_dw __soupa_temp_dx __soupa_temp_dy __soupa_temp_dz
+                __soupa_temp_ea __soupa_temp_eb __soupa_temp_ec __soupa_temp_ed __soupa_temp_ee __soupa_temp_ef __soupa_temp_eg __soupa_temp_eh __soupa_temp_ei __soupa_temp_ej __soupa_temp_ek __soupa_temp_el __soupa_temp_em __soupa_temp_en __soupa_temp_eo __soupa_temp_ep __soupa_temp_eq __soupa_temp_er __soupa_temp_es __soupa_temp_et __soupa_temp_eu __soupa_temp_ev __soupa_temp_ew __soupa_temp_ex __soupa_temp_ey __soupa_temp_ez
+                __soupa_temp_fa __soupa_temp_fb __soupa_temp_fc __soupa_temp_fd __soupa_temp_fe __soupa_temp_ff __soupa_temp_fg __soupa_temp_fh __soupa_temp_fi __soupa_temp_fj __soupa_temp_fk __soupa_temp_fl __soupa_temp_fm __soupa_temp_fn __soupa_temp_fo __soupa_temp_fp __soupa_temp_fq __soupa_temp_fr __soupa_temp_fs __soupa_temp_ft __soupa_temp_fu __soupa_temp_fv __soupa_temp_fw __soupa_temp_fx __soupa_temp_fy __soupa_temp_fz
+                __soupa_temp_ga __soupa_temp_gb __soupa_temp_gc __soupa_temp_gd __soupa_temp_ge __soupa_temp_gf __soupa_temp_gg __soupa_temp_gh __soupa_temp_gi __soupa_temp_gj __soupa_temp_gk __soupa_temp_gl __soupa_temp_gm __soupa_temp_gn __soupa_temp_go __soupa_temp_gp __soupa_temp_gq __soupa_temp_gr __soupa_temp_gs __soupa_temp_gt __soupa_temp_gu __soupa_temp_gv __soupa_temp_gw __soupa_temp_gx __soupa_temp_gy __soupa_temp_gz
+                __soupa_temp_ha __soupa_temp_hb __soupa_temp_hc __soupa_temp_hd __soupa_temp_he __soupa_temp_hf __soupa_temp_hg __soupa_temp_hh __soupa_temp_hi __soupa_temp_hj __soupa_temp_hk __soupa_temp_hl __soupa_temp_hm __soupa_temp_hn __soupa_temp_ho __soupa_temp_hp __soupa_temp_hq __soupa_temp_hr __soupa_temp_hs __soupa_temp_ht __soupa_temp_hu __soupa_temp_hv __soupa_temp_hw __soupa_temp_hx __soupa_temp_hy __soupa_temp_hz
+                __soupa_temp_ia __soupa_temp_ib __soupa_temp_ic __soupa_temp_id __soupa_temp_ie __soupa_temp_if __soupa_temp_ig __soupa_temp_ih __soupa_temp_ii __soupa_temp_ij __soupa_temp_ik __soupa_temp_il __soupa_temp_im __soupa_temp_in __soupa_temp_io __soupa_temp_ip __soupa_temp_iq __soupa_temp_ir __soupa_temp_is __soupa_temp_it __soupa_temp_iu __soupa_temp_iv __soupa_temp_iw __soupa_temp_ix __soupa_temp_iy __soupa_temp_iz
+            },
+            @stack: {
+                {
+                    @paren: (None),
+                    @scope: { closure: no, outer: no, looping: no, pending: no, prev: @ },
+                    @body: {},
+                    @rest: { $($rest)* },
+                }
+            },
+            @init: {},
+            @lints: {},
+            @body: {},
+        }
+    };
+    (
+        @start { $($config:tt)* }
+        $($rest:tt)*
+    ) => {
+        // Options have been collected
+        // Initialize with some temp variable names
+        $crate::soupa! {
+            @config: { $($config)* },
             @temps {
                 __soupa_temp_a __soupa_temp_b __soupa_temp_c __soupa_temp_d __soupa_temp_e __soupa_temp_f __soupa_temp_g __soupa_temp_h __soupa_temp_i __soupa_temp_j __soupa_temp_k __soupa_temp_l __soupa_temp_m __soupa_temp_n __soupa_temp_o __soupa_temp_p __soupa_temp_q __soupa_temp_r __soupa_temp_s __soupa_temp_t __soupa_temp_u __soupa_temp_v __soupa_temp_w __soupa_temp_x __soupa_temp_y __soupa_temp_z
                 __soupa_temp_aa __soupa_temp_ab __soupa_temp_ac __soupa_temp_ad __soupa_temp_ae __soupa_temp_af __soupa_temp_ag __soupa_temp_ah __soupa_temp_ai __soupa_temp_aj __soupa_temp_ak __soupa_temp_al __soupa_temp_am __soupa_temp_an __soupa_temp_ao __soupa_temp_ap __soupa_temp_aq __soupa_temp_ar __soupa_temp_as __soupa_temp_at __soupa_temp_au __soupa_temp_av __soupa_temp_aw __soupa_temp_ax __soupa_temp_ay __soupa_temp_az
@@ -450,13 +4692,263 @@ macro_rules! soupa {
             },
             @stack: {
                 {
-                    @paren: None,
+                    @paren: (None),
+                    @scope: { closure: no, outer: no, looping: no, pending: no, prev: {} },
                     @body: {},
                     @rest: { $($rest)* },
                 }
             },
             @init: {},
+            @lints: {},
             @body: {},
         }
     };
+
+    (
+        @branch_start { lazy $($option:tt)* } { $($kept:tt)* } $block:tt
+    ) => {
+        // Keep the lazy option for a branch, so branches within it are lifted into as well
+        $crate::soupa! {
+            @branch_start { $($option)* } { $($kept)* lazy } $block
+        }
+    };
+    (
+        @branch_start { strict $($option:tt)* } { $($kept:tt)* } $block:tt
+    ) => {
+        // Keep the strict option for a branch, so its lints are the same
+        $crate::soupa! {
+            @branch_start { $($option)* } { $($kept)* strict } $block
+        }
+    };
+    (
+        @branch_start { dedup $($option:tt)* } { $($kept:tt)* } $block:tt
+    ) => {
+        // Keep the dedup option for a branch, which stays first as it was
+        $crate::soupa! {
+            @branch_start { $($option)* } { $($kept)* dedup } $block
+        }
+    };
+    (
+        @branch_start { $next:tt $($option:tt)* } $kept:tt $block:tt
+    ) => {
+        // Other options apply to the output as a whole, rather than a branch
+        $crate::soupa! {
+            @branch_start { $($option)* } $kept $block
+        }
+    };
+    (
+        @branch_start {} { $($kept:tt)* } { $($block:tt)* }
+    ) => {
+        // Options for the branch have been collected
+        // Its block is processed as an invocation of its own
+        $crate::soupa! {
+            @start { $($kept)* }
+            $($block)*
+        }
+    };
+
+    (
+        $($rest:tt)*
+    ) => {
+        // No other rule matches
+        // Implies this is user supplied, so start with no options
+        $crate::soupa! {
+            @start {}
+            $($rest)*
+        }
+    };
+}
+
+/// Shows the desugared form of a [`soupa!`] invocation as a `&'static str`.
+///
+/// The input is processed exactly as [`soupa!`] would process it, but instead
+/// of being evaluated the output is passed to [`stringify!`].
+/// Lints and the checks added by assertion options are left out, as nothing is
+/// evaluated.
+/// This can be used to debug an expansion without reaching for `cargo expand`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use soupa::explain;
+/// let explained = explain!(move || super { foo.clone() });
+///
+/// // The exact whitespace of stringify! is not guaranteed
+/// let explained = explained.split_whitespace().collect::<String>();
+///
+/// assert_eq!(explained, "{let__soupa_temp_a={foo.clone()};move||__soupa_temp_a}");
+/// ```
+#[macro_export]
+macro_rules! explain {
+    ($($rest:tt)*) => {
+        $crate::soupa! {
+            @start { explain }
+            $($rest)*
+        }
+    };
+}
+
+/// Marks an expression to be lifted by [`soupa!`], equivalent to `super { ... }`.
+///
+/// Unlike `super { ... }`, a `lift!(...)` marker is valid Rust syntax, allowing
+/// the body of a [`soupa!`] invocation to be understood by tools such as
+/// rust-analyzer and rustfmt.
+/// The marker is recognised when written as `lift!`, `::lift!`, `soupa::lift!`
+/// or `::soupa::lift!`, with any kind of delimiter, but not as the end of a
+/// longer path such as `other::lift!`.
+///
+/// Outside of [`soupa!`] this macro only produces a compilation error.
+///
+/// ```rust,compile_fail
+/// # use soupa::lift;
+/// let foo = lift!(123usize);
+/// ```
+#[macro_export]
+macro_rules! lift {
+    ($($rest:tt)*) => {
+        compile_error!("`lift!` can only be used within `soupa!`")
+    };
+}
+
+/// Spawns a future as a task, after lifting any super blocks within it as
+/// [`soupa!`] would.
+///
+/// The first argument is the function used to spawn the future, such as
+/// `tokio::spawn`, and is called with the future as its only argument.
+/// Any expression callable as `fn(F) -> H` can be used, so the task can be spawned
+/// on any executor, and whatever the spawner returns is returned in turn.
+///
+/// ```rust,edition2018
+/// # use std::sync::Arc;
+/// # use std::future::Future;
+/// // A stand-in for an executor's spawn function
+/// fn spawn<F: Future>(future: F) -> F {
+///     future
+/// }
+///
+/// let state = Arc::new(123usize);
+///
+/// let task = soupa::spawn!(spawn, async move { *super { state.clone() } + 1 });
+///
+/// // The super block was evaluated before the future was created
+/// assert_eq!(Arc::strong_count(&state), 2);
+/// ```
+///
+/// Spawn functions for some common executors are provided in the
+/// `task` module, behind the `tokio`, `async-std` and `smol` features.
+#[macro_export]
+macro_rules! spawn {
+    ($spawner:expr, $($rest:tt)*) => {
+        $spawner($crate::soupa!($($rest)*))
+    };
+}
+
+#[doc(hidden)]
+pub mod __private {
+    //! Items used by the expansion of [`soupa!`](crate::soupa), which are not
+    //! part of the public API.
+    //!
+    //! Lints are emitted as calls to deprecated functions, as that is the only
+    //! way for a declarative macro to produce a warning on stable.
+
+    #[deprecated(
+        note = "this `super { ... }` block is not within a closure or async block, so lifting it only changes when it is evaluated"
+    )]
+    #[inline(always)]
+    pub const fn super_block_outside_closure() {}
+
+    #[deprecated(
+        note = "this `super { ... }` block is evaluated once, before the loop it is within; if its value is moved within the loop, clone it on each use with `super { ... }.clone()`"
+    )]
+    #[inline(always)]
+    pub const fn super_block_in_loop() {}
+
+    #[cfg(feature = "std")]
+    pub use std::thread::Builder as ThreadBuilder;
+
+    #[cfg(feature = "std")]
+    pub fn thread_name<N: Into<std::string::String>>(name: N) -> std::string::String {
+        name.into()
+    }
+
+    #[cfg(feature = "tracing")]
+    pub use tracing::Span;
+
+    /// Lifted by `super span`.
+    #[cfg(feature = "tracing")]
+    #[inline(always)]
+    pub fn current_span() -> Span {
+        Span::current()
+    }
+
+    /// Instruments an async block following `super span`.
+    #[cfg(feature = "tracing")]
+    #[inline(always)]
+    pub fn instrument<F: core::future::Future>(
+        future: F,
+        span: Span,
+    ) -> tracing::instrument::Instrumented<F> {
+        tracing::Instrument::instrument(future, span)
+    }
+
+    /// A value captured by a super block with a capture adapter, along with
+    /// how to restore it.
+    pub struct Capture<C, O> {
+        captured: C,
+        restore: fn(&C) -> O,
+    }
+
+    impl<C: Clone, O> Clone for Capture<C, O> {
+        fn clone(&self) -> Self {
+            Capture {
+                captured: self.captured.clone(),
+                restore: self.restore,
+            }
+        }
+    }
+
+    impl<C: Copy, O> Copy for Capture<C, O> {}
+
+    /// Lifted by `super with Adapter { ... }`.
+    #[inline(always)]
+    pub fn capture<A: crate::SoupaCapture<T>, T>(
+        adapter: &A,
+        value: T,
+    ) -> Capture<A::Captured, A::Output> {
+        Capture {
+            captured: adapter.capture(value),
+            restore: A::restore,
+        }
+    }
+
+    /// Used in place of `super with Adapter { ... }`.
+    #[inline(always)]
+    pub fn restore<C, O>(capture: &Capture<C, O>) -> O {
+        (capture.restore)(&capture.captured)
+    }
+
+    #[cfg(feature = "alloc")]
+    pub use alloc::boxed::Box;
+
+    pub use core::future::Future;
+
+    pub use core::pin::Pin;
+
+    /// Passes through the output of `soupa!(@send ...)`.
+    #[inline(always)]
+    pub fn output_must_be_send<T: Send>(output: T) -> T {
+        output
+    }
+
+    /// Passes through the output of `soupa!(@sync ...)`.
+    #[inline(always)]
+    pub fn output_must_be_sync<T: Sync>(output: T) -> T {
+        output
+    }
+
+    /// Passes through the output of `soupa!(@static ...)`.
+    #[inline(always)]
+    pub fn output_must_be_static<T: 'static>(output: T) -> T {
+        output
+    }
 }
@@ -0,0 +1,60 @@
+//! Functions spawning futures on common executors, for use with
+//! [`spawn!`](crate::spawn).
+//!
+//! Each function is only available with the feature of the same name, and
+//! returns the executor's own handle to the spawned task.
+//!
+//! ```rust,edition2018
+//! # #[cfg(feature = "tokio")]
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! # use std::sync::Arc;
+//! let state = Arc::new(123usize);
+//!
+//! let handle = soupa::spawn!(soupa::task::tokio, async move { *super { state.clone() } });
+//!
+//! assert_eq!(handle.await.unwrap(), 123);
+//! # });
+//! ```
+
+use core::future::Future;
+
+/// Spawns a future onto the current tokio runtime, as
+/// [`tokio::spawn`] would.
+///
+/// Panics if called outside of a tokio runtime, reporting where it was called.
+#[cfg(feature = "tokio")]
+#[track_caller]
+pub fn tokio<F>(future: F) -> ::tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    ::tokio::spawn(future)
+}
+
+/// Spawns a future onto the async-std executor, as
+/// [`async_std::task::spawn`] would.
+#[cfg(feature = "async-std")]
+#[track_caller]
+pub fn async_std<F>(future: F) -> ::async_std::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    ::async_std::task::spawn(future)
+}
+
+/// Spawns a future onto the global smol executor, as
+/// [`smol::spawn`] would.
+///
+/// The task is cancelled if the returned [`Task`](::smol::Task) is dropped,
+/// unless it is detached first.
+#[cfg(feature = "smol")]
+#[track_caller]
+pub fn smol<F>(future: F) -> ::smol::Task<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    ::smol::spawn(future)
+}
@@ -0,0 +1,109 @@
+//! Spawning threads with closures produced by [`soupa!`](crate::soupa).
+//!
+//! The most common use of [`soupa!`](crate::soupa) is to create the closure
+//! passed to [`std::thread::spawn`].
+//! The macros within this module do both at once, and check that the closure can
+//! be sent to another thread where it is written, rather than at the spawn call.
+//!
+//! ```rust
+//! # use std::sync::Arc;
+//! let foo = Arc::new(123usize);
+//!
+//! let handle = soupa::thread::spawn!(move || *super { foo.clone() });
+//!
+//! let _ = foo;
+//!
+//! assert_eq!(handle.join().unwrap(), 123);
+//! ```
+//!
+//! This module requires the `std` feature.
+
+/// Spawns a thread running a closure, after lifting any super blocks within it
+/// as [`soupa!`](crate::soupa) would.
+///
+/// The thread can be given a name and a stack size by writing `name = ...,`
+/// and `stack_size = ...,`, in that order, before the closure.
+/// The thread is spawned through [`std::thread::Builder`], panicking if it
+/// couldn't be, exactly as [`std::thread::spawn`] would.
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// let foo = Arc::new(123usize);
+///
+/// let handle = soupa::thread::spawn!(
+///     name = "worker",
+///     stack_size = 64 * 1024,
+///     move || (std::thread::current().name().map(String::from), *super { foo.clone() })
+/// );
+///
+/// assert_eq!(handle.join().unwrap(), (Some(String::from("worker")), 123));
+/// ```
+///
+/// The closure must be `Send + 'static`, as must its output.
+/// The value of every super block is checked first, so if one can't be sent to
+/// the new thread the error names its label, or its temporary name if it has
+/// none.
+///
+/// ```rust,compile_fail
+/// # use std::rc::Rc;
+/// let foo = Rc::new(123usize);
+///
+/// // error: `Rc<usize>` cannot be sent between threads safely
+/// // note: required by a bound in `foo_clone`
+/// let handle = soupa::thread::spawn!(move || *super(foo_clone) { foo.clone() });
+/// ```
+#[doc(inline)]
+pub use __soupa_thread_spawn as spawn;
+
+/// Spawns a named thread running a closure, after lifting any super blocks
+/// within it as [`soupa!`](crate::soupa) would.
+///
+/// This is shorthand for [`spawn!`] with a `name = ...,` argument.
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// let foo = Arc::new(123usize);
+///
+/// let handle = soupa::thread::spawn_named!("worker", move || *super { foo.clone() });
+///
+/// assert_eq!(handle.thread().name(), Some("worker"));
+/// assert_eq!(handle.join().unwrap(), 123);
+/// ```
+#[doc(inline)]
+pub use __soupa_thread_spawn_named as spawn_named;
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __soupa_thread_spawn {
+    (@builder $builder:expr; name = $name:expr, $($rest:tt)*) => {
+        $crate::__soupa_thread_spawn! {
+            @builder $builder.name($crate::__private::thread_name($name));
+            $($rest)*
+        }
+    };
+    (@builder $builder:expr; stack_size = $stack_size:expr, $($rest:tt)*) => {
+        $crate::__soupa_thread_spawn! {
+            @builder $builder.stack_size($stack_size);
+            $($rest)*
+        }
+    };
+    (@builder $builder:expr; $($rest:tt)*) => {
+        $builder
+            .spawn($crate::soupa!(@start { send static } $($rest)*))
+            .expect("failed to spawn thread")
+    };
+    ($($rest:tt)*) => {
+        $crate::__soupa_thread_spawn! {
+            @builder $crate::__private::ThreadBuilder::new();
+            $($rest)*
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __soupa_thread_spawn_named {
+    ($name:expr, $($rest:tt)*) => {
+        $crate::__soupa_thread_spawn!(name = $name, $($rest)*)
+    };
+}
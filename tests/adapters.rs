@@ -0,0 +1,125 @@
+#[macro_use]
+extern crate soupa;
+
+use soupa::adapters::Cloned;
+use soupa::SoupaCapture;
+use std::cell::{Cell, RefCell};
+
+/// Captures a snapshot of the contents of a `RefCell`, counting how many times
+/// it has been restored.
+struct Snapshot<'a>(&'a Cell<usize>);
+
+impl<'a, 'b, T: Clone> SoupaCapture<&'b RefCell<T>> for Snapshot<'a> {
+    type Captured = T;
+    type Output = T;
+
+    fn capture(&self, value: &'b RefCell<T>) -> T {
+        self.0.set(self.0.get() + 1);
+        value.borrow().clone()
+    }
+
+    fn restore(captured: &T) -> T {
+        captured.clone()
+    }
+}
+
+/// Restores captured values as their lengths.
+struct Length;
+
+impl<'a> SoupaCapture<&'a str> for Length {
+    type Captured = usize;
+    type Output = usize;
+
+    fn capture(&self, value: &'a str) -> usize {
+        value.len()
+    }
+
+    fn restore(captured: &usize) -> usize {
+        *captured
+    }
+}
+
+#[test]
+fn custom_adapter() {
+    let captures = Cell::new(0);
+    let config = RefCell::new(String::from("before"));
+
+    let snapshot = Snapshot(&captures);
+
+    // The adapter can be any path to a value, including a local variable
+    let func = soupa!(move || super with snapshot { &config });
+
+    *config.borrow_mut() = String::from("after");
+
+    assert_eq!(func(), "before");
+    assert_eq!(captures.get(), 1);
+}
+
+#[test]
+fn restored_within() {
+    let foo = String::from("foo");
+
+    let func = soupa!(move || super with Length { foo.as_str() } * 2);
+
+    drop(foo);
+
+    assert_eq!(func(), 6);
+    assert_eq!(func(), 6);
+}
+
+#[test]
+fn restored_repeatedly() {
+    fn call_twice<F: Fn() -> String>(func: F) -> (String, String) {
+        (func(), func())
+    }
+
+    let foo = String::from("foo");
+
+    // Captures which aren't Copy can be restored more than once too
+    let func = soupa!(move || super with Cloned { &foo });
+
+    drop(foo);
+
+    assert_eq!(call_twice(func), (String::from("foo"), String::from("foo")));
+}
+
+#[test]
+fn labelled() {
+    let foo = String::from("foo");
+
+    let func = soupa!(move || (
+        super(b, after = a) with Cloned { &foo },
+        super(a) with Length { "foo" },
+    ));
+
+    assert_eq!(func(), (String::from("foo"), 3));
+}
+
+#[test]
+fn explained() {
+    let explained = explain!(move || super with Cloned { &foo });
+
+    assert_eq!(
+        explained.split_whitespace().collect::<String>(),
+        "{let__soupa_temp_a={$crate::__private::capture(&Cloned,{&foo})};\
+         move||$crate::__private::restore(&__soupa_temp_a)}"
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn downgrade() {
+    use soupa::adapters::Downgrade;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    let foo = Arc::new(123usize);
+    let bar = Rc::new(456usize);
+
+    let func = soupa!(move || super with Downgrade { &foo }.map(|foo| *foo));
+    assert_eq!(func(), Some(123));
+
+    let func = soupa!(move || super with Downgrade { &bar });
+    drop(bar);
+    assert_eq!(func(), None);
+}
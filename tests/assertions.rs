@@ -0,0 +1,48 @@
+//! Checks the `@send`, `@sync` and `@static` options, which only add compile
+//! time checks to the output.
+
+#[macro_use]
+extern crate soupa;
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[test]
+fn send() {
+    let foo = Arc::new(Mutex::new(123usize));
+
+    let func = soupa!(@send move || *super { foo.clone() }.lock().unwrap());
+
+    assert_eq!(thread::spawn(func).join().unwrap(), 123);
+}
+
+#[test]
+fn sync() {
+    let foo = Arc::new(123usize);
+
+    let func = soupa!(@sync move || *super(foo) { foo.clone() });
+
+    // A shared reference to the closure can be sent to another thread
+    thread::scope(|scope| {
+        assert_eq!(scope.spawn(&func).join().unwrap(), 123);
+    });
+}
+
+#[test]
+fn combined() {
+    let foo = Arc::new(123usize);
+
+    let func = soupa!(@send @sync @static move || *super { foo.clone() } + 1);
+
+    assert_eq!(thread::spawn(func).join().unwrap(), 124);
+    assert_eq!(*foo, 123);
+}
+
+#[test]
+fn other_options() {
+    let foo = Arc::new(123usize);
+
+    let func = soupa!(@strict @send move || *super { foo.clone() });
+
+    assert_eq!(func(), 123);
+}
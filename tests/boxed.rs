@@ -0,0 +1,86 @@
+//! Checks the `@boxed` option, which requires the `alloc` feature.
+
+#![cfg(feature = "alloc")]
+
+#[macro_use]
+extern crate soupa;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+struct Event(usize);
+
+/// Polls a future which is immediately ready.
+fn poll_ready<F: Future + ?Sized>(future: Pin<&mut F>) -> F::Output {
+    fn waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker {
+            waker()
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+
+    let waker = unsafe { Waker::from_raw(waker()) };
+
+    match future.poll(&mut Context::from_waker(&waker)) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("future was not ready"),
+    }
+}
+
+#[test]
+fn callback_registry() {
+    let foo = Arc::new(1usize);
+
+    let mut callbacks: Vec<Box<dyn Fn(Event) -> usize + Send>> = Vec::new();
+
+    // The closure's signature is inferred from the trait object
+    callbacks.push(
+        soupa!(@boxed(Fn(Event) -> usize + Send) move |event| event.0 + *super { foo.clone() }),
+    );
+    callbacks.push(
+        soupa!(@boxed(Fn(Event) -> usize + Send) move |event| event.0 * *super { foo.clone() }),
+    );
+
+    assert_eq!(Arc::strong_count(&foo), 3);
+    assert_eq!(
+        callbacks
+            .iter()
+            .map(|callback| callback(Event(3)))
+            .sum::<usize>(),
+        7
+    );
+}
+
+#[test]
+fn with_assertions() {
+    let foo = Arc::new(1usize);
+
+    let mut func: Box<dyn FnMut() -> usize + Send> = soupa!(@send @static @boxed(FnMut() -> usize + Send + 'static) move || *super { foo.clone() });
+
+    assert_eq!(func(), 1);
+}
+
+#[test]
+fn borrowed_lifetime() {
+    fn adder<'a>(offset: &'a usize) -> Box<dyn Fn(usize) -> usize + 'a> {
+        soupa!(@boxed(Fn(usize) -> usize + 'a) move |x| x + *super { offset })
+    }
+
+    let offset = 2;
+
+    assert_eq!(adder(&offset)(1), 3);
+}
+
+#[test]
+fn future() {
+    let foo = Arc::new(1usize);
+
+    let mut future: Pin<Box<dyn Future<Output = usize> + Send>> =
+        soupa!(@boxed(Future<Output = usize> + Send) std::future::ready(*super { foo.clone() }));
+
+    assert_eq!(poll_ready(future.as_mut()), 1);
+}
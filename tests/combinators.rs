@@ -0,0 +1,64 @@
+extern crate soupa;
+
+use soupa::capture;
+use std::sync::Arc;
+
+#[test]
+fn captured_before_use() {
+    let a = Arc::new(1usize);
+    let b = Arc::new(2usize);
+
+    let func = capture((a.clone(), b.clone())).into_fn(|(a, b), x: usize| **a + **b + x);
+
+    assert_eq!(Arc::strong_count(&a), 2);
+    assert_eq!(func(3), 6);
+
+    drop(func);
+    assert_eq!(Arc::strong_count(&a), 1);
+}
+
+#[test]
+fn same_as_macro() {
+    let foo = Arc::new(1usize);
+
+    let with_macro = soupa::soupa!(move |x: usize| *super { foo.clone() } + x);
+    let without_macro = capture(foo.clone()).into_fn(|foo, x: usize| **foo + x);
+
+    assert_eq!(with_macro(1), without_macro(1));
+}
+
+#[test]
+fn fn_mut() {
+    let mut log = capture(Vec::new()).into_fn_mut(|log, entry: &'static str| {
+        log.push(entry);
+        log.len()
+    });
+
+    assert_eq!(log("a"), 1);
+    assert_eq!(log("b"), 2);
+}
+
+#[test]
+fn fn_once() {
+    let foo = Arc::new(1usize);
+
+    let take = capture(foo.clone()).into_fn_once(|foo, ()| foo);
+
+    assert!(Arc::ptr_eq(&take(()), &foo));
+}
+
+#[test]
+fn clone_per_call() {
+    let foo = Arc::new(1usize);
+
+    let func = capture(foo.clone()).into_fn_cloned(|foo, ()| Arc::strong_count(&foo));
+
+    // One held by the test, one captured, and one cloned for the call
+    assert_eq!(func(()), 3);
+    assert_eq!(func(()), 3);
+}
+
+#[test]
+fn into_inner() {
+    assert_eq!(capture((1, 2)).into_inner(), (1, 2));
+}
@@ -0,0 +1,76 @@
+#[macro_use]
+extern crate soupa;
+
+use std::cell::Cell;
+
+/// Records an evaluation, returning `value`.
+fn record<T>(evaluations: &Cell<usize>, value: T) -> T {
+    evaluations.set(evaluations.get() + 1);
+    value
+}
+
+#[test]
+fn identical_blocks() {
+    let evaluations = Cell::new(0);
+
+    let (a, b, c) = soupa!(@dedup (
+        move || super { record(&evaluations, 1usize) },
+        move || super { record(&evaluations, 1usize) } + 1,
+        move || lift!(record(&evaluations, 1usize)) + 2,
+    ));
+
+    assert_eq!(evaluations.get(), 1);
+    assert_eq!((a(), b(), c()), (1, 2, 3));
+}
+
+#[test]
+fn different_blocks() {
+    let evaluations = Cell::new(0);
+
+    let (a, b) = soupa!(@dedup (
+        move || super { record(&evaluations, 1usize) },
+        move || super { record(&evaluations, 2usize) },
+    ));
+
+    assert_eq!(evaluations.get(), 2);
+    assert_eq!((a(), b()), (1, 2));
+}
+
+#[test]
+fn labelled_blocks() {
+    let evaluations = Cell::new(0);
+
+    let (a, b) = soupa!(@dedup (
+        move || super(a) { record(&evaluations, 1usize) },
+        move || super { record(&evaluations, 1usize) },
+    ));
+
+    assert_eq!(evaluations.get(), 2);
+    assert_eq!((a(), b()), (1, 1));
+}
+
+#[test]
+fn without_dedup() {
+    let evaluations = Cell::new(0);
+
+    let (a, b) = soupa!((
+        move || super { record(&evaluations, 1usize) },
+        move || super { record(&evaluations, 1usize) },
+    ));
+
+    assert_eq!(evaluations.get(), 2);
+    assert_eq!((a(), b()), (1, 1));
+}
+
+#[test]
+fn with_other_options() {
+    let evaluations = Cell::new(0);
+
+    let (a, b) = soupa!(@strict @dedup @static (
+        move || super { record(&evaluations, 1usize) },
+        move || super { record(&evaluations, 1usize) },
+    ));
+
+    assert_eq!(evaluations.get(), 1);
+    assert_eq!((a(), b()), (1, 1));
+}
@@ -0,0 +1,5 @@
+//! Checks [`soupa!`](soupa::soupa) is usable from a crate using edition 2015,
+//! with the call-site features that edition provides.
+//! The checks themselves are integration tests of this crate.
+
+extern crate soupa;
@@ -0,0 +1,38 @@
+#[macro_use]
+extern crate soupa;
+
+use std::sync::Arc;
+
+#[test]
+fn closure() {
+    let foo = Arc::new(123usize);
+
+    let func = soupa!(move || *super { foo.clone() } * 2);
+
+    drop(foo);
+
+    assert_eq!(func(), 246);
+}
+
+#[test]
+fn async_is_an_identifier() {
+    let async = Arc::new(123usize);
+
+    let func = soupa!(move || *super { async.clone() } * 2);
+
+    assert_eq!(func(), 246);
+}
+
+#[test]
+fn let_else() {
+    let foo = Arc::new(Some(123usize));
+
+    let func = soupa!(move || {
+        let Some(value) = *super { foo.clone() } else {
+            return 0;
+        };
+        value * 2
+    });
+
+    assert_eq!(func(), 246);
+}
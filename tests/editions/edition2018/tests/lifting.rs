@@ -0,0 +1,65 @@
+use soupa::soupa;
+use soupa_edition2018::block_on;
+use std::sync::Arc;
+
+#[test]
+fn async_block() {
+    let foo = Arc::new(123usize);
+
+    let future = soupa!(async move { *super { foo.clone() } * 2 });
+
+    drop(foo);
+
+    assert_eq!(block_on(future), 246);
+}
+
+#[test]
+fn async_closure() {
+    let foo = Arc::new(123usize);
+
+    let func = soupa!(async move || *super { foo.clone() } * 2);
+
+    assert_eq!(Arc::strong_count(&foo), 2);
+    assert_eq!(block_on(func()), 246);
+    assert_eq!(block_on(func()), 246);
+}
+
+#[test]
+fn async_closure_awaiting() {
+    let foo = Arc::new(123usize);
+
+    let func = soupa!(async move |x: usize| {
+        let foo = super { foo.clone() };
+        async { *foo + x }.await
+    });
+
+    assert_eq!(block_on(func(1)), 124);
+}
+
+#[test]
+fn let_else() {
+    let foo = Arc::new(Some(123usize));
+
+    let func = soupa!(move || {
+        let Some(value) = *super { foo.clone() } else {
+            return 0;
+        };
+        value * 2
+    });
+
+    assert_eq!(func(), 246);
+}
+
+#[test]
+fn let_else_lifted_scrutinee() {
+    let values = vec![1usize, 2, 3];
+
+    let func = soupa!(async move || {
+        let [first, .., last] = super { values.clone() }[..] else {
+            return 0;
+        };
+        first + last
+    });
+
+    assert_eq!(block_on(func()), 4);
+}
@@ -0,0 +1,86 @@
+//! Checks `super span` markers before async blocks, which require the `tracing`
+//! feature.
+
+use soupa::soupa;
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::{Mutex, Once};
+use std::task::{Context, Poll, Waker};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{info_span, Event, Metadata, Span, Subscriber};
+use tracing_core::span::Current;
+
+thread_local! {
+    /// The spans the current thread has entered.
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Records which span each thread has entered, so the current span is known.
+#[derive(Default)]
+struct Recorder {
+    spans: Mutex<Vec<&'static Metadata<'static>>>,
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(span.metadata());
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, _: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().pop());
+    }
+
+    fn current_span(&self) -> Current {
+        match ENTERED.with(|entered| entered.borrow().last().copied()) {
+            Some(id) => {
+                let metadata = self.spans.lock().unwrap()[id as usize - 1];
+                Current::new(Id::from_u64(id), metadata)
+            }
+            None => Current::none(),
+        }
+    }
+}
+
+/// Installs the [`Recorder`] for all tests.
+fn record() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| tracing::subscriber::set_global_default(Recorder::default()).unwrap());
+}
+
+/// The name of the current span.
+fn current() -> Option<&'static str> {
+    Span::current().metadata().map(Metadata::name)
+}
+
+#[test]
+fn async_block() {
+    record();
+
+    let future = info_span!("outer").in_scope(|| soupa!(super span async move { current() }));
+
+    assert_eq!(current(), None);
+
+    // The future is ready when first polled
+    let mut context = Context::from_waker(Waker::noop());
+    let output = pin!(future).poll(&mut context);
+
+    assert_eq!(output, Poll::Ready(Some("outer")));
+    assert_eq!(current(), None);
+}
@@ -0,0 +1,56 @@
+use soupa::soupa;
+use soupa_edition2021::block_on;
+use std::cell::RefCell;
+use std::sync::Arc;
+
+/// Records its name when dropped.
+struct Named<'a>(&'static str, &'a RefCell<Vec<&'static str>>);
+
+impl Drop for Named<'_> {
+    fn drop(&mut self) {
+        self.1.borrow_mut().push(self.0);
+    }
+}
+
+#[test]
+fn disjoint_captures_unchanged() {
+    let dropped = RefCell::new(Vec::new());
+    let pair = (Named("first", &dropped), Named("second", &dropped));
+
+    // A closure without a super block is left as it was written
+    let func = soupa!(move || {
+        let _ = &pair.0;
+    });
+
+    drop(func);
+
+    assert_eq!(*dropped.borrow(), ["first"]);
+}
+
+#[test]
+fn async_closure() {
+    let foo = Arc::new(123usize);
+
+    let func = soupa!(async move |x: usize| {
+        let foo = super { foo.clone() };
+        async { *foo + x }.await
+    });
+
+    drop(foo);
+
+    assert_eq!(block_on(func(1)), 124);
+}
+
+#[test]
+fn let_else() {
+    let foo = Arc::new(Some(123usize));
+
+    let func = soupa!(async move || {
+        let Some(value) = *super { foo.clone() } else {
+            return 0;
+        };
+        value * 2
+    });
+
+    assert_eq!(block_on(func()), 246);
+}
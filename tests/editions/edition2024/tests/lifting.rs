@@ -0,0 +1,101 @@
+use soupa::soupa;
+use soupa_edition2024::block_on;
+use std::cell::RefCell;
+use std::sync::Arc;
+
+#[test]
+fn if_let_chain() {
+    let foo = Arc::new(Some(123usize));
+
+    // A labelled super block is restored by its label, which is written here,
+    // so the chain keeps this crate's edition
+    let func = soupa!(move |limit: usize| {
+        if let Some(value) = *super(foo) { foo.clone() }
+            && value < limit
+            && let Some(double) = value.checked_mul(2)
+        {
+            double
+        } else {
+            0
+        }
+    });
+
+    assert_eq!(func(200), 246);
+    assert_eq!(func(100), 0);
+}
+
+#[test]
+fn if_let_chain_unchanged() {
+    let foo = Arc::new(Some(123usize));
+
+    // The chain itself contains no super block
+    let func = soupa!(move || {
+        let foo = super { foo.clone() };
+        if let Some(value) = *foo
+            && value > 100
+        {
+            value
+        } else {
+            0
+        }
+    });
+
+    assert_eq!(func(), 123);
+}
+
+#[test]
+fn if_let_chain_async_closure() {
+    let foo = Arc::new(Some(123usize));
+
+    let func = soupa!(async move || {
+        if let Some(value) = *super(foo) { foo.clone() }
+            && let Some(double) = value.checked_mul(2)
+        {
+            double
+        } else {
+            0
+        }
+    });
+
+    drop(foo);
+
+    assert_eq!(block_on(func()), 246);
+}
+
+#[test]
+fn let_else() {
+    let foo = Arc::new(Some(123usize));
+
+    let func = soupa!(move || {
+        let Some(value) = *super { foo.clone() } else {
+            return 0;
+        };
+        value * 2
+    });
+
+    assert_eq!(func(), 246);
+}
+
+/// Records its name when dropped.
+struct Named<'a>(&'static str, &'a RefCell<Vec<&'static str>>);
+
+impl Drop for Named<'_> {
+    fn drop(&mut self) {
+        self.1.borrow_mut().push(self.0);
+    }
+}
+
+#[test]
+fn disjoint_captures_unchanged() {
+    let dropped = RefCell::new(Vec::new());
+    let pair = (Named("first", &dropped), Named("second", &dropped));
+
+    // A closure without a super block is left as it was written
+    let func = soupa!(move || {
+        let _ = &pair.0;
+    });
+
+    drop(func);
+
+    assert_eq!(*dropped.borrow(), ["first"]);
+}
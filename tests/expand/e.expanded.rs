@@ -0,0 +1,32 @@
+#![no_std]
+#[macro_use]
+extern crate soupa;
+use soupa::soupa;
+use std::sync::Arc;
+fn test_body() {
+    let foo = Arc::new(123usize);
+    let func = {
+        #[allow(clippy::redundant_clone)]
+        let __soupa_temp_b = { foo.clone() };
+        let __soupa_temp_c = { foo.clone() };
+        move || {
+            __soupa_temp_b.as_ref();
+            *__soupa_temp_c
+        }
+    };
+    let _ = foo;
+    let x = func();
+    match (&x, &123) {
+        (left_val, right_val) => {
+            if !(*left_val == *right_val) {
+                let kind = ::core::panicking::AssertKind::Eq;
+                ::core::panicking::assert_failed(
+                    kind,
+                    &*left_val,
+                    &*right_val,
+                    ::core::option::Option::None,
+                );
+            }
+        }
+    };
+}
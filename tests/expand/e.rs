@@ -0,0 +1,31 @@
+#[cfg(not(test))]
+#[macro_use]
+extern crate soupa;
+
+use soupa::soupa;
+use std::sync::Arc;
+
+fn test_body() {
+    let foo = Arc::new(123usize);
+
+    let func = soupa!(move || {
+        // Attributes directly before a super block are copied onto its init statement,
+        // and cfg attributes are also kept on the statement it begins
+        #[cfg(any())]
+        super { foo.does_not_exist() }.does_not_exist();
+        #[cfg(all())]
+        #[allow(clippy::redundant_clone)]
+        super { foo.clone() }.as_ref();
+        *super { foo.clone() }
+    });
+
+    let _ = foo;
+
+    let x = func();
+    assert_eq!(x, 123);
+}
+
+#[test]
+fn test() {
+    test_body();
+}
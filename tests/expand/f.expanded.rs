@@ -0,0 +1,32 @@
+#![no_std]
+#[macro_use]
+extern crate soupa;
+use soupa::soupa;
+use std::sync::Arc;
+fn test_body() {
+    let foo = Arc::new(123usize);
+    let func = {
+        let foo_clone = { foo.clone() };
+        let __soupa_temp_a = { foo.clone() };
+        move || {
+            let a = foo_clone;
+            let b = __soupa_temp_a;
+            *a + *b
+        }
+    };
+    let _ = foo;
+    let x = func();
+    match (&x, &246) {
+        (left_val, right_val) => {
+            if !(*left_val == *right_val) {
+                let kind = ::core::panicking::AssertKind::Eq;
+                ::core::panicking::assert_failed(
+                    kind,
+                    &*left_val,
+                    &*right_val,
+                    ::core::option::Option::None,
+                );
+            }
+        }
+    };
+}
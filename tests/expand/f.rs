@@ -0,0 +1,27 @@
+#[cfg(not(test))]
+#[macro_use]
+extern crate soupa;
+
+use soupa::soupa;
+use std::sync::Arc;
+
+fn test_body() {
+    let foo = Arc::new(123usize);
+
+    let func = soupa!(move || {
+        // Labelled super blocks use the label as the name of their temporary
+        let a = super(foo_clone) { foo.clone() };
+        let b = super { foo.clone() };
+        *a + *b
+    });
+
+    let _ = foo;
+
+    let x = func();
+    assert_eq!(x, 246);
+}
+
+#[test]
+fn test() {
+    test_body();
+}
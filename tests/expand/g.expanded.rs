@@ -0,0 +1,30 @@
+#![no_std]
+#[macro_use]
+extern crate soupa;
+use soupa::soupa;
+use std::sync::Arc;
+fn test_body() {
+    let foo = Arc::new(123usize);
+    #[allow(deprecated)]
+    let func = {
+        ::soupa::__private::super_block_outside_closure();
+        let __soupa_temp_a = { foo.clone() };
+        let __soupa_temp_b = { foo.clone() };
+        (*__soupa_temp_a, move || *__soupa_temp_b)
+    };
+    let _ = foo;
+    let x = func.0 + (func.1)();
+    match (&x, &246) {
+        (left_val, right_val) => {
+            if !(*left_val == *right_val) {
+                let kind = ::core::panicking::AssertKind::Eq;
+                ::core::panicking::assert_failed(
+                    kind,
+                    &*left_val,
+                    &*right_val,
+                    ::core::option::Option::None,
+                );
+            }
+        }
+    };
+}
@@ -0,0 +1,27 @@
+#[cfg(not(test))]
+#[macro_use]
+extern crate soupa;
+
+use soupa::soupa;
+use std::sync::Arc;
+
+fn test_body() {
+    let foo = Arc::new(123usize);
+
+    // Strict mode warns about super blocks outside of any closure
+    #[allow(deprecated)]
+    let func = soupa!(@strict (
+        *super { foo.clone() },
+        move || *super { foo.clone() },
+    ));
+
+    let _ = foo;
+
+    let x = func.0 + (func.1)();
+    assert_eq!(x, 246);
+}
+
+#[test]
+fn test() {
+    test_body();
+}
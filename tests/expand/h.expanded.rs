@@ -0,0 +1,42 @@
+#![no_std]
+#[macro_use]
+extern crate soupa;
+use soupa::soupa;
+use std::sync::Arc;
+fn test_body() {
+    let foo = Arc::new(123usize);
+    #[allow(deprecated)]
+    let func = {
+        ::soupa::__private::super_block_in_loop();
+        let __soupa_temp_a = { foo.clone() };
+        let __soupa_temp_b = { foo.clone() };
+        let __soupa_temp_c = { foo.clone() };
+        move || {
+            let mut total = 0;
+            for _ in 0..2 {
+                total += *__soupa_temp_a.clone();
+            }
+            loop {
+                total += *Arc::clone(&__soupa_temp_b);
+                drop(__soupa_temp_c);
+                break;
+            }
+            total
+        }
+    };
+    let _ = foo;
+    let x = func();
+    match (&x, &369) {
+        (left_val, right_val) => {
+            if !(*left_val == *right_val) {
+                let kind = ::core::panicking::AssertKind::Eq;
+                ::core::panicking::assert_failed(
+                    kind,
+                    &*left_val,
+                    &*right_val,
+                    ::core::option::Option::None,
+                );
+            }
+        }
+    };
+}
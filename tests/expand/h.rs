@@ -0,0 +1,37 @@
+#[cfg(not(test))]
+#[macro_use]
+extern crate soupa;
+
+use soupa::soupa;
+use std::sync::Arc;
+
+fn test_body() {
+    let foo = Arc::new(123usize);
+
+    // The warning is emitted ahead of the expansion, so is allowed on the whole invocation
+    #[allow(deprecated)]
+    let func = soupa!(@strict move || {
+        let mut total = 0;
+        // Cloning on each use within a loop doesn't warn
+        for _ in 0..2 {
+            total += *super { foo.clone() }.clone();
+        }
+        // Moving within a loop warns, even if it only runs once
+        loop {
+            total += *Arc::clone(&super { foo.clone() });
+            drop(super { foo.clone() });
+            break;
+        }
+        total
+    });
+
+    let _ = foo;
+
+    let x = func();
+    assert_eq!(x, 369);
+}
+
+#[test]
+fn test() {
+    test_body();
+}
@@ -0,0 +1,39 @@
+#![no_std]
+#[macro_use]
+extern crate soupa;
+use soupa::soupa;
+use std::sync::Arc;
+fn test_body() {
+    let foo = Arc::new(123usize);
+    let func = {
+        let __soupa_temp_a = { foo.clone() };
+        let __soupa_temp_b = { foo.clone() };
+        let __soupa_temp_c = { foo.clone() };
+        move || {
+            let x = 'a: {
+                if *__soupa_temp_a > 100 {
+                    break 'a *__soupa_temp_b;
+                }
+                0
+            };
+            'b: loop {
+                break 'b x + *__soupa_temp_c;
+            }
+        }
+    };
+    let _ = foo;
+    let x = func();
+    match (&x, &246) {
+        (left_val, right_val) => {
+            if !(*left_val == *right_val) {
+                let kind = ::core::panicking::AssertKind::Eq;
+                ::core::panicking::assert_failed(
+                    kind,
+                    &*left_val,
+                    &*right_val,
+                    ::core::option::Option::None,
+                );
+            }
+        }
+    };
+}
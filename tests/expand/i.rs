@@ -0,0 +1,33 @@
+#[cfg(not(test))]
+#[macro_use]
+extern crate soupa;
+
+use soupa::soupa;
+use std::sync::Arc;
+
+fn test_body() {
+    let foo = Arc::new(123usize);
+
+    // Labels on blocks and loops are passed through, and super blocks within them are lifted
+    let func = soupa!(move || {
+        let x = 'a: {
+            if *super { foo.clone() } > 100 {
+                break 'a *super { foo.clone() };
+            }
+            0
+        };
+        'b: loop {
+            break 'b x + *super { foo.clone() };
+        }
+    });
+
+    let _ = foo;
+
+    let x = func();
+    assert_eq!(x, 246);
+}
+
+#[test]
+fn test() {
+    test_body();
+}
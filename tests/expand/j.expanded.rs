@@ -0,0 +1,36 @@
+#![no_std]
+#[macro_use]
+extern crate soupa;
+use soupa::soupa;
+use std::sync::Arc;
+fn make(foo: &Arc<usize>, done: bool) -> impl Fn() -> (usize, bool) {
+    {
+        let __soupa_temp_a = { foo.clone() };
+        let __soupa_temp_b = { foo.clone() };
+        let __soupa_temp_c = { done };
+        move || {
+            let total = 0 + *__soupa_temp_a + *__soupa_temp_b;
+            let pending = !__soupa_temp_c;
+            (total, pending)
+        }
+    }
+}
+fn test_body() {
+    let foo = Arc::new(123usize);
+    let func = make(&foo, false);
+    let _ = foo;
+    let x = func();
+    match (&x, &(246, true)) {
+        (left_val, right_val) => {
+            if !(*left_val == *right_val) {
+                let kind = ::core::panicking::AssertKind::Eq;
+                ::core::panicking::assert_failed(
+                    kind,
+                    &*left_val,
+                    &*right_val,
+                    ::core::option::Option::None,
+                );
+            }
+        }
+    };
+}
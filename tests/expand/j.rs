@@ -0,0 +1,38 @@
+#[cfg(not(test))]
+#[macro_use]
+extern crate soupa;
+
+use soupa::soupa;
+use std::sync::Arc;
+
+macro_rules! sum {
+    ($($value:expr),*) => {
+        0 $(+ $value)*
+    };
+}
+
+// Kept out of test_body, as cargo expand also expands sum!
+fn make(foo: &Arc<usize>, done: bool) -> impl Fn() -> (usize, bool) {
+    // Super blocks within macro calls, such as `vec![...]`, and after `!` are lifted
+    soupa!(move || {
+        let total = sum![*super { foo.clone() }, *super { foo.clone() }];
+        let pending = !super { done };
+        (total, pending)
+    })
+}
+
+fn test_body() {
+    let foo = Arc::new(123usize);
+
+    let func = make(&foo, false);
+
+    let _ = foo;
+
+    let x = func();
+    assert_eq!(x, (246, true));
+}
+
+#[test]
+fn test() {
+    test_body();
+}
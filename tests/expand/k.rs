@@ -0,0 +1,32 @@
+#[cfg(not(test))]
+#[macro_use]
+extern crate soupa;
+
+use soupa::soupa;
+use std::sync::Arc;
+
+fn test_body() {
+    let foo = Arc::new(123usize);
+
+    // Inner attributes, and outer attributes not directly before a super block, are left alone
+    let func = soupa!(move || {
+        #[allow(unused_mut)]
+        let mut x = *super { foo.clone() };
+        fn zero() -> usize {
+            #![allow(unused_variables)]
+            let y = 1;
+            0
+        }
+        x + zero()
+    });
+
+    let _ = foo;
+
+    let x = func();
+    assert_eq!(x, 123);
+}
+
+#[test]
+fn test() {
+    test_body();
+}
@@ -0,0 +1,49 @@
+#![no_std]
+#[macro_use]
+extern crate soupa;
+use std::sync::Arc;
+mod outer {
+    pub struct Wrapper {
+        pub value: usize,
+    }
+    pub fn double(x: usize) -> usize {
+        x * 2
+    }
+    pub mod inner {
+        use soupa::soupa;
+        use std::sync::Arc;
+        pub fn run(foo: &Arc<usize>) -> usize {
+            let func = {
+                let __soupa_temp_a = { foo.clone() };
+                move || {
+                    mod nested {
+                        pub(super) fn value(wrapper: super::super::Wrapper) -> usize {
+                            wrapper.value
+                        }
+                    }
+                    nested::value(super::Wrapper {
+                        value: super::double(*__soupa_temp_a),
+                    })
+                }
+            };
+            func()
+        }
+    }
+}
+fn test_body() {
+    let foo = Arc::new(123usize);
+    let x = outer::inner::run(&foo);
+    match (&x, &246) {
+        (left_val, right_val) => {
+            if !(*left_val == *right_val) {
+                let kind = ::core::panicking::AssertKind::Eq;
+                ::core::panicking::assert_failed(
+                    kind,
+                    &*left_val,
+                    &*right_val,
+                    ::core::option::Option::None,
+                );
+            }
+        }
+    };
+}
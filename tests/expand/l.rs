@@ -0,0 +1,48 @@
+#[cfg(not(test))]
+#[macro_use]
+extern crate soupa;
+
+use std::sync::Arc;
+
+mod outer {
+    pub struct Wrapper {
+        pub value: usize,
+    }
+
+    pub fn double(x: usize) -> usize {
+        x * 2
+    }
+
+    pub mod inner {
+        use soupa::soupa;
+        use std::sync::Arc;
+
+        pub fn run(foo: &Arc<usize>) -> usize {
+            // Paths starting with super, and pub(super), are passed through
+            let func = soupa!(move || {
+                mod nested {
+                    pub(super) fn value(wrapper: super::super::Wrapper) -> usize {
+                        wrapper.value
+                    }
+                }
+                nested::value(super::Wrapper {
+                    value: super::double(*super { foo.clone() }),
+                })
+            });
+
+            func()
+        }
+    }
+}
+
+fn test_body() {
+    let foo = Arc::new(123usize);
+
+    let x = outer::inner::run(&foo);
+    assert_eq!(x, 246);
+}
+
+#[test]
+fn test() {
+    test_body();
+}
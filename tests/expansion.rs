@@ -6,6 +6,14 @@ mod expand {
     mod b;
     mod c;
     mod d;
+    mod e;
+    mod f;
+    mod g;
+    mod h;
+    mod i;
+    mod j;
+    mod k;
+    mod l;
 }
 
 #[test]
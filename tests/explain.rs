@@ -0,0 +1,153 @@
+#[macro_use]
+extern crate soupa;
+
+/// Removes whitespace, which `stringify!` makes no guarantees about.
+fn normalize(explained: &str) -> String {
+    explained.split_whitespace().collect()
+}
+
+#[test]
+fn no_super_blocks() {
+    assert_eq!(normalize(explain!(move || foo)), "{move||foo}");
+}
+
+#[test]
+fn super_blocks_in_order() {
+    assert_eq!(
+        normalize(explain!(move || (super { a() }, [super { b() }], { super { c() } }))),
+        "{let__soupa_temp_a={a()};let__soupa_temp_b={b()};let__soupa_temp_c={c()};\
+         move||(__soupa_temp_a,[__soupa_temp_b],{__soupa_temp_c})}"
+    );
+}
+
+#[test]
+fn labels_and_attributes() {
+    assert_eq!(
+        normalize(explain!(move || {
+            #[cfg(unix)]
+            super(a) { a() }.b();
+        })),
+        "{#[cfg(unix)]leta={a()};move||{#[cfg(unix)]a.b();}}"
+    );
+}
+
+#[test]
+fn lint_attributes() {
+    // Only the cfg attributes of a statement are kept in place, as attributes
+    // on other expressions are unstable
+    assert_eq!(
+        normalize(explain!(move || {
+            let x = #[allow(unused)] #[cfg(unix)] super { a() };
+            let y = #[allow(unused)] super(b) { b() };
+            #[allow(unused)] #[cfg(unix)] super { c() }.d();
+            (#[cfg(unix)] super { e() }, y)
+        })),
+        "{#[allow(unused)]#[cfg(unix)]let__soupa_temp_a={a()};#[allow(unused)]letb={b()};\
+         #[allow(unused)]#[cfg(unix)]let__soupa_temp_b={c()};#[cfg(unix)]let__soupa_temp_c={e()};\
+         move||{letx=__soupa_temp_a;lety=b;#[cfg(unix)]__soupa_temp_b.d();(__soupa_temp_c,y)}}"
+    );
+
+    let foo = String::from("foo");
+
+    let func = soupa!(move || {
+        let x = #[allow(unused)] super { foo.clone() };
+        x
+    });
+
+    assert_eq!(func(), "foo");
+}
+
+#[test]
+fn lift_markers() {
+    assert_eq!(
+        normalize(explain!(move || (
+            lift!(a()),
+            soupa::lift![b()],
+            ::soupa::lift! { c() }
+        ))),
+        "{let__soupa_temp_a={a()};let__soupa_temp_b={b()};let__soupa_temp_c={c()};\
+         move||(__soupa_temp_a,__soupa_temp_b,__soupa_temp_c)}"
+    );
+}
+
+#[test]
+fn paths_ending_in_lift() {
+    // Only lift! itself is a marker, rather than any macro of the same name
+    assert_eq!(
+        normalize(explain!(move || (other::lift!(a()), other::soupa::lift!(b()), ::lift!(c())))),
+        "{let__soupa_temp_a={c()};move||(other::lift!(a()),other::soupa::lift!(b()),__soupa_temp_a)}"
+    );
+}
+
+#[test]
+fn strict_mode() {
+    // Lints are not part of the explanation
+    assert_eq!(
+        normalize(explain!(@strict (super { a() }, async move { super { b() } }))),
+        "{let__soupa_temp_a={a()};let__soupa_temp_b={b()};\
+         (__soupa_temp_a,asyncmove{__soupa_temp_b})}"
+    );
+}
+
+#[test]
+fn loops() {
+    // Lints are not part of the explanation
+    assert_eq!(
+        normalize(explain!(move || for _ in 0..3 { work(super { tx.clone() }) })),
+        "{let__soupa_temp_a={tx.clone()};move||for_in0..3{work(__soupa_temp_a)}}"
+    );
+}
+
+#[test]
+fn ordered_super_blocks() {
+    assert_eq!(
+        normalize(explain!(move || (super(b, after = a) { b() }, super(a) { a() }))),
+        "{leta={a()};letb={b()};move||(b,a)}"
+    );
+}
+
+#[test]
+fn assertions() {
+    // Checks are not part of the explanation
+    assert_eq!(
+        normalize(explain!(@send @strict @static move || super(a) { a() })),
+        "{leta={a()};move||a}"
+    );
+}
+
+#[test]
+fn shared_super_blocks() {
+    assert_eq!(
+        normalize(explain!((move || super share(a) { a() }, move || super share(a)))),
+        "{leta={a()};let__soupa_temp_a={a.clone()};let__soupa_temp_b={a};\
+         (move||__soupa_temp_a,move||__soupa_temp_b)}"
+    );
+}
+
+#[test]
+fn deduplication() {
+    assert_eq!(
+        normalize(explain!(@dedup (move || super { a() }, move || (super { b() }, super { a() })))),
+        "{let__soupa_temp_a={a()};let__soupa_temp_b={b()};let__soupa_temp_c={__soupa_temp_a.clone()};\
+         (move||__soupa_temp_a,move||(__soupa_temp_b,__soupa_temp_c))}"
+    );
+}
+
+#[test]
+fn boxed() {
+    assert_eq!(
+        normalize(explain!(@boxed(Fn() + Send) move || super { a() })),
+        "{let__soupa_boxed:$crate::__private::Box<dynFn()+Send>=\
+         $crate::__private::Box::new({let__soupa_temp_a={a()};move||__soupa_temp_a});__soupa_boxed}"
+    );
+}
+
+#[test]
+fn lazy() {
+    // Branches are explained as the invocations they are expanded by
+    assert_eq!(
+        normalize(explain!(@lazy if c { move || super { a() } } else { move || super { b() } })),
+        "{ifc{$crate::soupa!(@branch_start{explainlazy}{}{move||super{a()}})}\
+         else{$crate::soupa!(@branch_start{explainlazy}{}{move||super{b()}})}}"
+    );
+}
@@ -0,0 +1,110 @@
+//! Checks struct literals whose fields are closures, which are lifted from as
+//! a whole.
+
+#[macro_use]
+extern crate soupa;
+
+use std::cell::Cell;
+
+/// Records an evaluation, returning `value`.
+fn record<T>(evaluations: &Cell<usize>, value: T) -> T {
+    evaluations.set(evaluations.get() + 1);
+    value
+}
+
+/// Counts how many times it has been cloned.
+struct Counted<'a>(&'a Cell<usize>);
+
+impl Clone for Counted<'_> {
+    fn clone(&self) -> Self {
+        self.0.set(self.0.get() + 1);
+        Counted(self.0)
+    }
+}
+
+struct Handlers<O, C> {
+    on_open: O,
+    on_close: C,
+}
+
+impl<O: Fn() -> usize, C: Fn(usize) -> usize> Handlers<O, C> {
+    /// Requires both handlers to be `Fn`, as a table of handlers would.
+    fn new(handlers: Self) -> Self {
+        handlers
+    }
+}
+
+#[test]
+fn fields_lifted_together() {
+    let evaluations = Cell::new(0);
+
+    let handlers = Handlers::new(soupa!(Handlers {
+        on_open: move || super { record(&evaluations, 1usize) },
+        on_close: move |code| code + super { record(&evaluations, 2usize) },
+    }));
+
+    // Each is evaluated once, when the struct is built
+    assert_eq!(evaluations.get(), 2);
+
+    assert_eq!((handlers.on_open)(), 1);
+    assert_eq!((handlers.on_close)(1), 3);
+    assert_eq!((handlers.on_close)(2), 4);
+    assert_eq!(evaluations.get(), 2);
+}
+
+#[test]
+fn shared_between_fields() {
+    let clones = Cell::new(0);
+
+    let handlers = soupa!(Handlers {
+        on_open: move || {
+            let _counted = super share(counted) { Counted(&clones) };
+        },
+        on_close: move || {
+            let _counted = super share(counted);
+        },
+    });
+
+    (handlers.on_open)();
+    (handlers.on_close)();
+
+    // The last field takes the value itself
+    assert_eq!(clones.get(), 1);
+}
+
+#[test]
+fn clone_within_fn_field() {
+    let name = String::from("name");
+
+    let handlers = Handlers::new(soupa!(Handlers {
+        on_open: move || super(owned) { name.clone() }.clone().len(),
+        on_close: move |code| code + super(len) { name.len() },
+    }));
+
+    assert_eq!((handlers.on_open)(), 4);
+    assert_eq!((handlers.on_open)(), 4);
+    assert_eq!((handlers.on_close)(1), 5);
+}
+
+#[test]
+fn nested_struct() {
+    struct Table<O, C, E> {
+        handlers: Handlers<O, C>,
+        on_error: E,
+    }
+
+    let evaluations = Cell::new(0);
+
+    let table = soupa!(Table {
+        handlers: Handlers {
+            on_open: move || super { record(&evaluations, 1usize) },
+            on_close: move |code: usize| code,
+        },
+        on_error: move || super { record(&evaluations, 2usize) },
+    });
+
+    assert_eq!(evaluations.get(), 2);
+    assert_eq!((table.handlers.on_open)(), 1);
+    assert_eq!((table.handlers.on_close)(3), 3);
+    assert_eq!((table.on_error)(), 2);
+}
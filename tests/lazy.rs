@@ -0,0 +1,183 @@
+#[macro_use]
+extern crate soupa;
+
+use std::cell::Cell;
+
+/// Records an evaluation, returning `value`.
+fn record<T>(evaluations: &Cell<usize>, value: T) -> T {
+    evaluations.set(evaluations.get() + 1);
+    value
+}
+
+/// Builds one of two closures, recording the evaluations each lifts.
+fn choose<'a>(
+    condition: bool,
+    a: &'a Cell<usize>,
+    b: &'a Cell<usize>,
+) -> Box<dyn Fn() -> usize + 'a> {
+    soupa!(@lazy if condition {
+        Box::new(move || super { record(a, 1usize) })
+    } else {
+        Box::new(move || super { record(b, 2usize) })
+    })
+}
+
+#[test]
+fn only_taken_branch() {
+    let (a, b) = (Cell::new(0), Cell::new(0));
+
+    let func = choose(true, &a, &b);
+
+    assert_eq!((a.get(), b.get()), (1, 0));
+    assert_eq!(func(), 1);
+
+    let func = choose(false, &a, &b);
+
+    assert_eq!((a.get(), b.get()), (1, 1));
+    assert_eq!(func(), 2);
+}
+
+#[test]
+fn without_option() {
+    let (a, b) = (Cell::new(0), Cell::new(0));
+
+    // Every super block is lifted to the start of the invocation
+    let func: Box<dyn Fn() -> usize> = soupa!(if true {
+        Box::new(move || super { record(&a, 1usize) })
+    } else {
+        Box::new(move || super { record(&b, 2usize) })
+    });
+
+    assert_eq!((a.get(), b.get()), (1, 1));
+    assert_eq!(func(), 1);
+}
+
+#[test]
+fn else_if() {
+    let evaluations = [Cell::new(0), Cell::new(0), Cell::new(0)];
+    let [a, b, c] = &evaluations;
+
+    let func: Box<dyn Fn() -> usize> = soupa!(@lazy if false {
+        Box::new(move || super { record(a, 1usize) })
+    } else if true {
+        Box::new(move || super { record(b, 2usize) })
+    } else {
+        Box::new(move || super { record(c, 3usize) })
+    });
+
+    assert_eq!(evaluations.each_ref().map(Cell::get), [0, 1, 0]);
+    assert_eq!(func(), 2);
+}
+
+#[test]
+fn match_arms() {
+    let evaluations = [Cell::new(0), Cell::new(0), Cell::new(0)];
+    let [a, b, c] = &evaluations;
+
+    let func: Box<dyn Fn() -> usize> = soupa!(@lazy match Some(2) {
+        Some(1) => {
+            Box::new(move || super { record(a, 1usize) })
+        }
+        Some(_) => {
+            Box::new(move || super { record(b, 2usize) })
+        }
+        // An arm without a block is lifted out of along with the match
+        None => Box::new(move || super { record(c, 3usize) }),
+    });
+
+    assert_eq!(evaluations.each_ref().map(Cell::get), [0, 1, 1]);
+    assert_eq!(func(), 2);
+}
+
+#[test]
+fn patterns_in_condition() {
+    struct Pair {
+        left: usize,
+        right: usize,
+    }
+
+    let (a, b) = (Cell::new(0), Cell::new(0));
+
+    // The braces of the pattern aren't mistaken for the block
+    let func: Box<dyn Fn() -> usize> = soupa!(@lazy if let Pair { left, right: 2 } = (Pair { left: 1, right: 2 }) {
+        Box::new(move || left + super { record(&a, 1usize) })
+    } else {
+        Box::new(move || super { record(&b, 2usize) })
+    });
+
+    assert_eq!((a.get(), b.get()), (1, 0));
+    assert_eq!(func(), 2);
+}
+
+#[test]
+fn nested_branches() {
+    let evaluations = [Cell::new(0), Cell::new(0), Cell::new(0)];
+    let [a, b, c] = &evaluations;
+
+    let func: Box<dyn Fn() -> usize> = soupa!(@lazy if true {
+        let outer = super { record(a, 1usize) };
+        if false {
+            Box::new(move || outer + super { record(b, 2usize) })
+        } else {
+            Box::new(move || outer + super { record(c, 3usize) })
+        }
+    } else {
+        Box::new(|| 0)
+    });
+
+    assert_eq!(evaluations.each_ref().map(Cell::get), [1, 0, 1]);
+    assert_eq!(func(), 4);
+}
+
+#[test]
+fn branches_within_closures() {
+    let (a, b) = (Cell::new(0), Cell::new(0));
+
+    // A branch within a closure is evaluated each call, so its super blocks are still
+    // lifted to the start of the invocation
+    let func = soupa!(@lazy move |condition: bool| if condition {
+        super { record(&a, 1usize) }
+    } else {
+        super { record(&b, 2usize) }
+    });
+
+    assert_eq!((a.get(), b.get()), (1, 1));
+    assert_eq!((func(true), func(false)), (1, 2));
+}
+
+#[test]
+fn branches_after_turbofish() {
+    fn zero<A: Default + Into<usize>, B: Default + Into<usize>>() -> usize {
+        A::default().into() + B::default().into()
+    }
+
+    let evaluations = Cell::new(0);
+
+    // The `,` between generic arguments doesn't end the closure, so the branch is within it
+    let func = soupa!(@lazy move || zero::<u8, u16>() + if true {
+        super { record(&evaluations, 1usize) }
+    } else {
+        0
+    });
+
+    assert_eq!(evaluations.get(), 1);
+    assert_eq!((func(), func(), func()), (1, 1, 1));
+    assert_eq!(evaluations.get(), 1);
+}
+
+#[test]
+fn options_within_branches() {
+    let evaluations = Cell::new(0);
+
+    let (left, right) = soupa!(@lazy @dedup if true {
+        (
+            move || super { record(&evaluations, 1usize) },
+            move || super { record(&evaluations, 1usize) },
+        )
+    } else {
+        unreachable!()
+    });
+
+    assert_eq!(evaluations.get(), 1);
+    assert_eq!((left(), right()), (1, 1));
+}
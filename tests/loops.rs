@@ -0,0 +1,51 @@
+#![deny(deprecated)]
+
+#[macro_use]
+extern crate soupa;
+
+use std::sync::Arc;
+
+#[test]
+fn without_option() {
+    let n = 3usize;
+
+    // Moving a copy within a loop is fine, so no warning is emitted by default
+    let func = soupa!(move || {
+        let mut total = 0;
+        for _ in 0..3 {
+            total += super { n };
+        }
+        total
+    });
+
+    assert_eq!(func(), 9);
+}
+
+#[test]
+fn used_without_moving() {
+    let foo = Arc::new(vec![1usize, 2, 3]);
+
+    let func = soupa!(@strict move || {
+        let mut total = 0;
+        for i in 0..3 {
+            total += super { foo.clone() }.len();
+            total += super { foo.clone() }[i];
+            total += Arc::strong_count(&super { foo.clone() });
+        }
+        total
+    });
+
+    assert_eq!(func(), 9 + 6 + 3 * 4);
+}
+
+#[test]
+fn outside_loop() {
+    let foo = Arc::new(123usize);
+
+    let func = soupa!(@strict move || {
+        for _ in 0..3 {}
+        drop(super { foo.clone() });
+    });
+
+    func();
+}
@@ -0,0 +1,95 @@
+//! Checks the order lifted expressions are evaluated in, by recording their
+//! side effects.
+
+#[macro_use]
+extern crate soupa;
+
+use std::cell::RefCell;
+
+/// Records `name` as having been evaluated, returning it.
+fn record(log: &RefCell<Vec<&'static str>>, name: &'static str) -> &'static str {
+    log.borrow_mut().push(name);
+    name
+}
+
+#[test]
+fn depth_first_left_to_right() {
+    let log = RefCell::new(Vec::new());
+
+    let func = soupa!(move || {
+        (
+            super { record(&log, "a") },
+            [super { record(&log, "b") }, { super { record(&log, "c") } }],
+            super { record(&log, "d") },
+        )
+    });
+
+    record(&log, "created");
+    let _ = func();
+
+    assert_eq!(*log.borrow(), ["a", "b", "c", "d", "created"]);
+}
+
+#[test]
+fn nested_invocations() {
+    let log = RefCell::new(Vec::new());
+
+    let func = soupa!(move || {
+        let inner = soupa!(move || super { record(&log, "b") });
+        (super { record(&log, "a") }, inner())
+    });
+
+    record(&log, "created");
+    let _ = func();
+
+    // Nested invocations are lifted to the outermost scope, in the order written
+    assert_eq!(*log.borrow(), ["b", "a", "created"]);
+}
+
+#[test]
+fn labels_attributes_and_markers() {
+    let log = RefCell::new(Vec::new());
+
+    let func = soupa!(move || {
+        (
+            super(a) { record(&log, "a") },
+            #[cfg(all())]
+            super { record(&log, "b") },
+            lift!(record(&log, "c")),
+        )
+    });
+
+    let _ = func();
+
+    assert_eq!(*log.borrow(), ["a", "b", "c"]);
+}
+
+#[test]
+fn ordered_super_blocks() {
+    let log = RefCell::new(Vec::new());
+
+    let func = soupa!(move || {
+        (
+            super(read, after = lock) { record(&log, "read") },
+            super { record(&log, "other") },
+            super(lock) { record(&log, "lock") },
+            super(release, after = read) { record(&log, "release") },
+        )
+    });
+
+    let _ = func();
+
+    assert_eq!(*log.borrow(), ["other", "lock", "read", "release"]);
+}
+
+#[test]
+fn labels_in_scope_of_later_super_blocks() {
+    let x = 10;
+
+    // The label shadows `x` within the super blocks evaluated after it
+    let func = soupa!(move || super(x) { 1 } + super { x + 1 });
+    assert_eq!(func(), 3);
+
+    let func = soupa!(move || super { x + 1 } + super(x) { 1 });
+    assert_eq!(func(), 12);
+}
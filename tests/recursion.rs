@@ -0,0 +1,20 @@
+//! Checks that long invocations still fit within the default recursion limit,
+//! as each token is peeled off in a single step.
+
+#[macro_use]
+extern crate soupa;
+
+#[test]
+#[rustfmt::skip]
+fn long_expression_in_loop() {
+    let x = 1usize;
+
+    let func = soupa!(move || for _ in 0..1 {
+        let _ = x + x + x + x + x + x + x + x + x + x + x + x + x + x + x +
+            x + x + x + x + x + x + x + x + x + x + x + x + x + x + x +
+            x + x + x + x + x + x + x + x + x + x + x + x + x + x + x +
+            super { x };
+    });
+
+    func();
+}
@@ -0,0 +1,74 @@
+#[macro_use]
+extern crate soupa;
+
+use std::cell::Cell;
+
+/// Counts how many times it has been cloned.
+struct Counted<'a>(&'a Cell<usize>);
+
+impl Clone for Counted<'_> {
+    fn clone(&self) -> Self {
+        self.0.set(self.0.get() + 1);
+        Counted(self.0)
+    }
+}
+
+#[test]
+fn each_use_gets_a_clone() {
+    let clones = Cell::new(0);
+    let evaluated = Cell::new(0);
+
+    let (a, b, c) = soupa!((
+        move || {
+            let _counted = super share(counted) {
+                evaluated.set(evaluated.get() + 1);
+                Counted(&clones)
+            };
+        },
+        move || {
+            let _counted = super share(counted);
+        },
+        move || {
+            let _counted = super share(counted);
+        },
+    ));
+
+    a();
+    b();
+    c();
+
+    // The last use takes the value itself
+    assert_eq!(evaluated.get(), 1);
+    assert_eq!(clones.get(), 2);
+}
+
+#[test]
+fn single_use() {
+    struct NotClone;
+
+    // A single use takes the value, so it doesn't need to be cloneable
+    let func = soupa!(move || super share(value) { NotClone });
+
+    let NotClone = func();
+}
+
+#[test]
+fn surrounding_variable() {
+    let name = String::from("name");
+
+    let (a, b) = soupa!((move || super share(name), move || super share(name)));
+
+    assert_eq!(a(), "name");
+    assert_eq!(b(), "name");
+}
+
+#[test]
+fn several_names() {
+    let (a, b) = soupa!((
+        move || (super share(x) { 1usize }, super share(y) { 2usize }),
+        move || (super share(y), super share(x)),
+    ));
+
+    assert_eq!(a(), (1, 2));
+    assert_eq!(b(), (2, 1));
+}
@@ -0,0 +1,115 @@
+//! Checks `super span` markers, which require the `tracing` feature.
+//!
+//! This crate is edition 2015, so only closures are checked here.
+
+#![cfg(feature = "tracing")]
+
+#[macro_use]
+extern crate soupa;
+#[macro_use]
+extern crate tracing;
+extern crate tracing_core;
+
+use std::cell::RefCell;
+use std::sync::{Mutex, Once};
+
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Span, Subscriber};
+use tracing_core::span::Current;
+
+thread_local! {
+    /// The spans the current thread has entered.
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Records which span each thread has entered, so the current span is known.
+#[derive(Default)]
+struct Recorder {
+    spans: Mutex<Vec<&'static Metadata<'static>>>,
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(span.metadata());
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, _: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().pop());
+    }
+
+    fn current_span(&self) -> Current {
+        match ENTERED.with(|entered| entered.borrow().last().copied()) {
+            Some(id) => {
+                let metadata = self.spans.lock().unwrap()[id as usize - 1];
+                Current::new(Id::from_u64(id), metadata)
+            }
+            None => Current::none(),
+        }
+    }
+}
+
+/// Installs the [`Recorder`] for all tests.
+fn record() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| tracing::subscriber::set_global_default(Recorder::default()).unwrap());
+}
+
+/// The name of the current span.
+fn current() -> Option<&'static str> {
+    Span::current().metadata().map(Metadata::name)
+}
+
+#[test]
+fn current_span() {
+    record();
+
+    let func = info_span!("outer").in_scope(|| soupa!(super span move || { current() }));
+
+    assert_eq!(current(), None);
+    assert_eq!(func(), Some("outer"));
+
+    // The span is only entered while the closure runs
+    assert_eq!(current(), None);
+}
+
+#[test]
+fn given_span() {
+    record();
+
+    let func = soupa!(super span(info_span!("job")) move |offset: usize| -> usize {
+        assert_eq!(current(), Some("job"));
+        offset + 1
+    });
+
+    assert_eq!(current(), None);
+    assert_eq!(func(1), 2);
+}
+
+#[test]
+fn super_blocks_within() {
+    record();
+
+    let foo = String::from("foo");
+
+    let func = soupa!(super span(info_span!("job")) move || {
+        (current(), super { foo.clone() })
+    });
+
+    assert_eq!(func(), (Some("job"), foo));
+}
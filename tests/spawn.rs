@@ -0,0 +1,153 @@
+//! Checks spawning futures with `spawn!`, using a minimal local executor as well
+//! as the executors supported by the `tokio`, `async-std` and `smol` features.
+//!
+//! This crate is edition 2015, so futures are created with [`lazy`] rather than
+//! `async` blocks.
+
+#[macro_use]
+extern crate soupa;
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+/// Wakes a thread blocked on a future.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs a future to completion on the current thread.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// A future which calls a function when first polled.
+struct Lazy<F>(Option<F>);
+
+impl<F> Unpin for Lazy<F> {}
+
+impl<F: FnOnce() -> T, T> Future for Lazy<F> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<T> {
+        Poll::Ready(self.0.take().expect("polled after completion")())
+    }
+}
+
+/// Creates a future which calls `func` when first polled.
+fn lazy<F: FnOnce() -> T, T>(func: F) -> Lazy<F> {
+    Lazy(Some(func))
+}
+
+/// Tasks spawned onto the local executor, which are only run when requested.
+type Queue = RefCell<Vec<Pin<Box<dyn Future<Output = ()>>>>>;
+
+/// Runs every spawned task to completion.
+fn run(queue: &Queue) {
+    for task in queue.borrow_mut().drain(..) {
+        block_on(task);
+    }
+}
+
+#[test]
+fn lifted_before_future_is_created() {
+    let queue = Queue::default();
+    let log = Rc::new(RefCell::new(Vec::new()));
+
+    spawn!(
+        |task| queue.borrow_mut().push(Box::pin(task)),
+        lazy(move || {
+            let log = super(log) { log.clone() };
+            log.borrow_mut().push("task");
+        })
+    );
+
+    // The super block has already been evaluated, so log has two owners
+    assert_eq!(Rc::strong_count(&log), 2);
+    log.borrow_mut().push("spawned");
+
+    run(&queue);
+
+    assert_eq!(*log.borrow(), ["spawned", "task"]);
+    assert_eq!(Rc::strong_count(&log), 1);
+}
+
+#[test]
+fn spawner_output_is_returned() {
+    let state = Arc::new(123usize);
+
+    let output = spawn!(block_on, lazy(move || *super { state.clone() } + 1));
+
+    assert_eq!(output, 124);
+    assert_eq!(*state, 123);
+}
+
+#[test]
+fn options() {
+    let state = Arc::new(123usize);
+
+    let output = spawn!(block_on, @strict lazy(move || *super { state.clone() }));
+
+    assert_eq!(output, 123);
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn tokio() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let state = Arc::new(123usize);
+
+    let handle = {
+        // tokio only spawns tasks within the context of a runtime
+        let _guard = runtime.enter();
+        spawn!(soupa::task::tokio, lazy(move || *super { state.clone() }))
+    };
+    let output = runtime.block_on(handle).unwrap();
+
+    assert_eq!(output, 123);
+    assert_eq!(*state, 123);
+}
+
+#[cfg(feature = "async-std")]
+#[test]
+fn async_std() {
+    let state = Arc::new(123usize);
+
+    let task = spawn!(soupa::task::async_std, lazy(move || *super { state.clone() }));
+    let output = async_std::task::block_on(task);
+
+    assert_eq!(output, 123);
+    assert_eq!(*state, 123);
+}
+
+#[cfg(feature = "smol")]
+#[test]
+fn smol() {
+    let state = Arc::new(123usize);
+
+    let task = spawn!(soupa::task::smol, lazy(move || *super { state.clone() }));
+    let output = smol::block_on(task);
+
+    assert_eq!(output, 123);
+    assert_eq!(*state, 123);
+}
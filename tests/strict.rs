@@ -0,0 +1,24 @@
+#![deny(deprecated)]
+
+#[macro_use]
+extern crate soupa;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[test]
+fn within_closure_after_turbofish() {
+    let foo = Arc::new(123usize);
+
+    // The `,` between generic arguments doesn't end the closure, so no warning is emitted
+    let collect = soupa!(@strict move || (0..2)
+        .map(|i| (i, i))
+        .collect::<HashMap<usize, usize>>()
+        .len()
+        + *super { foo.clone() });
+    let nested =
+        soupa!(@strict move || Vec::<Option<(u8, u8)>>::new().len() + *super { foo.clone() });
+
+    assert_eq!(collect(), 125);
+    assert_eq!(nested(), 123);
+}
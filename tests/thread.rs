@@ -0,0 +1,77 @@
+//! Checks the thread spawning macros, which require the `std` feature.
+
+#![cfg(feature = "std")]
+
+extern crate soupa;
+
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn spawn() {
+    let foo = Arc::new(123usize);
+
+    let handle = soupa::thread::spawn!(move || *super { foo.clone() } + 1);
+
+    // foo was not moved into the thread
+    assert_eq!(*foo, 123);
+    assert_eq!(handle.join().unwrap(), 124);
+}
+
+#[test]
+fn lifted_before_spawning() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+
+    let handle = soupa::thread::spawn!(move || {
+        let log = super(log) { log.clone() };
+        log.lock().unwrap().push("thread");
+    });
+
+    // The super block has already been evaluated, so log has two owners
+    assert_eq!(Arc::strong_count(&log), 2);
+
+    handle.join().unwrap();
+    log.lock().unwrap().push("joined");
+
+    assert_eq!(*log.lock().unwrap(), ["thread", "joined"]);
+}
+
+#[test]
+fn builder_options() {
+    let foo = Arc::new(123usize);
+
+    let handle = soupa::thread::spawn!(
+        name = String::from("worker"),
+        stack_size = 256 * 1024,
+        move || (
+            std::thread::current().name().map(String::from),
+            *super { foo.clone() },
+        )
+    );
+
+    assert_eq!(handle.thread().name(), Some("worker"));
+    assert_eq!(handle.join().unwrap(), (Some(String::from("worker")), 123));
+
+    let handle = soupa::thread::spawn!(stack_size = 256 * 1024, move || *super { foo.clone() });
+
+    assert_eq!(handle.thread().name(), None);
+    assert_eq!(handle.join().unwrap(), 123);
+}
+
+#[test]
+fn spawn_named() {
+    let foo = Arc::new(123usize);
+
+    let handle = soupa::thread::spawn_named!("worker", move || *super { foo.clone() });
+
+    assert_eq!(handle.thread().name(), Some("worker"));
+    assert_eq!(handle.join().unwrap(), 123);
+}
+
+#[test]
+fn options() {
+    let foo = Arc::new(123usize);
+
+    let handle = soupa::thread::spawn!(@strict move || *super(foo) { foo.clone() });
+
+    assert_eq!(handle.join().unwrap(), 123);
+}
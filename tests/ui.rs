@@ -0,0 +1,4 @@
+#[test]
+pub fn compile_fail() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
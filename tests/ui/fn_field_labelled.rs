@@ -0,0 +1,15 @@
+#[macro_use]
+extern crate soupa;
+
+struct Handlers<F: Fn()> {
+    on_close: F,
+}
+
+fn main() {
+    let name = String::from("name");
+
+    // The move is reported where the labelled value is moved
+    let _handlers = soupa!(Handlers {
+        on_close: move || drop(super(owned) { name.clone() }),
+    });
+}
@@ -0,0 +1,15 @@
+#[macro_use]
+extern crate soupa;
+
+struct Handlers<F: Fn()> {
+    on_close: F,
+}
+
+fn main() {
+    let name = String::from("name");
+
+    // Without a label, the move is reported at the whole invocation
+    let _handlers = soupa!(Handlers {
+        on_close: move || drop(super { name.clone() }),
+    });
+}